@@ -116,6 +116,13 @@ export interface LintError {
    * The error message.
    */
   error: string;
+
+  /**
+   * The stable code of the error, e.g. `TOML001`.
+   *
+   * It can be used to suppress the error with `#:taplo-ignore` directives.
+   */
+  code: string;
 }
 
 /**
@@ -136,6 +136,13 @@ export interface Handlers {
      * The timestamp is in UNIX milliseconds.
      */
     needsUpdate: (path: string, newTimestamp: number) => boolean;
+    /**
+     * Return the paths of the files matching the glob pattern.
+     *
+     * Only required for indexing the TOML files in the workspace,
+     * otherwise only the open documents are used.
+     */
+    globPaths?: (pattern: string) => string[];
 }
 export declare class TaploLsp {
     private static lsp;
@@ -153,6 +153,13 @@ export interface Handlers {
    * The timestamp is in UNIX milliseconds.
    */
   needsUpdate: (path: string, newTimestamp: number) => boolean;
+  /**
+   * Return the paths of the files matching the glob pattern.
+   *
+   * Only required for indexing the TOML files in the workspace,
+   * otherwise only the open documents are used.
+   */
+  globPaths?: (pattern: string) => string[];
 }
 
 export class TaploLsp {
@@ -185,6 +192,7 @@ export class TaploLsp {
       (global as any).fileExists = handlers.fileExists;
       (global as any).mkdir = handlers.mkdir;
       (global as any).needsUpdate = handlers.needsUpdate;
+      (global as any).globPaths = handlers.globPaths;
 
       TaploLsp.lsp = await loadTaplo();
       TaploLsp.lsp.initialize();
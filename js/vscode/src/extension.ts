@@ -31,7 +31,7 @@ export async function activate(context: vscode.ExtensionContext) {
     synchronize: {
       configurationSection: "evenBetterToml",
       fileEvents: [
-        vscode.workspace.createFileSystemWatcher("**/.toml"),
+        vscode.workspace.createFileSystemWatcher("**/*.toml"),
         vscode.workspace.createFileSystemWatcher("**/Cargo.lock"),
       ],
     },
@@ -10,6 +10,7 @@ import * as fs from "fs";
 import * as path from "path";
 import { exit } from "process";
 import { TaploLsp } from "@taplo/lsp";
+import fastGlob from "fast-glob";
 import fetch, { Headers, Request, Response } from "node-fetch";
 
 // For reqwest
@@ -51,6 +52,12 @@ process.on("message", async d => {
       fileExists: (p: string): boolean => {
         return fs.existsSync(p);
       },
+      globPaths: (p: string): string[] => {
+        return fastGlob.sync(p, {
+          dot: true,
+          caseSensitiveMatch: process.platform !== "win32",
+        });
+      },
       mkdir: (p: string) => {
         fs.mkdirSync(p, { recursive: true });
       },
@@ -1,4 +1,4 @@
 #!/bin/sh
-cargo run --manifest-path ./util/schema-index/Cargo.toml -- -o site/static/schema_index.json --url https://taplo.tamasfe.dev/schemas --schema-store ./schemas || exit 1
+cargo run --manifest-path ./util/schema-index/Cargo.toml -- -o site/static/schema_index.json --url https://taplo.tamasfe.dev/schemas --schema-store ./taplo/schemas || exit 1
 rm -rf site/static/schemas || exit 1
-cp -r schemas site/static || exit 1
\ No newline at end of file
+cp -r taplo/schemas site/static || exit 1
\ No newline at end of file
@@ -1,24 +1,43 @@
 use anyhow::anyhow;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use taplo::formatter;
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+};
+use taplo::{formatter, schema::BUILTIN_SCHEME};
 
-use crate::external::glob_match_options;
+use crate::external::{glob_match_options, is_absolute_path};
 
 pub const CONFIG_FILE_NAMES: &[&str] = &[".taplo.toml", "taplo.toml"];
 
+/// The builtin configuration with the default options.
+pub const DEFAULT_CONFIG: &str = "taplo://default";
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Other configurations this one inherits from.
+    ///
+    /// Either paths of configuration files relative to this one,
+    /// or `taplo://default` for the default options.
+    ///
+    /// The configurations are merged in order, later ones and this configuration
+    /// take precedence: `include` and `exclude` are replaced, rules are appended,
+    /// and the rest of the options are overridden one by one.
+    pub extends: Option<Vec<String>>,
+
     /// Files to include.
     ///
     /// A list of Unix-like [glob](https://en.wikipedia.org/wiki/Glob_(programming)) path patterns.
     /// Globstars (`**`) are supported.
     ///
-    /// Relative paths are **not** relative to the configuration file, but rather
-    /// depends on the tool using the configuration.
+    /// Relative paths are relative to the directory the tool is used in,
+    /// see `config_relative_paths`.
     ///
-    /// Omitting this property includes all files, **however an empty array will include none**.
+    /// Omitting this property includes all files in the directory the tool is used in,
+    /// **however an empty array will include none**.
     pub include: Option<Vec<String>>,
 
     /// Files to exclude (ignore).
@@ -26,12 +45,22 @@ pub struct Config {
     /// A list of Unix-like [glob](https://en.wikipedia.org/wiki/Glob_(programming)) path patterns.
     /// Globstars (`**`) are supported.
     ///
-    /// Relative paths are **not** relative to the configuration file, but rather
-    /// depends on the tool using the configuration.
+    /// Relative paths are relative to the directory the tool is used in,
+    /// see `config_relative_paths`.
     ///
     /// This has priority over `include`.
     pub exclude: Option<Vec<String>>,
 
+    /// Whether the relative paths in this file are relative to the directory
+    /// of the configuration file.
+    ///
+    /// Otherwise they are relative to the directory the tool is used in,
+    /// which is the current directory for the CLI and the workspace for the editors.
+    /// Configuration files found in its subdirectories always use their own directory.
+    ///
+    /// This only applies to this file, not to the configurations it extends.
+    pub config_relative_paths: Option<bool>,
+
     /// Rules are used to override configurations by path and keys.
     pub rule: Option<Vec<Rule>>,
 
@@ -40,7 +69,44 @@ pub struct Config {
 }
 
 impl Config {
-    /// Tell if a path is excluded
+    /// Merges another configuration into this one,
+    /// the values of the other configuration take precedence.
+    ///
+    /// `extends` is not merged.
+    pub fn merge(&mut self, other: Config) {
+        if other.include.is_some() {
+            self.include = other.include;
+        }
+
+        if other.exclude.is_some() {
+            self.exclude = other.exclude;
+        }
+
+        if let Some(rules) = other.rule {
+            self.rule.get_or_insert_with(Vec::new).extend(rules);
+        }
+
+        self.global_options.merge(other.global_options);
+    }
+
+    /// Resolves the relative paths and patterns in the configuration
+    /// against the given directory.
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        resolve_patterns(&mut self.include, dir);
+        resolve_patterns(&mut self.exclude, dir);
+        self.global_options.resolve_paths(dir);
+
+        for rule in self.rule.iter_mut().flatten() {
+            resolve_patterns(&mut rule.include, dir);
+            resolve_patterns(&mut rule.exclude, dir);
+            rule.options.resolve_paths(dir);
+        }
+    }
+
+    /// Tell if a path is excluded.
+    ///
+    /// The path should be absolute if the paths
+    /// in the configuration were resolved.
     pub fn is_excluded(&self, path: &str) -> Result<bool, glob::PatternError> {
         match &self.exclude {
             Some(excluded) => {
@@ -57,9 +123,29 @@ impl Config {
         }
     }
 
+    /// Tell if a path is included and not excluded.
+    ///
+    /// The path should be absolute if the paths
+    /// in the configuration were resolved.
+    pub fn is_included(&self, path: &str) -> Result<bool, glob::PatternError> {
+        if self.is_excluded(path)? {
+            return Ok(false);
+        }
+
+        for p in self.get_include_paths() {
+            let pat = glob::Pattern::new(&p)?;
+
+            if pat.matches_with(path, glob_match_options()) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     pub fn get_schema_path(&self, path: &str) -> Result<Option<String>, anyhow::Error> {
         if let Some(rules) = &self.rule {
-            for rule in rules.iter().rev() {
+            for rule in rules.iter().rev().filter(|r| r.keys.is_none()) {
                 if let Some(schema_opts) = &rule.options.schema {
                     if schema_opts.enabled.unwrap_or(false) {
                         match &schema_opts.path {
@@ -87,6 +173,32 @@ impl Config {
         Ok(None)
     }
 
+    /// Returns the schemas for keys in the document as `(keys, schema path)` pairs,
+    /// in the order they should be attached.
+    pub fn get_key_schema_paths(&self, path: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+        let mut schemas = Vec::new();
+
+        for rule in self.rule.iter().flatten() {
+            let keys = match &rule.keys {
+                Some(k) => k,
+                None => continue,
+            };
+
+            if let Some(schema_opts) = &rule.options.schema {
+                if schema_opts.enabled.unwrap_or(false) && rule.includes(path)? {
+                    match &schema_opts.path {
+                        Some(schema_path) => {
+                            schemas.extend(keys.iter().map(|k| (k.clone(), schema_path.clone())))
+                        }
+                        None => return Err(anyhow!("schema is enabled, but path is missing")),
+                    }
+                }
+            }
+        }
+
+        Ok(schemas)
+    }
+
     pub fn collect_schemas(&self) -> Vec<String> {
         let mut schemas = Vec::new();
 
@@ -250,6 +362,40 @@ pub struct Options {
     pub formatting: Option<formatter::OptionsIncomplete>,
 }
 
+impl Options {
+    fn resolve_paths(&mut self, dir: &Path) {
+        if let Some(path) = self.schema.as_mut().and_then(|s| s.path.as_mut()) {
+            if !path.contains("://") && !is_absolute_path(path) {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Options) {
+        if let Some(schema) = other.schema {
+            match &mut self.schema {
+                Some(s) => {
+                    if schema.enabled.is_some() {
+                        s.enabled = schema.enabled;
+                    }
+
+                    if schema.path.is_some() {
+                        s.path = schema.path;
+                    }
+                }
+                None => self.schema = Some(schema),
+            }
+        }
+
+        if let Some(formatting) = other.formatting {
+            match &mut self.formatting {
+                Some(f) => f.update(formatting),
+                None => self.formatting = Some(formatting),
+            }
+        }
+    }
+}
+
 /// A rule to override options by either name or file.
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -263,8 +409,8 @@ pub struct Rule {
     ///
     /// A list of Unix-like [glob](https://en.wikipedia.org/wiki/Glob_(programming)) path patterns.
     ///
-    /// Relative paths are **not** relative to the configuration file, but rather
-    /// depends on the tool using the configuration.
+    /// Relative paths are relative to the directory the tool is used in,
+    /// see `config_relative_paths`.
     ///
     /// Omitting this property includes all files, **however an empty array will include none**.
     pub include: Option<Vec<String>>,
@@ -273,8 +419,8 @@ pub struct Rule {
     ///
     /// A list of Unix-like [glob](https://en.wikipedia.org/wiki/Glob_(programming)) path patterns.
     ///
-    /// Relative paths are **not** relative to the configuration file, but rather
-    /// depends on the tool using the configuration.
+    /// Relative paths are relative to the directory the tool is used in,
+    /// see `config_relative_paths`.
     ///
     /// This has priority over `include`.
     pub exclude: Option<Vec<String>>,
@@ -329,7 +475,8 @@ impl Rule {
 
 /// Options for schema validation and completion.
 ///
-/// Schemas in rules with defined keys are ignored.
+/// Schemas in rules with defined keys are attached to the values at those keys,
+/// the values are validated against both these and the schema of the document.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct SchemaOptions {
@@ -343,5 +490,157 @@ pub struct SchemaOptions {
     /// The path of the schema, this can be either path to a local file path or an URL with the schemes `taplo`,
     /// `http` or `https`. (`file` scheme is also accepted, it is the same as specifying a local path)
     ///
+    /// Relative local paths are resolved the same way as `include`.
     pub path: Option<String>,
 }
+
+/// Returns the path of the configuration file
+/// in the given directory or the nearest ancestor of it.
+pub fn find_config_file(dir: &Path, file_exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|p| file_exists(p))
+    })
+}
+
+/// The directory the relative paths of a configuration file found
+/// for the documents are resolved against, unless it sets `config_relative_paths`.
+///
+/// Configuration files in `dir` or in its ancestors use `dir`,
+/// the ones in its subdirectories use their own directory.
+pub fn discovered_base_dir<'p>(config_path: &'p Path, dir: &'p Path) -> &'p Path {
+    match config_path.parent() {
+        Some(parent) if !dir.starts_with(parent) => parent,
+        _ => dir,
+    }
+}
+
+/// Loads a configuration file along with the configurations it extends,
+/// and merges them.
+///
+/// The `load` function is used to read and parse a single configuration file.
+///
+/// Relative paths in the files are resolved against `base_dir`,
+/// or against their own directory if they set `config_relative_paths`.
+pub async fn load_config_file<L, F>(
+    path: &Path,
+    base_dir: &Path,
+    load: L,
+) -> Result<Config, anyhow::Error>
+where
+    L: Fn(PathBuf) -> F,
+    F: Future<Output = Result<Config, anyhow::Error>>,
+{
+    let mut configs: HashMap<PathBuf, Config> = HashMap::new();
+    let mut queue = vec![path.to_path_buf()];
+
+    // Every file is loaded only once, even if there are cycles.
+    while let Some(path) = queue.pop() {
+        if configs.contains_key(&path) {
+            continue;
+        }
+
+        let mut config = load(path.clone()).await?;
+
+        let dir = match (config.config_relative_paths, path.parent()) {
+            (Some(true), Some(dir)) => dir,
+            _ => base_dir,
+        };
+        config.resolve_paths(dir);
+
+        for extended in config.extends.iter().flatten() {
+            if !is_builtin(extended) {
+                queue.push(extended_path(&path, extended));
+            }
+        }
+
+        configs.insert(path, config);
+    }
+
+    merge_extended(path, &configs, &mut Vec::new())
+}
+
+fn merge_extended(
+    path: &Path,
+    configs: &HashMap<PathBuf, Config>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Config, anyhow::Error> {
+    if chain.iter().any(|p| p == path) {
+        return Err(anyhow!(
+            "configuration {:?} extends itself through {:?}",
+            path,
+            chain
+        ));
+    }
+
+    let config = configs
+        .get(path)
+        .ok_or_else(|| anyhow!("configuration {:?} was not loaded", path))?;
+
+    chain.push(path.to_path_buf());
+
+    let mut merged = Config::default();
+
+    for extended in config.extends.iter().flatten() {
+        if is_builtin(extended) {
+            merged.merge(builtin_config(extended)?);
+        } else {
+            merged.merge(merge_extended(
+                &extended_path(path, extended),
+                configs,
+                chain,
+            )?);
+        }
+    }
+
+    chain.pop();
+
+    let mut config = config.clone();
+    config.extends = None;
+    merged.merge(config);
+
+    Ok(merged)
+}
+
+/// Makes the relative glob patterns relative to the given directory.
+pub(crate) fn resolve_patterns(patterns: &mut Option<Vec<String>>, dir: &Path) {
+    let dir = glob::Pattern::escape(&dir.to_string_lossy());
+
+    for pattern in patterns.iter_mut().flatten() {
+        if !is_absolute_path(pattern) {
+            *pattern = Path::new(&dir)
+                .join(pattern.trim_start_matches("./"))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+}
+
+fn is_builtin(extended: &str) -> bool {
+    extended.starts_with(&format!("{}://", BUILTIN_SCHEME))
+}
+
+/// The path of an extended configuration that is relative to the extending one.
+fn extended_path(config_path: &Path, extended: &str) -> PathBuf {
+    match config_path.parent() {
+        Some(dir) => dir.join(extended),
+        None => PathBuf::from(extended),
+    }
+}
+
+fn builtin_config(name: &str) -> Result<Config, anyhow::Error> {
+    match name {
+        DEFAULT_CONFIG => Ok(Config {
+            global_options: Options {
+                schema: None,
+                formatting: Some(formatter::OptionsIncomplete::from_options(
+                    formatter::Options::default(),
+                )),
+            },
+            ..Default::default()
+        }),
+        _ => Err(anyhow!("invalid builtin configuration: {}", name)),
+    }
+}
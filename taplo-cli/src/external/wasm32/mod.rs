@@ -1,15 +1,19 @@
 #![allow(unused_unsafe)]
-use crate::config::{Config, CONFIG_FILE_NAMES};
+use crate::{
+    config::Config,
+    is_offline,
+    util::{cache_schema, cached_schema_path},
+};
 use anyhow::anyhow;
-use hex::ToHex;
 use js_sys::Uint8Array;
 use once_cell::sync::Lazy;
 use reqwest::{Client, header};
 use schemars::schema::RootSchema;
 use schemars::schema_for;
-use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
-use taplo::schema::{CachedSchema, SchemaIndex, BUILTIN_SCHEME};
+use taplo::schema::{
+    builtin::builtin_schema, util::schema_from_slice, CachedSchema, SchemaIndex, BUILTIN_SCHEME,
+};
 use wasm_bindgen::prelude::*;
 
 static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
@@ -76,6 +80,15 @@ pub(crate) fn glob_match_options() -> glob::MatchOptions {
     }
 }
 
+/// Runs CPU-heavy work, there are no threads to run it on in this environment.
+pub(crate) async fn run_blocking<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    f()
+}
+
 pub(crate) fn read_stdin() -> Result<String, anyhow::Error> {
     unsafe { js_read_stdin().map_err(js_err_to_anyhow) }
 }
@@ -88,25 +101,8 @@ pub(crate) fn get_paths_by_glob(pattern: &str) -> Result<Vec<PathBuf>, anyhow::E
     }
 }
 
-pub(crate) async fn load_config(path: Option<&str>) -> Result<Config, anyhow::Error> {
-    let cwd = PathBuf::from(unsafe { js_current_dir() });
-
-    if let Some(p) = path {
-        return toml::from_slice(&read_file(p).await?).map_err(Into::into);
-    }
-
-    for name in CONFIG_FILE_NAMES {
-        let config_path = cwd.join(name);
-
-        unsafe {
-            if js_file_exists(config_path.to_str().unwrap()) {
-                return toml::from_slice(&read_file(config_path.to_str().unwrap()).await?)
-                    .map_err(Into::into);
-            }
-        }
-    }
-
-    Ok(Config::default())
+pub(crate) fn current_dir() -> Result<PathBuf, anyhow::Error> {
+    Ok(PathBuf::from(unsafe { js_current_dir() }))
 }
 
 pub(crate) async fn get_schema(
@@ -116,20 +112,14 @@ pub(crate) async fn get_schema(
     if path.starts_with(&format!("{}://", BUILTIN_SCHEME)) {
         if path == "taplo://taplo.toml" {
             Ok((schema_for!(Config), None))
+        } else if let Some(builtin) = builtin_schema(path) {
+            Ok((builtin.schema(), None))
         } else {
             Err(anyhow!("invalid builtin schema: {}", path))
         }
     } else if path.starts_with("http://") || path.starts_with("https://") {
-        let mut hasher = Sha256::new();
-        hasher.update(path.as_bytes());
-        let url_hash = hasher.finalize().encode_hex::<String>();
-
         if let Some(cache_path) = cache_path.as_ref() {
-            let file_path = cache_path
-                .as_ref()
-                .join("schemas")
-                .join(&url_hash)
-                .with_extension("json");
+            let file_path = cached_schema_path(cache_path, path);
             let fp = file_path.to_str().unwrap();
 
             if file_exists(fp) {
@@ -139,31 +129,11 @@ pub(crate) async fn get_schema(
             }
         }
 
-        let res = HTTP_CLIENT.get(path).send().await?;
-        let schema: RootSchema = res.json().await.map_err::<anyhow::Error, _>(Into::into)?;
-
-        let p = path.to_string();
-        let s = schema.clone();
+        let schema = download_schema(path).await?;
 
         // We also cache it here.
         if let Some(cache_path) = cache_path.as_ref() {
-            mkdir(cache_path.as_ref().join("schemas").to_str().unwrap())?;
-            if let Err(err) = write_file(
-                cache_path
-                    .as_ref()
-                    .join("schemas")
-                    .join(&url_hash)
-                    .with_extension("json")
-                    .to_str()
-                    .unwrap(),
-                &serde_json::to_vec(&CachedSchema {
-                    url: Some(p),
-                    schema: s,
-                })
-                .unwrap(),
-            )
-            .await
-            {
+            if let Err(err) = cache_schema(cache_path, path, schema.clone()).await {
                 return Ok((schema, Some(anyhow!("failed to cache schema: {}", err))));
             };
         }
@@ -171,13 +141,32 @@ pub(crate) async fn get_schema(
         Ok((schema, None))
     } else if path.starts_with("file://") {
         path = path.trim_start_matches("file://");
-        Ok((serde_json::from_slice(&read_file(path).await?)?, None))
+        Ok((schema_from_slice(&read_file(path).await?)?, None))
     } else {
-        Ok((serde_json::from_slice(&read_file(path).await?)?, None))
+        Ok((schema_from_slice(&read_file(path).await?)?, None))
     }
 }
 
+/// Downloads a schema without caching it.
+pub(crate) async fn download_schema(url: &str) -> Result<RootSchema, anyhow::Error> {
+    if is_offline() {
+        return Err(anyhow!(
+            "{} is not cached and cannot be downloaded in offline mode",
+            url
+        ));
+    }
+
+    let res = HTTP_CLIENT.get(url).send().await?.error_for_status()?;
+    Ok(schema_from_slice(&res.bytes().await?)?)
+}
+
 pub(crate) async fn download_schema_index(index_url: &str) -> Result<SchemaIndex, anyhow::Error> {
+    if is_offline() {
+        return Err(anyhow!(
+            "the schema index cannot be downloaded in offline mode"
+        ));
+    }
+
     match HTTP_CLIENT.get(index_url).send().await {
         Ok(res) => match res.json::<SchemaIndex>().await {
             Ok(idx) => Ok(idx),
@@ -217,19 +206,15 @@ pub(crate) async fn update_schemas(
 
     let mut updated: usize = 0;
 
+    if is_offline() {
+        return (updated, errors);
+    }
+
     for schema in &index.schemas {
         if let Some(updated_str) = &schema.updated {
             match time::OffsetDateTime::parse(updated_str, time::Format::Rfc3339) {
                 Ok(updated_ts) => {
-                    let mut hasher = Sha256::new();
-                    hasher.update(schema.url.as_bytes());
-                    let url_hash = hasher.finalize().encode_hex::<String>();
-
-                    let file_path = cache_path
-                        .as_ref()
-                        .join("schemas")
-                        .join(&url_hash)
-                        .with_extension("json");
+                    let file_path = cached_schema_path(&cache_path, &schema.url);
                     let fp = file_path.to_str().unwrap();
 
                     if let Ok(true) = needs_update(fp, (updated_ts.unix_timestamp() * 1000) as u64)
@@ -238,32 +223,9 @@ pub(crate) async fn update_schemas(
                         let cache_path = PathBuf::from(cache_path.as_ref());
 
                         let res = HTTP_CLIENT.get(&path).send().await.unwrap();
-                        let schema: RootSchema = res
-                            .json()
-                            .await
-                            .map_err::<anyhow::Error, _>(Into::into)
-                            .unwrap();
-
-                        if let Err(err) = mkdir(cache_path.join("schemas").to_str().unwrap()) {
-                            errors.push(err);
-                            continue;
-                        }
+                        let schema = schema_from_slice(&res.bytes().await.unwrap()).unwrap();
 
-                        match write_file(
-                            cache_path
-                                .join("schemas")
-                                .join(&url_hash)
-                                .with_extension("json")
-                                .to_str()
-                                .unwrap(),
-                            &serde_json::to_vec(&CachedSchema {
-                                url: Some(path),
-                                schema,
-                            })
-                            .unwrap(),
-                        )
-                        .await
-                        {
+                        match cache_schema(cache_path, &path, schema).await {
                             Ok(_) => updated += 1,
                             Err(err) => errors.push(err),
                         }
@@ -295,8 +257,12 @@ pub(crate) async fn write_file(p: &str, data: &[u8]) -> Result<(), anyhow::Error
     Ok(())
 }
 
+pub(crate) fn is_absolute_path(p: &str) -> bool {
+    unsafe { js_is_absolute_path(p) }
+}
+
 pub(crate) fn file_exists(p: &str) -> bool {
-    Path::new(p).exists()
+    unsafe { js_file_exists(p) }
 }
 
 pub(crate) fn mkdir(p: &str) -> Result<(), anyhow::Error> {
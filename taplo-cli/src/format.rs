@@ -1,36 +1,71 @@
 use crate::{
-    config::Config,
-    external::{get_paths_by_glob, read_file, read_stdin, write_file},
+    external::{get_paths_by_glob, read_file, read_stdin, run_blocking, write_file},
     print_message,
+    util::{document_paths, jobs, Configs},
 };
 use anyhow::anyhow;
 use clap::ArgMatches;
+use futures::{stream, StreamExt};
 use pretty_lint::Severity;
 use std::collections::HashSet;
-use taplo::formatter::{self, Options};
+use taplo::formatter::{self, Options, TextEdit};
 
 pub(crate) struct FormatResult {
     pub matched_document_count: usize,
     pub excluded_document_count: usize,
     pub error_count: usize,
     pub forced: usize,
+    pub unformatted_document_count: usize,
 }
 
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct CliOptions {
     pub force: bool,
+    /// Only report the unformatted documents.
+    pub check: bool,
+    /// Only print the changes as a unified diff.
+    pub diff: bool,
+    /// The maximum amount of documents formatted at the same time.
+    pub jobs: usize,
 }
 
-pub(crate) async fn format(config: Config, m: &ArgMatches) -> FormatResult {
+impl CliOptions {
+    /// Whether the documents should be left untouched.
+    fn dry_run(&self) -> bool {
+        self.check || self.diff
+    }
+}
+
+/// Formats the documents given in the arguments or the configuration.
+///
+/// If `changed` is given, only those documents are formatted instead.
+pub(crate) async fn format(
+    mut configs: Configs,
+    m: &ArgMatches,
+    changed: Option<&[String]>,
+) -> FormatResult {
     let mut res = FormatResult {
         matched_document_count: 0,
         error_count: 0,
         excluded_document_count: 0,
         forced: 0,
+        unformatted_document_count: 0,
+    };
+
+    let jobs = match jobs(m) {
+        Ok(j) => j,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            res.error_count += 1;
+            return res;
+        }
     };
 
     let opts = CliOptions {
         force: m.is_present("force"),
+        check: m.is_present("check"),
+        diff: m.is_present("diff"),
+        jobs,
     };
 
     let mut cli_opts = None;
@@ -50,13 +85,25 @@ pub(crate) async fn format(config: Config, m: &ArgMatches) -> FormatResult {
         }
     }
 
-    if let Some(files) = m.values_of("files") {
-        format_paths(&config, opts, files, &mut res, false, cli_opts).await;
+    if let Some(changed) = changed {
+        format_paths(
+            &mut configs,
+            opts,
+            changed.iter().map(|s| s.as_ref()),
+            &mut res,
+            !m.is_present("files"),
+            cli_opts,
+        )
+        .await;
+    } else if let Some(files) = m.values_of("files") {
+        format_paths(&mut configs, opts, files, &mut res, false, cli_opts).await;
     } else {
+        let include = configs.root().get_include_paths();
+
         format_paths(
-            &config,
+            &mut configs,
             opts,
-            config.get_include_paths().iter().map(|s| s.as_ref()),
+            include.iter().map(|s| s.as_ref()),
             &mut res,
             true,
             cli_opts,
@@ -103,13 +150,15 @@ fn collect_cli_opts<'i, I: Iterator<Item = &'i str>>(
 }
 
 async fn format_paths<'i, F: Iterator<Item = &'i str>>(
-    config: &Config,
+    configs: &mut Configs,
     opts: CliOptions,
     files: F,
     res: &mut FormatResult,
     allow_exclude: bool, // for cli file args
     cli_format_opts: Option<Vec<(String, String)>>,
 ) {
+    let mut paths = Vec::new();
+
     for val in files {
         if val == "-" {
             let src = match read_stdin() {
@@ -121,7 +170,7 @@ async fn format_paths<'i, F: Iterator<Item = &'i str>>(
                 }
             };
 
-            let mut format_opts = match config.get_formatter_options(None, None) {
+            let mut format_opts = match configs.root().get_formatter_options(None, None) {
                 Ok(opts) => opts,
                 Err(err) => {
                     print_message(Severity::Error, "error", &err.to_string());
@@ -142,9 +191,17 @@ async fn format_paths<'i, F: Iterator<Item = &'i str>>(
             }
 
             res.matched_document_count += 1;
-            match format_source(&src, opts, format_opts, res) {
-                Ok(s) => {
-                    print!("{}", &s);
+            match format_source(&src, opts, format_opts) {
+                Ok((edits, forced)) => {
+                    if forced {
+                        res.forced += 1;
+                    }
+
+                    if opts.dry_run() {
+                        report_unformatted("-", &src, &edits, opts, res);
+                    } else {
+                        print!("{}", formatter::apply_edits(&src, &edits));
+                    }
                 }
                 Err(_) => {
                     res.error_count += 1;
@@ -154,99 +211,7 @@ async fn format_paths<'i, F: Iterator<Item = &'i str>>(
         }
 
         match get_paths_by_glob(val) {
-            Ok(sources) => {
-                for path in sources {
-                    if allow_exclude {
-                        // Don't format taplo config files unless asked explicitly.
-                        if let Some(file_name) = path.file_name() {
-                            if file_name == "taplo.toml" || file_name == ".taplo.toml" {
-                                // Don't count it as excluded.
-                                continue;
-                            }
-                        }
-
-                        if let Some(p) = path.to_str() {
-                            match config.is_excluded(p) {
-                                Ok(excluded) => {
-                                    if excluded {
-                                        res.excluded_document_count += 1;
-                                        continue;
-                                    }
-                                }
-                                Err(err) => {
-                                    print_message(Severity::Error, "error", &err.to_string());
-                                    res.error_count += 1;
-                                    return;
-                                }
-                            }
-                        }
-                    }
-
-                    match read_file(path.to_str().unwrap()).await {
-                        Ok(src) => {
-                            let src = match String::from_utf8(src) {
-                                Ok(src) => src,
-                                Err(err) => {
-                                    print_message(
-                                        Severity::Error,
-                                        "error",
-                                        &format!("file {:?} is not valid UTF-8: {}", path, err),
-                                    );
-                                    res.error_count += 1;
-                                    continue;
-                                }
-                            };
-
-                            res.matched_document_count += 1;
-
-                            let mut format_opts =
-                                match config.get_formatter_options(path.to_str(), None) {
-                                    Ok(opts) => opts,
-                                    Err(err) => {
-                                        print_message(Severity::Error, "error", &err.to_string());
-                                        res.error_count += 1;
-                                        continue;
-                                    }
-                                };
-
-                            if let Some(cli_format_opts) = &cli_format_opts {
-                                if let Err(err) = format_opts
-                                    .0
-                                    .update_from_str(cli_format_opts.iter().map(|s| (&s.0, &s.1)))
-                                {
-                                    print_message(Severity::Error, "error", &err.to_string());
-                                    res.error_count += 1;
-                                    return;
-                                }
-                            }
-
-                            match format_source(&src, opts, format_opts, res) {
-                                Ok(s) => {
-                                    if src != s {
-                                        match write_file(path.to_str().unwrap(), s.as_bytes()).await
-                                        {
-                                            Ok(_) => {}
-                                            Err(err) => {
-                                                res.error_count += 1;
-                                                print_message(
-                                                    Severity::Error,
-                                                    "error",
-                                                    &err.to_string(),
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(_) => res.error_count += 1,
-                            }
-                        }
-                        Err(err) => {
-                            print_message(Severity::Error, "error", &err.to_string());
-                            res.error_count += 1;
-                        }
-                    }
-                }
-            }
+            Ok(sources) => paths.extend(sources),
             Err(e) => {
                 print_message(Severity::Error, "error", &e.to_string());
                 res.error_count += 1;
@@ -254,31 +219,309 @@ async fn format_paths<'i, F: Iterator<Item = &'i str>>(
             }
         }
     }
+
+    let (paths, excluded) = match document_paths(configs, paths, allow_exclude).await {
+        Ok(p) => p,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            res.error_count += 1;
+            return;
+        }
+    };
+
+    res.excluded_document_count += excluded;
+
+    let mut documents = Vec::with_capacity(paths.len());
+
+    for (path, config) in paths {
+        let path = match path.to_str() {
+            Some(p) => p.to_string(),
+            None => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("invalid file path: {:?}", path),
+                );
+                res.error_count += 1;
+                continue;
+            }
+        };
+
+        let absolute_path = configs.absolute_path(&path);
+
+        let mut format_opts = match config.get_formatter_options(Some(&absolute_path), None) {
+            Ok(opts) => opts,
+            Err(err) => {
+                print_message(Severity::Error, "error", &err.to_string());
+                res.error_count += 1;
+                continue;
+            }
+        };
+
+        if let Some(cli_format_opts) = &cli_format_opts {
+            if let Err(err) = format_opts
+                .0
+                .update_from_str(cli_format_opts.iter().map(|s| (&s.0, &s.1)))
+            {
+                print_message(Severity::Error, "error", &err.to_string());
+                res.error_count += 1;
+                return;
+            }
+        }
+
+        documents.push((path, format_opts));
+    }
+
+    let mut results = stream::iter(documents.into_iter().map(|(path, format_opts)| async move {
+        let formatted = format_file(&path, opts, format_opts).await;
+        (path, formatted)
+    }))
+    .buffered(opts.jobs);
+
+    while let Some((path, formatted)) = results.next().await {
+        match formatted {
+            Ok(Some(doc)) => {
+                res.matched_document_count += 1;
+
+                if doc.forced {
+                    res.forced += 1;
+                }
+
+                if opts.dry_run() {
+                    report_unformatted(&path, &doc.src, &doc.edits, opts, res);
+                }
+            }
+            Ok(None) => {
+                res.matched_document_count += 1;
+                res.error_count += 1;
+            }
+            Err(err) => {
+                print_message(Severity::Error, "error", &err.to_string());
+                res.error_count += 1;
+            }
+        }
+    }
+}
+
+/// A document and its formatting changes.
+struct FormattedDocument {
+    /// The original source.
+    src: String,
+    edits: Vec<TextEdit>,
+    /// Whether the document had syntax errors.
+    forced: bool,
 }
 
+/// Reads and formats a document, the changes are written unless it is a dry run.
+///
+/// Returns `None` if the document has syntax errors and formatting is not forced.
+async fn format_file(
+    path: &str,
+    opts: CliOptions,
+    formatter_options: (Options, Vec<(String, formatter::OptionsIncomplete)>),
+) -> Result<Option<FormattedDocument>, anyhow::Error> {
+    let src = read_file(path).await?;
+
+    let src = match String::from_utf8(src) {
+        Ok(src) => src,
+        Err(err) => return Err(anyhow!("file {:?} is not valid UTF-8: {}", path, err)),
+    };
+
+    let (src, formatted) = run_blocking(move || {
+        let formatted = format_source(&src, opts, formatter_options);
+        (src, formatted)
+    })
+    .await;
+
+    let (edits, forced) = match formatted {
+        Ok(f) => f,
+        Err(_) => return Ok(None),
+    };
+
+    if !opts.dry_run() && !edits.is_empty() {
+        write_file(path, formatter::apply_edits(&src, &edits).as_bytes()).await?;
+    }
+
+    Ok(Some(FormattedDocument { src, edits, forced }))
+}
+
+/// Returns the formatting edits, and whether the
+/// formatting was forced despite syntax errors.
 fn format_source(
     src: &str,
     opts: CliOptions,
     formatter_options: (Options, Vec<(String, formatter::OptionsIncomplete)>),
-    res: &mut FormatResult,
-) -> Result<String, ()> {
+) -> Result<(Vec<TextEdit>, bool), ()> {
     let parse = taplo::parser::parse(src);
 
     let had_errors = !parse.errors.is_empty();
 
-    if had_errors {
-        if !opts.force {
-            return Err(());
-        } else {
-            res.forced += 1;
-        }
+    if had_errors && !opts.force {
+        return Err(());
     }
 
     let dom = parse.into_dom();
 
-    Ok(taplo::formatter::format_with_path_scopes(
-        dom,
-        formatter_options.0,
-        formatter_options.1,
+    Ok((
+        taplo::formatter::format_edits(dom, formatter_options.0, formatter_options.1),
+        had_errors,
     ))
 }
+
+fn report_unformatted(
+    name: &str,
+    src: &str,
+    edits: &[TextEdit],
+    opts: CliOptions,
+    res: &mut FormatResult,
+) {
+    if edits.is_empty() {
+        return;
+    }
+
+    res.unformatted_document_count += 1;
+
+    if opts.diff {
+        print!("{}", unified_diff(name, src, edits));
+    }
+
+    if opts.check {
+        print_message(Severity::Error, "unformatted", name);
+    }
+}
+
+/// The amount of unchanged lines around the changes in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Creates a unified diff of the formatter changes.
+pub(crate) fn unified_diff(name: &str, src: &str, edits: &[TextEdit]) -> String {
+    let lines: Vec<&str> = src.split_inclusive('\n').collect();
+
+    let mut line_starts = vec![0];
+    line_starts.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+
+    let line_of = |offset: usize| line_starts.partition_point(|&s| s <= offset) - 1;
+    let line_offset = |line: usize| line_starts.get(line).copied().unwrap_or(src.len());
+
+    // Changed lines as `(old lines, new lines)`,
+    // edits that touch the same lines are merged.
+    let mut changes: Vec<(std::ops::Range<usize>, Vec<&TextEdit>)> = Vec::new();
+
+    for edit in edits {
+        let start = line_of(edit.range.start().into()).min(lines.len());
+
+        // An edit that ends at the start of a line does not change that line.
+        let last_line = if edit.range.is_empty() {
+            line_of(edit.range.end().into())
+        } else {
+            line_of(usize::from(edit.range.end()) - 1)
+        };
+        let end = (last_line + 1).min(lines.len());
+
+        match changes.last_mut() {
+            Some((range, edits)) if start < range.end => {
+                range.end = range.end.max(end);
+                edits.push(edit);
+            }
+            _ => changes.push((start..end, vec![edit])),
+        }
+    }
+
+    let changes: Vec<(std::ops::Range<usize>, String)> = changes
+        .into_iter()
+        .map(|(range, edits)| {
+            let region_start = line_offset(range.start);
+            let mut new_text = String::new();
+            let mut offset = region_start;
+
+            for edit in edits {
+                new_text += &src[offset..usize::from(edit.range.start())];
+                new_text += &edit.new_text;
+                offset = edit.range.end().into();
+            }
+
+            new_text += &src[offset..line_offset(range.end)];
+
+            (range, new_text)
+        })
+        .collect();
+
+    let mut diff = format!("--- {name}\n+++ {name}\n", name = name);
+
+    // The difference of the line counts before the current hunk.
+    let mut line_delta: isize = 0;
+
+    let mut i = 0;
+    while i < changes.len() {
+        // Changes that are close enough share a hunk.
+        let mut j = i + 1;
+        while j < changes.len() && changes[j].0.start - changes[j - 1].0.end <= 2 * DIFF_CONTEXT {
+            j += 1;
+        }
+
+        let hunk = &changes[i..j];
+
+        let old_start = hunk[0].0.start.saturating_sub(DIFF_CONTEXT);
+        let old_end = (hunk[hunk.len() - 1].0.end + DIFF_CONTEXT).min(lines.len());
+
+        let mut body = String::new();
+        let mut hunk_delta: isize = 0;
+        let mut line = old_start;
+
+        for (range, new_text) in hunk {
+            for l in &lines[line..range.start] {
+                push_diff_line(&mut body, ' ', l);
+            }
+
+            for l in &lines[range.clone()] {
+                push_diff_line(&mut body, '-', l);
+            }
+
+            let new_lines = new_text.split_inclusive('\n').count();
+            for l in new_text.split_inclusive('\n') {
+                push_diff_line(&mut body, '+', l);
+            }
+
+            hunk_delta += new_lines as isize - range.len() as isize;
+            line = range.end;
+        }
+
+        for l in &lines[line..old_end] {
+            push_diff_line(&mut body, ' ', l);
+        }
+
+        let old_len = old_end - old_start;
+        let new_start = (old_start as isize + line_delta) as usize;
+        let new_len = (old_len as isize + hunk_delta) as usize;
+
+        diff += &format!(
+            "@@ -{} +{} @@\n",
+            diff_range(old_start, old_len),
+            diff_range(new_start, new_len)
+        );
+        diff += &body;
+
+        line_delta += hunk_delta;
+        i = j;
+    }
+
+    diff
+}
+
+fn push_diff_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// A line range in a diff hunk header.
+fn diff_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
@@ -1,7 +1,7 @@
 #![cfg_attr(all(target_arch = "wasm32", feature = "nightly"), feature(set_stdio))]
 
 use clap::{App, AppSettings, Arg, ArgMatches};
-use external::{download_schema_index, load_config, load_schema_index, update_schemas};
+use external::{download_schema_index, glob_match_options, load_schema_index, update_schemas};
 use once_cell::sync::Lazy;
 use pretty_lint::{
     colored::{self, Colorize},
@@ -9,10 +9,12 @@ use pretty_lint::{
 };
 use std::{
     ffi::OsString,
+    future::Future,
+    path::Path,
     sync::atomic::{AtomicBool, Ordering},
 };
-use taplo::formatter;
-use util::cache_schema_index;
+use taplo::{formatter, schema::SchemaIndex};
+use util::{cache_schema_index, Configs};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -29,12 +31,20 @@ mod external;
 
 mod format;
 mod lint;
+mod schema;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
 
 pub mod util;
 
+#[cfg(test)]
+mod tests;
+
 static ERROR_STATUS: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 static SILENT_OUTPUT: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 static WARN_AS_ERROR: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+static OFFLINE: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 
 /// For exit conditions.
 pub(crate) fn is_error() -> bool {
@@ -60,6 +70,14 @@ pub(crate) fn set_warn_as_error(e: bool) {
     WARN_AS_ERROR.store(e, Ordering::SeqCst);
 }
 
+/// Whether network access is forbidden.
+pub(crate) fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+pub(crate) fn set_offline(o: bool) {
+    OFFLINE.store(o, Ordering::SeqCst);
+}
+
 pub(crate) fn print_message(severity: Severity, name: &str, message: &str) {
     match severity {
         Severity::Error => {
@@ -124,7 +142,21 @@ where
         colored::control::set_override(colors);
     }
 
-    let mut app = App::new("Taplo TOML Utility")
+    match app().try_get_matches_from_mut(itr) {
+        Ok(matches) => execute(matches).await,
+        Err(err) => {
+            eprintln!("{}", err);
+            matches!(
+                err.kind,
+                clap::ErrorKind::DisplayHelp | clap::ErrorKind::DisplayVersion
+            )
+        }
+    }
+}
+
+/// The command-line interface, the arguments do not include the binary name.
+fn app() -> App<'static> {
+    App::new("Taplo TOML Utility")
         .author("tamasfe (https://github.com/tamasfe)")
         .bin_name("taplo")
         .version(concat!(env!("CARGO_PKG_VERSION"), " (", env!("BUILD_TARGET"), ")"))
@@ -151,11 +183,18 @@ where
                 .about("Treat warnings as errors")
                 .global(true)
         )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .about("Never access the network")
+                .long_about("Never access the network. Remote schemas and the schema index are only read from the cache directory, and it is an error if they are not cached")
+                .global(true)
+        )
         .subcommand(
             App::new("format")
                 .visible_alias("fmt")
                 .about("Format TOML documents in place")
-                .long_about("Format TOML documents. Files are modified in-place unless the input comes from the standard input, in which case the formatted result is printed to the standard output")
+                .long_about("Format TOML documents. Files are modified in-place unless the input comes from the standard input, in which case the formatted result is printed to the standard output, or either --check or --diff is given")
                 .arg(
                     Arg::new("files")
                         .about("Paths or glob patterns to TOML documents")
@@ -174,6 +213,33 @@ where
                         .long("force")   
                         .about("Ignore syntax errors and format anyway (potentially destructive)"),
                 )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .about("Check whether the documents are formatted without modifying them")
+                        .long_about("Check whether the documents are formatted without modifying them. The documents that are not properly formatted are listed, and the command fails if there are any")
+                )
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .about("Print the formatting changes as a unified diff without modifying the documents")
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .about("The maximum number of documents to format in parallel")
+                        .long_about("The maximum number of documents to format in parallel, defaults to the number of available CPUs")
+                        .value_name("N")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::new("watch")
+                        .long("watch")
+                        .about("Keep running and format the documents again when they change")
+                        .long_about("Keep running and format the documents in the current directory again when they change. All the documents are formatted again if the configuration file changes")
+                        .conflicts_with("stdin")
+                )
                 .arg(
                     Arg::new("options")
                         .short('o')
@@ -238,6 +304,161 @@ where
                     .about("Use a remote schema repository")
                     .takes_value(true)
             )
+            .arg(
+                Arg::new("output-format")
+                    .long("output-format")
+                    .about("The format of the reported problems")
+                    .long_about("The format of the reported problems. The text format is printed to the standard error, all the other formats are printed to the standard output after all the documents were checked.")
+                    .takes_value(true)
+                    .possible_values(lint::OutputFormat::VALUES)
+                    .default_value("text")
+            )
+            .arg(
+                Arg::new("fix")
+                    .long("fix")
+                    .about("Apply automatic fixes for schema errors")
+                    .long_about("Apply automatic fixes for schema errors and lint the fixed documents. Missing required keys are inserted with their default values, the casing of enum values is corrected, and strings are wrapped in arrays where the schema requires an array. The documents are modified in-place, documents from the standard input are not fixed")
+            )
+            .arg(
+                Arg::new("remove-unknown-keys")
+                    .long("remove-unknown-keys")
+                    .about("Remove keys that are not allowed by the schema when fixing")
+                    .long_about("Remove keys that are not allowed by the schema (additionalProperties: false) when fixing, they are only reported otherwise as they might be misspelled")
+                    .requires("fix")
+            )
+            .arg(
+                Arg::new("jobs")
+                    .short('j')
+                    .long("jobs")
+                    .about("The maximum number of documents to lint in parallel")
+                    .long_about("The maximum number of documents to lint in parallel, defaults to the number of available CPUs. The problems are always reported in the order of the document paths")
+                    .value_name("N")
+                    .takes_value(true)
+            )
+            .arg(
+                Arg::new("watch")
+                    .long("watch")
+                    .about("Keep running and lint the documents again when they change")
+                    .long_about("Keep running and lint the documents in the current directory again when they change. All the documents are linted again if the configuration file changes")
+            )
+        )
+        .subcommand(
+            App::new("schema")
+                .about("Manage the schemas in the cache directory")
+                .long_about("Manage the schemas in the cache directory. The cache directory can be exported and used on machines without network access with the --offline flag")
+                .subcommand(
+                    App::new("list")
+                        .about("List the schemas in the schema index")
+                        .arg(
+                            Arg::new("cache-path")
+                                .long("cache-path")
+                                .about("Path to the cache folder")
+                                .takes_value(true)
+                        )
+                        .arg(
+                            Arg::new("default-schema-repository")
+                                .short('S')
+                                .long("default-schema-repository")
+                                .about("Use the default remote schema repository")
+                                .conflicts_with("schema-repository")
+                                .takes_value(false)
+                        )
+                        .arg(
+                            Arg::new("schema-repository")
+                                .long("schema-repository")
+                                .about("Use a remote schema repository")
+                                .takes_value(true)
+                        )
+                )
+                .subcommand(
+                    App::new("fetch")
+                        .about("Download schemas into the cache folder")
+                        .long_about("Download schemas along with the schemas they reference into the cache folder. Already cached schemas are downloaded again")
+                        .arg(
+                            Arg::new("urls")
+                                .about("URLs of the schemas")
+                                .multiple(true)
+                        )
+                        .arg(
+                            Arg::new("all")
+                                .long("all")
+                                .about("Download all the schemas in the schema index")
+                                .conflicts_with("urls")
+                        )
+                        .arg(
+                            Arg::new("cache-path")
+                                .long("cache-path")
+                                .about("Path to the cache folder")
+                                .takes_value(true)
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("default-schema-repository")
+                                .short('S')
+                                .long("default-schema-repository")
+                                .about("Use the default remote schema repository")
+                                .conflicts_with("schema-repository")
+                                .takes_value(false)
+                        )
+                        .arg(
+                            Arg::new("schema-repository")
+                                .long("schema-repository")
+                                .about("Use a remote schema repository")
+                                .takes_value(true)
+                        )
+                )
+                .subcommand(
+                    App::new("export")
+                        .about("Export schemas for offline use")
+                        .long_about("Export the schemas in the schema index, or the given ones, along with the schemas they reference into a folder that can be used as the cache folder with the --offline flag. Cached schemas are not downloaded again")
+                        .arg(
+                            Arg::new("urls")
+                                .about("URLs of the schemas, all the schemas in the schema index are exported if omitted")
+                                .multiple(true)
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .about("Path to the folder to export the schemas to")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::new("cache-path")
+                                .long("cache-path")
+                                .about("Path to the cache folder")
+                                .takes_value(true)
+                        )
+                        .arg(
+                            Arg::new("default-schema-repository")
+                                .short('S')
+                                .long("default-schema-repository")
+                                .about("Use the default remote schema repository")
+                                .conflicts_with("schema-repository")
+                                .takes_value(false)
+                        )
+                        .arg(
+                            Arg::new("schema-repository")
+                                .long("schema-repository")
+                                .about("Use a remote schema repository")
+                                .takes_value(true)
+                        )
+                )
+                .subcommand(
+                    App::new("verify")
+                        .about("Verify the integrity of the cache folder")
+                        .long_about("Verify that the cached schemas are valid and are stored under the hashes of their URLs, and that the URL hashes in the cached schema index are correct")
+                        .arg(
+                            Arg::new("cache-path")
+                                .long("cache-path")
+                                .about("Path to the cache folder")
+                                .takes_value(true)
+                                .required(true)
+                        )
+                )
+                .setting(AppSettings::SubcommandRequired)
         )
         .subcommand(
             App::new("config")
@@ -252,18 +473,7 @@ where
                 )
         )
         .setting(AppSettings::SubcommandRequired)
-        .setting(AppSettings::NoBinaryName);
-
-    match app.try_get_matches_from_mut(itr) {
-        Ok(matches) => execute(matches).await,
-        Err(err) => {
-            eprintln!("{}", err);
-            matches!(
-                err.kind,
-                clap::ErrorKind::DisplayHelp | clap::ErrorKind::DisplayVersion
-            )
-        }
-    }
+        .setting(AppSettings::NoBinaryName)
 }
 
 async fn execute(matches: ArgMatches) -> bool {
@@ -281,6 +491,10 @@ async fn execute(matches: ArgMatches) -> bool {
         set_warn_as_error(true);
     }
 
+    if matches.is_present("offline") {
+        set_offline(true);
+    }
+
     let success = match matches.subcommand() {
         Some(("config", config_matches)) => match config_matches.subcommand() {
             Some(("schema", _)) => {
@@ -296,256 +510,417 @@ async fn execute(matches: ArgMatches) -> bool {
                     formatter::Options::default(),
                 ));
 
-                print!("{}", toml::to_string_pretty(&c).unwrap());
+                print!("{}", taplo::ser::to_string(&c).unwrap());
                 !is_error()
             }
             _ => unreachable!(),
         },
         Some(("format", format_matches)) => {
-            let config = match load_config(format_matches.value_of("config")).await {
-                Ok(c) => c,
-                Err(err) => {
-                    print_message(
-                        Severity::Error,
-                        "error",
-                        &format!("failed to read configuration: {}", err),
-                    );
-                    return false;
-                }
-            };
-
-            if let Err(e) = config.check_patterns() {
-                print_message(
-                    Severity::Error,
-                    "error",
-                    &format!("invalid glob pattern in config: {}", e),
-                );
-                return false;
+            run_watched(format_matches, |changed| {
+                run_format(format_matches, changed)
+            })
+            .await
+        }
+        Some(("schema", schema_matches)) => schema::schema(schema_matches).await,
+        Some(("lint", lint_matches)) => match lint_schema_index(lint_matches).await {
+            Ok(schema_index) => {
+                run_watched(lint_matches, |changed| {
+                    run_lint(lint_matches, schema_index.clone(), changed)
+                })
+                .await
             }
+            Err(_) => false,
+        },
+        _ => unreachable!(),
+    };
 
-            let format_result = format::format(config, format_matches).await;
+    if !success && is_warn_as_error() {
+        print_message(
+            Severity::Error,
+            "failure",
+            r#"warnings occurred ("--warn-as-error" flag was used)"#,
+        );
+    }
 
-            if format_result.matched_document_count == 0 {
-                print_message(Severity::Warning, "warning", "no documents were found");
-            }
+    success
+}
 
-            if format_result.error_count > 0 {
-                print_message(
-                    Severity::Error,
-                    "failure",
-                    &format!(
-                        "processed {} {docs} with {} {errors} {excluded}",
-                        format_result.matched_document_count
-                            - format_result.excluded_document_count,
-                        format_result.error_count,
-                        docs = if format_result.matched_document_count != 1 {
-                            "documents"
-                        } else {
-                            "document"
-                        },
-                        errors = if format_result.error_count != 1 {
-                            "errors"
-                        } else {
-                            "error"
-                        },
-                        excluded = if format_result.excluded_document_count > 0 {
-                            format!(" (excluded {})", format_result.excluded_document_count)
-                        } else {
-                            format!("")
-                        }
-                    ),
-                );
-                false
-            } else {
-                print_message(
-                    Severity::Success,
-                    "success",
-                    &format!(
-                        "processed {} {docs} with no errors {excluded}",
-                        format_result.matched_document_count
-                            - format_result.excluded_document_count,
-                        docs = if format_result.matched_document_count != 1 {
-                            "documents"
-                        } else {
-                            "document"
-                        },
-                        excluded = if format_result.excluded_document_count > 0 {
-                            format!(" (excluded {})", format_result.excluded_document_count)
-                        } else {
-                            format!("")
-                        }
-                    ),
-                );
-                !is_error()
-            }
+/// Runs a command once, or in watch mode if `--watch` was given.
+async fn run_watched<F, Fut>(matches: &ArgMatches, mut run: F) -> bool
+where
+    F: FnMut(Option<Vec<String>>) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    if !matches.is_present("watch") {
+        return run(None).await;
+    }
+
+    if let Some(mut files) = matches.values_of("files") {
+        if files.any(|f| f == "-") {
+            print_message(
+                Severity::Error,
+                "error",
+                "the standard input cannot be watched",
+            );
+            return false;
         }
-        Some(("lint", lint_matches)) => {
-            let config = match load_config(lint_matches.value_of("config")).await {
-                Ok(c) => c,
-                Err(err) => {
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        watch::watch(matches.value_of("config"), run).await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        print_message(
+            Severity::Error,
+            "error",
+            "watch mode is not supported in this environment",
+        );
+        false
+    }
+}
+
+/// Returns the changed paths that match the given document paths as glob patterns,
+/// or the included paths in the configurations of the changed paths.
+///
+/// Returns `None` if none of them match.
+async fn changed_documents(
+    configs: &mut Configs,
+    matches: &ArgMatches,
+    changed: &[String],
+) -> Option<Vec<String>> {
+    let files: Option<Vec<glob::Pattern>> = matches
+        .values_of("files")
+        .map(|files| files.filter_map(|f| glob::Pattern::new(f).ok()).collect());
+
+    let mut documents = Vec::new();
+
+    for path in changed {
+        // The patterns from configuration files are absolute.
+        let absolute_path = configs.absolute_path(path);
+
+        let matches_any = |patterns: &[glob::Pattern]| {
+            patterns.iter().any(|p| {
+                p.matches_with(path, glob_match_options())
+                    || p.matches_with(&absolute_path, glob_match_options())
+            })
+        };
+
+        let included = match &files {
+            Some(patterns) => matches_any(patterns),
+            // Every document is matched against its own configuration.
+            None => match configs.for_document(Path::new(path)).await {
+                Ok(config) => matches_any(
+                    &config
+                        .get_include_paths()
+                        .iter()
+                        .filter_map(|f| glob::Pattern::new(f).ok())
+                        .collect::<Vec<_>>(),
+                ),
+                // The error is reported when the document is processed.
+                Err(_) => true,
+            },
+        };
+
+        if included {
+            documents.push(glob::Pattern::escape(path));
+        }
+    }
+
+    if documents.is_empty() {
+        None
+    } else {
+        Some(documents)
+    }
+}
+
+/// Formats the documents, only the `changed` ones if given.
+async fn run_format(format_matches: &ArgMatches, changed: Option<Vec<String>>) -> bool {
+    let mut configs = match Configs::load(format_matches.value_of("config")).await {
+        Ok(c) => c,
+        Err(err) => {
+            print_message(
+                Severity::Error,
+                "error",
+                &format!("failed to read configuration: {}", err),
+            );
+            return false;
+        }
+    };
+
+    if let Err(e) = configs.root().check_patterns() {
+        print_message(
+            Severity::Error,
+            "error",
+            &format!("invalid glob pattern in config: {}", e),
+        );
+        return false;
+    }
+
+    let changed = match changed {
+        Some(changed) => match changed_documents(&mut configs, format_matches, &changed).await {
+            Some(changed) => Some(changed),
+            None => return true,
+        },
+        None => None,
+    };
+
+    let format_result = format::format(configs, format_matches, changed.as_deref()).await;
+
+    if format_result.matched_document_count == 0 {
+        print_message(Severity::Warning, "warning", "no documents were found");
+    }
+
+    if format_result.error_count > 0 {
+        print_message(
+            Severity::Error,
+            "failure",
+            &format!(
+                "processed {} {docs} with {} {errors} {excluded}",
+                format_result.matched_document_count - format_result.excluded_document_count,
+                format_result.error_count,
+                docs = if format_result.matched_document_count != 1 {
+                    "documents"
+                } else {
+                    "document"
+                },
+                errors = if format_result.error_count != 1 {
+                    "errors"
+                } else {
+                    "error"
+                },
+                excluded = if format_result.excluded_document_count > 0 {
+                    format!(" (excluded {})", format_result.excluded_document_count)
+                } else {
+                    format!("")
+                }
+            ),
+        );
+        false
+    } else if format_matches.is_present("check") && format_result.unformatted_document_count > 0 {
+        print_message(
+            Severity::Error,
+            "failure",
+            &format!(
+                "{} {docs} not properly formatted",
+                format_result.unformatted_document_count,
+                docs = if format_result.unformatted_document_count != 1 {
+                    "documents are"
+                } else {
+                    "document is"
+                },
+            ),
+        );
+        false
+    } else {
+        print_message(
+            Severity::Success,
+            "success",
+            &format!(
+                "processed {} {docs} with no errors {excluded}",
+                format_result.matched_document_count - format_result.excluded_document_count,
+                docs = if format_result.matched_document_count != 1 {
+                    "documents"
+                } else {
+                    "document"
+                },
+                excluded = if format_result.excluded_document_count > 0 {
+                    format!(" (excluded {})", format_result.excluded_document_count)
+                } else {
+                    format!("")
+                }
+            ),
+        );
+        !is_error()
+    }
+}
+
+/// Downloads or loads the cached schema index for linting.
+async fn lint_schema_index(lint_matches: &ArgMatches) -> Result<Option<SchemaIndex>, ()> {
+    let schema_repository = if lint_matches.is_present("default-schema-repository") {
+        Some(SCHEMA_REPOSITORY)
+    } else {
+        lint_matches.value_of("schema-repository")
+    };
+
+    let mut schema_index = None;
+    if schema_repository.is_some() && is_offline() {
+        print_message(
+            Severity::Info,
+            "info",
+            "using the cached schema index in offline mode",
+        );
+    } else if let Some(v) = schema_repository {
+        print_message(
+            Severity::Info,
+            "info",
+            &format!("updating schema index from {}", v),
+        );
+        match download_schema_index(v).await {
+            Ok(idx) => {
+                if let Some(v) = lint_matches.value_of("cache-path") {
+                    if let Err(err) = cache_schema_index(&idx, v).await {
+                        print_message(
+                            Severity::Warning,
+                            "warning",
+                            &format!("failed to save schema index: {}", err),
+                        );
+                    }
+                }
+
+                schema_index = Some(idx)
+            }
+            Err(err) => {
+                if is_warn_as_error() {
                     print_message(
                         Severity::Error,
                         "error",
-                        &format!("failed to read configuration: {}", err),
+                        &format!("failed to download schema index: {}", err),
+                    );
+                    return Err(());
+                } else {
+                    print_message(
+                        Severity::Warning,
+                        "warning",
+                        &format!("failed to download schema index: {}", err),
                     );
-                    return false;
                 }
-            };
-
-            let schema_repository = if lint_matches.is_present("default-schema-repository") {
-                Some(SCHEMA_REPOSITORY)
-            } else {
-                lint_matches.value_of("schema-repository")
-            };
-
-            let mut schema_index = None;
-            if let Some(v) = schema_repository {
-                print_message(
-                    Severity::Info,
-                    "info",
-                    &format!("updating schema index from {}", v),
-                );
-                match download_schema_index(v).await {
-                    Ok(idx) => {
-                        if let Some(v) = lint_matches.value_of("cache-path") {
-                            if let Err(err) = cache_schema_index(&idx, v).await {
-                                print_message(
-                                    Severity::Warning,
-                                    "warning",
-                                    &format!("failed to save schema index: {}", err),
-                                );
-                            }
-                        }
-
-                        schema_index = Some(idx)
-                    }
-                    Err(err) => {
-                        if is_warn_as_error() {
-                            print_message(
-                                Severity::Error,
-                                "error",
-                                &format!("failed to download schema index: {}", err),
-                            );
-                            return false;
-                        } else {
-                            print_message(
-                                Severity::Warning,
-                                "warning",
-                                &format!("failed to download schema index: {}", err),
-                            );
-                        }
-                    }
-                };
             }
+        };
+    }
 
-            if let Some(cache_path) = lint_matches.value_of("cache-path") {
-                match &schema_index {
-                    Some(idx) => {
-                        print_message(Severity::Info, "info", "checking for schema updates");
-                        let (updated, errors) = update_schemas(&idx, cache_path).await;
-                        if updated > 0 {
-                            print_message(
-                                Severity::Info,
-                                "info",
-                                &format!("updated remote {} schemas", updated),
-                            );
-                        }
-                        if !errors.is_empty() {
-                            print_message(
-                                Severity::Warning,
-                                "warn",
-                                &format!("failed to update {} schemas", errors.len()),
-                            );
-                        }
-                    }
-                    None => match load_schema_index(cache_path).await {
-                        Ok(idx) => schema_index = idx,
-                        Err(err) => {
-                            print_message(
-                                Severity::Warning,
-                                "warning",
-                                &format!("failed to read schema index: {}", err),
-                            );
-                        }
-                    },
+    if let Some(cache_path) = lint_matches.value_of("cache-path") {
+        match &schema_index {
+            Some(idx) => {
+                print_message(Severity::Info, "info", "checking for schema updates");
+                let (updated, errors) = update_schemas(&idx, cache_path).await;
+                if updated > 0 {
+                    print_message(
+                        Severity::Info,
+                        "info",
+                        &format!("updated remote {} schemas", updated),
+                    );
+                }
+                if !errors.is_empty() {
+                    print_message(
+                        Severity::Warning,
+                        "warn",
+                        &format!("failed to update {} schemas", errors.len()),
+                    );
                 }
             }
+            None => match load_schema_index(cache_path).await {
+                Ok(idx) => schema_index = idx,
+                Err(err) => {
+                    print_message(
+                        Severity::Warning,
+                        "warning",
+                        &format!("failed to read schema index: {}", err),
+                    );
+                }
+            },
+        }
+    }
 
-            if let Err(e) = config.check_patterns() {
-                print_message(
-                    Severity::Error,
-                    "error",
-                    &format!("invalid glob pattern in config: {}", e),
-                );
-                return false;
-            }
-
-            let lint_result = lint::lint(config, lint_matches, schema_index).await;
+    // The built-in schemas are associated with
+    // the documents even without a schema index.
+    if schema_repository.is_some() && schema_index.is_none() {
+        schema_index = Some(SchemaIndex::default());
+    }
 
-            if lint_result.matched_document_count == 0 {
-                print_message(Severity::Warning, "warning", "no documents were found");
-            }
+    Ok(schema_index)
+}
 
-            if lint_result.error_count > 0 {
-                print_message(
-                    Severity::Error,
-                    "failure",
-                    &format!(
-                        "processed {} {docs} with {} {errors} {excluded}",
-                        lint_result.matched_document_count - lint_result.excluded_document_count,
-                        lint_result.error_count,
-                        docs = if lint_result.matched_document_count != 1 {
-                            "documents"
-                        } else {
-                            "document"
-                        },
-                        errors = if lint_result.error_count != 1 {
-                            "errors"
-                        } else {
-                            "error"
-                        },
-                        excluded = if lint_result.excluded_document_count > 0 {
-                            format!(" (excluded {})", lint_result.excluded_document_count)
-                        } else {
-                            format!("")
-                        }
-                    ),
-                );
-                false
-            } else {
-                print_message(
-                    Severity::Success,
-                    "success",
-                    &format!(
-                        "processed {} {docs} with no errors {excluded}",
-                        lint_result.matched_document_count - lint_result.excluded_document_count,
-                        docs = if lint_result.matched_document_count != 1 {
-                            "documents"
-                        } else {
-                            "document"
-                        },
-                        excluded = if lint_result.excluded_document_count > 0 {
-                            format!(" (excluded {})", lint_result.excluded_document_count)
-                        } else {
-                            format!("")
-                        }
-                    ),
-                );
-                !is_error()
-            }
+/// Lints the documents, only the `changed` ones if given.
+async fn run_lint(
+    lint_matches: &ArgMatches,
+    schema_index: Option<SchemaIndex>,
+    changed: Option<Vec<String>>,
+) -> bool {
+    let mut configs = match Configs::load(lint_matches.value_of("config")).await {
+        Ok(c) => c,
+        Err(err) => {
+            print_message(
+                Severity::Error,
+                "error",
+                &format!("failed to read configuration: {}", err),
+            );
+            return false;
         }
-        _ => unreachable!(),
     };
 
-    if !success && is_warn_as_error() {
+    if let Err(e) = configs.root().check_patterns() {
         print_message(
             Severity::Error,
-            "failure",
-            r#"warnings occurred ("--warn-as-error" flag was used)"#,
+            "error",
+            &format!("invalid glob pattern in config: {}", e),
         );
+        return false;
     }
 
-    success
+    let changed = match changed {
+        Some(changed) => match changed_documents(&mut configs, lint_matches, &changed).await {
+            Some(changed) => Some(changed),
+            None => return true,
+        },
+        None => None,
+    };
+
+    let lint_result = lint::lint(configs, lint_matches, schema_index, changed.as_deref()).await;
+
+    if lint_result.matched_document_count == 0 {
+        print_message(Severity::Warning, "warning", "no documents were found");
+    }
+
+    if lint_result.error_count > 0 {
+        print_message(
+            Severity::Error,
+            "failure",
+            &format!(
+                "processed {} {docs} with {} {errors} {excluded}",
+                lint_result.matched_document_count - lint_result.excluded_document_count,
+                lint_result.error_count,
+                docs = if lint_result.matched_document_count != 1 {
+                    "documents"
+                } else {
+                    "document"
+                },
+                errors = if lint_result.error_count != 1 {
+                    "errors"
+                } else {
+                    "error"
+                },
+                excluded = if lint_result.excluded_document_count > 0 {
+                    format!(" (excluded {})", lint_result.excluded_document_count)
+                } else {
+                    format!("")
+                }
+            ),
+        );
+        false
+    } else {
+        print_message(
+            Severity::Success,
+            "success",
+            &format!(
+                "processed {} {docs} with no errors {excluded}",
+                lint_result.matched_document_count - lint_result.excluded_document_count,
+                docs = if lint_result.matched_document_count != 1 {
+                    "documents"
+                } else {
+                    "document"
+                },
+                excluded = if lint_result.excluded_document_count > 0 {
+                    format!(" (excluded {})", lint_result.excluded_document_count)
+                } else {
+                    format!("")
+                }
+            ),
+        );
+        !is_error()
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1,38 +1,182 @@
 use crate::{
     config::Config,
-    external::{get_paths_by_glob, get_schema, read_file, read_stdin},
+    external::{get_paths_by_glob, read_file, read_stdin, run_blocking, write_file},
     print_message,
+    util::{document_paths, get_schema, jobs, Configs},
 };
+use anyhow::anyhow;
 use clap::ArgMatches;
-use pretty_lint::{PrettyLint, Severity};
+use futures::{stream, StreamExt};
+use pretty_lint::Severity;
 use regex::Regex;
 use schemars::schema::RootSchema;
-use std::collections::HashSet;
-use taplo::{dom, rowan::TextRange, schema::SchemaIndex, util::coords::Mapper};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+use taplo::{
+    analytics::Suppressions,
+    code::Code,
+    dom::{self, rewrite::Editor},
+    parser::Parse,
+    rowan::TextRange,
+    schema::{
+        builtin::associated_schema,
+        fix::{collect_fixes, FixOptions},
+        util::attach_schema,
+        SchemaIndex,
+    },
+    util::coords::{Mapper, Range},
+};
 use verify::Verifier;
 
+pub(crate) mod output;
+
+/// Dotted keys and the paths of the schemas attached to them.
+type KeySchemaPaths = Vec<(String, String)>;
+
+/// A document path, its schema path and the schema paths attached to its keys.
+type DocumentSchemaPaths = (String, Option<String>, KeySchemaPaths);
+
+/// Combined schemas by the schema paths they were created from.
+type CombinedSchemas<'s> = HashMap<(Option<&'s str>, &'s KeySchemaPaths), Arc<RootSchema>>;
+
 pub(crate) struct LintResult {
     pub matched_document_count: usize,
     pub excluded_document_count: usize,
     pub error_count: usize,
+    /// Lints collected for machine-readable output formats.
+    pub lints: Vec<Lint>,
+}
+
+/// Options shared by all the linted documents.
+#[derive(Debug, Clone)]
+struct LintOptions {
+    /// The format of the results.
+    format: OutputFormat,
+    /// The maximum amount of documents linted at the same time.
+    jobs: usize,
+    /// Fixes are applied to the documents if set.
+    fix: Option<FixOptions>,
+    cache_path: Option<String>,
+}
+
+/// The format of the lint results.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    /// Human-readable text printed to the standard error.
+    Text,
+    /// An array of JSON objects.
+    Json,
+    /// A SARIF 2.1.0 log.
+    Sarif,
+    /// A Checkstyle XML report.
+    Checkstyle,
+    /// GitHub Actions workflow commands.
+    Github,
+}
+
+impl OutputFormat {
+    pub(crate) const VALUES: &'static [&'static str] =
+        &["text", "json", "sarif", "checkstyle", "github"];
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "checkstyle" => Ok(OutputFormat::Checkstyle),
+            "github" => Ok(OutputFormat::Github),
+            _ => Err(anyhow!("invalid output format: {}", s)),
+        }
+    }
+}
+
+/// A single problem found in a document.
+pub(crate) struct Lint {
+    /// The path of the document, `None` for the standard input.
+    pub file: Option<String>,
+    pub code: Code,
+    pub severity: Severity,
+    pub message: String,
+    /// Additional information that is otherwise
+    /// only shown in the labels.
+    pub details: Option<String>,
+    /// Labelled ranges in the document, the first one is the primary location.
+    pub labels: Vec<Label>,
+    pub notes: Vec<&'static str>,
+}
+
+impl Lint {
+    /// The message with details for output formats without labels.
+    fn full_message(&self) -> String {
+        match &self.details {
+            Some(d) => format!("{}: {}", self.message, d),
+            None => self.message.clone(),
+        }
+    }
+}
+
+pub(crate) struct Label {
+    /// Zero-based range with UTF-16 character offsets.
+    pub range: Range,
+    pub message: String,
 }
 
+/// Lints the documents given in the arguments or the configuration.
+///
+/// If `changed` is given, only those documents are linted instead.
 pub(crate) async fn lint(
-    config: Config,
+    mut configs: Configs,
     m: &ArgMatches,
     schema_index: Option<SchemaIndex>,
+    changed: Option<&[String]>,
 ) -> LintResult {
     let mut res = LintResult {
         matched_document_count: 0,
         excluded_document_count: 0,
         error_count: 0,
+        lints: Vec::new(),
+    };
+
+    let format = match m.value_of("output-format").map(OutputFormat::from_str) {
+        Some(Ok(f)) => f,
+        Some(Err(err)) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            res.error_count += 1;
+            return res;
+        }
+        None => OutputFormat::Text,
+    };
+
+    let jobs = match jobs(m) {
+        Ok(j) => j,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            res.error_count += 1;
+            return res;
+        }
     };
 
     let mut schema = None;
 
     if let Some(schema_path) = m.value_of("schema") {
         match get_schema(schema_path, m.value_of("cache-path")).await {
-            Ok(s) => schema = Some(s.0),
+            Ok((s, err)) => {
+                if let Some(err) = err {
+                    print_message(
+                        Severity::Warning,
+                        "warning",
+                        &format!("schema {}: {}", schema_path, err),
+                    );
+                }
+                schema = Some(s);
+            }
             Err(err) => {
                 print_message(
                     Severity::Error,
@@ -45,44 +189,74 @@ pub(crate) async fn lint(
         }
     }
 
-    let cache_path = m.value_of("cache-path").map(|s| s.to_string());
+    let options = LintOptions {
+        format,
+        jobs,
+        fix: if m.is_present("fix") {
+            Some(FixOptions {
+                remove_additional: m.is_present("remove-unknown-keys"),
+            })
+        } else {
+            None
+        },
+        cache_path: m.value_of("cache-path").map(|s| s.to_string()),
+    };
 
-    if let Some(files) = m.values_of("files") {
+    if let Some(changed) = changed {
         lint_paths(
-            &config,
+            &mut configs,
+            schema_index.as_ref(),
+            schema,
+            changed.iter().map(|s| s.as_ref()),
+            &options,
+            &mut res,
+            !m.is_present("files"),
+        )
+        .await;
+    } else if let Some(files) = m.values_of("files") {
+        lint_paths(
+            &mut configs,
             schema_index.as_ref(),
             schema,
-            cache_path,
             files,
+            &options,
             &mut res,
             false,
         )
         .await;
     } else {
+        let include = configs.root().get_include_paths();
+
         lint_paths(
-            &config,
+            &mut configs,
             schema_index.as_ref(),
             schema,
-            cache_path,
-            config.get_include_paths().iter().map(|s| s.as_ref()),
+            include.iter().map(|s| s.as_ref()),
+            &options,
             &mut res,
             true,
         )
         .await;
     }
 
+    if options.format != OutputFormat::Text {
+        output::print(options.format, &res.lints);
+    }
+
     res
 }
 
 async fn lint_paths<'i, F: Iterator<Item = &'i str>>(
-    config: &Config,
+    configs: &mut Configs,
     schema_index: Option<&SchemaIndex>,
     schema: Option<RootSchema>,
-    cache_path: Option<String>,
     files: F,
+    options: &LintOptions,
     res: &mut LintResult,
     allow_exclude: bool,
 ) {
+    let mut paths = Vec::new();
+
     for val in files {
         if val == "-" {
             let src = match read_stdin() {
@@ -94,507 +268,457 @@ async fn lint_paths<'i, F: Iterator<Item = &'i str>>(
                 }
             };
 
-            lint_source(None, schema.as_ref(), &src, res);
+            let lints = lint_source(None, schema.as_ref(), &src);
+            report_lints(&src, lints, options.format, res);
             res.matched_document_count += 1;
             continue;
         }
 
         match get_paths_by_glob(val) {
-            Ok(sources) => {
-                for path in sources {
-                    match read_file(path.to_str().unwrap()).await {
-                        Ok(src) => {
-                            res.matched_document_count += 1;
-
-                            if allow_exclude {
-                                // Don't format taplo config files unless asked explicitly.
-                                if let Some(file_name) = path.file_name() {
-                                    if file_name == "taplo.toml" || file_name == ".taplo.toml" {
-                                        // Don't count it as excluded.
-                                        continue;
-                                    }
-                                }
-
-                                if let Some(p) = path.to_str() {
-                                    match config.is_excluded(p) {
-                                        Ok(excluded) => {
-                                            if excluded {
-                                                res.excluded_document_count += 1;
-                                                continue;
-                                            }
-                                        }
-                                        Err(err) => {
-                                            print_message(
-                                                Severity::Error,
-                                                "error",
-                                                &err.to_string(),
-                                            );
-                                            res.error_count += 1;
-                                            return;
-                                        }
-                                    }
-                                }
-                            }
-
-                            let str_src = match std::str::from_utf8(&src) {
-                                Ok(s) => s,
-                                Err(err) => {
-                                    print_message(
-                                        Severity::Error,
-                                        "error",
-                                        &format!("file {:?} is not valid UTF-8: {}", path, err),
-                                    );
-                                    res.error_count += 1;
-                                    continue;
-                                }
-                            };
-
-                            match &schema {
-                                Some(s) => {
-                                    lint_source(path.to_str(), Some(s), str_src, res);
-                                }
-                                None => {
-                                    let schema_path = match config
-                                        .get_schema_path(path.to_str().unwrap())
-                                    {
-                                        Ok(p) => match p {
-                                            Some(p) => p,
-                                            None => match schema_index {
-                                                Some(idx) => {
-                                                    match idx.schemas.iter().find(|s| {
-                                                        s.extra.patterns.iter().any(|p| {
-                                                            Regex::new(p)
-                                                                .ok()
-                                                                .map(|r| {
-                                                                    r.is_match(
-                                                                        path.to_str().unwrap(),
-                                                                    )
-                                                                })
-                                                                .unwrap_or(false)
-                                                        })
-                                                    }) {
-                                                        Some(s) => s.url.clone(),
-                                                        None => {
-                                                            lint_source(
-                                                                path.to_str(),
-                                                                None,
-                                                                str_src,
-                                                                res,
-                                                            );
-                                                            continue;
-                                                        }
-                                                    }
-                                                }
-                                                None => {
-                                                    lint_source(path.to_str(), None, str_src, res);
-                                                    continue;
-                                                }
-                                            },
-                                        },
-                                        Err(err) => {
-                                            print_message(
-                                                Severity::Error,
-                                                "error",
-                                                &format!("invalid config: {}", err),
-                                            );
-                                            res.error_count += 1;
-                                            continue;
-                                        }
-                                    };
-
-                                    match get_schema(&schema_path, cache_path.as_ref()).await {
-                                        Ok(schema) => {
-                                            lint_source(
-                                                path.to_str(),
-                                                Some(&schema.0),
-                                                str_src,
-                                                res,
-                                            );
-                                        }
-                                        Err(err) => {
-                                            print_message(
-                                                Severity::Error,
-                                                "error",
-                                                &format!("could not load schema: {}", err),
-                                            );
-                                            res.error_count += 1;
-                                            continue;
-                                        }
-                                    };
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            print_message(Severity::Error, "error", &err.to_string());
-                            res.error_count += 1;
+            Ok(sources) => paths.extend(sources),
+            Err(e) => {
+                print_message(Severity::Error, "error", &e.to_string());
+                res.error_count += 1;
+                break;
+            }
+        }
+    }
+
+    let (paths, excluded) = match document_paths(configs, paths, allow_exclude).await {
+        Ok(p) => p,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            res.error_count += 1;
+            return;
+        }
+    };
+
+    res.matched_document_count += paths.len() + excluded;
+    res.excluded_document_count += excluded;
+
+    let schema = schema.map(Arc::new);
+
+    // The schema paths for every document,
+    // and the schema paths attached to keys in it.
+    let mut documents: Vec<DocumentSchemaPaths> = Vec::with_capacity(paths.len());
+
+    for (path, config) in paths {
+        let path = match path.to_str() {
+            Some(p) => p.to_string(),
+            None => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("invalid file path: {:?}", path),
+                );
+                res.error_count += 1;
+                continue;
+            }
+        };
+
+        if schema.is_some() {
+            documents.push((path, None, Vec::new()));
+            continue;
+        }
+
+        let absolute_path = configs.absolute_path(&path);
+
+        match (
+            schema_path(&config, schema_index, &absolute_path),
+            config.get_key_schema_paths(&absolute_path),
+        ) {
+            (Ok(schema_path), Ok(key_schema_paths)) => {
+                documents.push((path, schema_path, key_schema_paths))
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("invalid config: {}", err),
+                );
+                res.error_count += 1;
+            }
+        }
+    }
+
+    // Every schema is loaded only once, no matter how many documents use it.
+    let schema_paths: BTreeSet<&str> = documents
+        .iter()
+        .flat_map(|(_, schema_path, key_schema_paths)| {
+            schema_path
+                .as_deref()
+                .into_iter()
+                .chain(key_schema_paths.iter().map(|(_, p)| p.as_str()))
+        })
+        .collect();
+
+    let cache_path = options.cache_path.as_ref();
+    let loaded_schemas: HashMap<&str, Option<Arc<RootSchema>>> = stream::iter(schema_paths)
+        .map(|schema_path| async move {
+            match get_schema(schema_path, cache_path).await {
+                Ok((s, err)) => {
+                    if let Some(err) = err {
+                        print_message(
+                            Severity::Warning,
+                            "warning",
+                            &format!("schema {}: {}", schema_path, err),
+                        );
+                    }
+                    (schema_path, Ok(Arc::new(s)))
+                }
+                Err(err) => (schema_path, Err(err)),
+            }
+        })
+        .buffered(options.jobs)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .map(|(schema_path, schema)| match schema {
+            Ok(s) => (schema_path, Some(s)),
+            Err(err) => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("could not load schema {}: {}", schema_path, err),
+                );
+                res.error_count += 1;
+                (schema_path, None)
+            }
+        })
+        .collect();
+
+    // Documents with schemas attached to their keys have their own schemas,
+    // these are shared between documents with the same schemas.
+    let mut combined_schemas: CombinedSchemas = HashMap::new();
+
+    let mut linted_documents = Vec::with_capacity(documents.len());
+
+    'documents: for (path, schema_path, key_schema_paths) in &documents {
+        let loaded = |schema_path: &str| loaded_schemas.get(schema_path).cloned().flatten();
+
+        let mut document_schema = match schema_path {
+            Some(schema_path) => match loaded(schema_path) {
+                Some(s) => Some(s),
+                None => continue,
+            },
+            None => schema.clone(),
+        };
+
+        if !key_schema_paths.is_empty() {
+            let combined_key = (schema_path.as_deref(), key_schema_paths);
+
+            match combined_schemas.get(&combined_key) {
+                Some(s) => document_schema = Some(s.clone()),
+                None => {
+                    let mut combined = document_schema.as_deref().cloned().unwrap_or_default();
+
+                    for (keys, key_schema_path) in key_schema_paths {
+                        match loaded(key_schema_path) {
+                            Some(s) => attach_schema(&mut combined, keys, (*s).clone()),
+                            None => continue 'documents,
                         }
                     }
+
+                    let combined = Arc::new(combined);
+                    combined_schemas.insert(combined_key, combined.clone());
+                    document_schema = Some(combined);
                 }
             }
-            Err(e) => {
-                print_message(Severity::Error, "error", &e.to_string());
+        }
+
+        linted_documents.push((path.clone(), document_schema));
+    }
+
+    let mut results = stream::iter(
+        linted_documents
+            .into_iter()
+            .map(|(path, document_schema)| lint_file(path, document_schema, options.fix)),
+    )
+    .buffered(options.jobs);
+
+    while let Some(result) = results.next().await {
+        match result {
+            Ok((src, lints)) => report_lints(&src, lints, options.format, res),
+            Err(err) => {
+                print_message(Severity::Error, "error", &err.to_string());
                 res.error_count += 1;
-                break;
             }
         }
     }
 }
 
-fn lint_source(path: Option<&str>, schema: Option<&RootSchema>, src: &str, res: &mut LintResult) {
-    let parse = taplo::parser::parse(src);
+/// Returns the path of the schema for the document from
+/// either the configuration or the schema index.
+///
+/// If a schema index is used, the built-in schemas
+/// are associated with the documents as well.
+fn schema_path(
+    config: &Config,
+    schema_index: Option<&SchemaIndex>,
+    path: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    if let Some(p) = config.get_schema_path(path)? {
+        return Ok(Some(p));
+    }
+
+    Ok(schema_index.and_then(|idx| {
+        idx.schemas
+            .iter()
+            .find(|s| {
+                s.extra.patterns.iter().any(|p| {
+                    Regex::new(p)
+                        .ok()
+                        .map(|r| r.is_match(path))
+                        .unwrap_or(false)
+                })
+            })
+            .map(|s| s.url.clone())
+            .or_else(|| associated_schema(path).map(|builtin| builtin.url()))
+    }))
+}
+
+/// Reads and lints a document, returning its source and the lints.
+///
+/// If fixes are requested and the document has a schema,
+/// the fixes are written first and the fixed document is linted.
+async fn lint_file(
+    path: String,
+    schema: Option<Arc<RootSchema>>,
+    fix: Option<FixOptions>,
+) -> Result<(String, Vec<Lint>), anyhow::Error> {
+    let src = read_file(&path).await?;
+
+    let mut src = match String::from_utf8(src) {
+        Ok(s) => s,
+        Err(err) => return Err(anyhow!("file {:?} is not valid UTF-8: {}", path, err)),
+    };
+
+    if let (Some(options), Some(schema)) = (fix, schema.clone()) {
+        let (fixed, fix_count) = run_blocking(move || fix_source(src, &schema, options)).await;
+
+        if fix_count > 0 {
+            write_file(&path, fixed.as_bytes()).await?;
+            print_message(
+                Severity::Info,
+                "info",
+                &format!(
+                    "applied {} fix{} to {}",
+                    fix_count,
+                    if fix_count != 1 { "es" } else { "" },
+                    path
+                ),
+            );
+        }
+
+        src = fixed;
+    }
+
+    Ok(run_blocking(move || {
+        let lints = lint_source(Some(&path), schema.as_deref(), &src);
+        (src, lints)
+    })
+    .await)
+}
+
+/// Counts the errors and prints the lints,
+/// or collects them for the machine-readable formats.
+fn report_lints(src: &str, lints: Vec<Lint>, format: OutputFormat, res: &mut LintResult) {
+    res.error_count += lints
+        .iter()
+        .filter(|l| matches!(l.severity, Severity::Error))
+        .count();
+
+    match format {
+        OutputFormat::Text => {
+            for lint in &lints {
+                output::print_pretty(src, lint);
+            }
+        }
+        _ => res.lints.extend(lints),
+    }
+}
+
+/// Applies the schema fixes that are not suppressed,
+/// returning the fixed source and the number of applied fixes.
+///
+/// Documents with errors other than schema errors are not fixed.
+fn fix_source(src: String, schema: &RootSchema, options: FixOptions) -> (String, usize) {
+    let parse = taplo::parser::parse(&src);
+
+    if !parse.errors.is_empty() {
+        return (src, 0);
+    }
+
+    let suppressions = Suppressions::collect_from_syntax(parse.clone().into_syntax());
+
+    let mut editor = Editor::new(parse);
+
+    if !editor.dom().errors().is_empty() {
+        return (src, 0);
+    }
 
+    let fix_count = collect_fixes(editor.dom(), schema, options)
+        .into_iter()
+        .filter(|fix| {
+            !suppressions.is_suppressed(Code::Schema, Some(fix.range.start()))
+                && fix.apply(&mut editor).is_ok()
+        })
+        .count();
+
+    if fix_count == 0 {
+        return (src, 0);
+    }
+
+    (editor.to_string(), fix_count)
+}
+
+fn lint_source(path: Option<&str>, schema: Option<&RootSchema>, src: &str) -> Vec<Lint> {
     let mapper = Mapper::new_utf16(src, false);
 
-    // So that same range lint aren't applied twice.
-    let mut ranges: HashSet<TextRange> = HashSet::new();
+    let parse = taplo::parser::parse(src);
+    let suppressions = Suppressions::collect_from_syntax(parse.clone().into_syntax());
+
+    let mut lints = collect_lints(path, schema, parse, &mapper);
+
+    lints.retain(|lint| {
+        let offset = lint
+            .labels
+            .first()
+            .and_then(|label| mapper.offset(label.range.start));
 
-    let fpath = match path {
-        Some(p) => p,
-        None => "",
+        !suppressions.is_suppressed(lint.code, offset)
+    });
+
+    lints
+}
+
+fn collect_lints(
+    path: Option<&str>,
+    schema: Option<&RootSchema>,
+    parse: Parse,
+    mapper: &Mapper,
+) -> Vec<Lint> {
+    let mut lints = Vec::new();
+
+    let lint = |code: Code, message: &str| Lint {
+        file: path.map(|p| p.to_string()),
+        code,
+        severity: Severity::Error,
+        message: message.to_string(),
+        details: None,
+        labels: Vec::new(),
+        notes: Vec::new(),
+    };
+
+    let label = |range: TextRange, message: &str| Label {
+        range: mapper.range(range).unwrap(),
+        message: message.to_string(),
     };
 
     if !parse.errors.is_empty() {
+        // So that same range lint aren't applied twice.
+        let mut ranges: HashSet<TextRange> = HashSet::new();
+
         for err in &parse.errors {
-            if ranges.contains(&err.range) {
+            if !ranges.insert(err.range) {
                 continue;
             }
-            res.error_count += 1;
 
-            eprintln!(
-                "{}",
-                &PrettyLint::error(src)
-                    .with_file_path(fpath)
-                    .with_message("invalid syntax")
-                    .at({
-                        let r = mapper.range(err.range).unwrap();
-                        pretty_lint::Span {
-                            start: pretty_lint::Position {
-                                line: r.start.line as usize + 1,
-                                col: r.start.character as usize + 1,
-                            },
-                            end: pretty_lint::Position {
-                                line: r.end.line as usize + 1,
-                                col: r.end.character as usize,
-                            },
-                        }
-                    })
-                    .with_inline_message(&err.message)
-                    .to_string(),
-            );
-
-            ranges.insert(err.range);
+            let mut l = lint(err.code, "invalid syntax");
+            l.details = Some(err.message.clone());
+            l.labels.push(label(err.range, &err.message));
+            lints.push(l);
         }
 
-        return;
+        return lints;
     }
 
     let dom = parse.into_dom();
-    ranges.clear();
 
     if !dom.errors().is_empty() {
         for err in dom.errors() {
-            match err {
+            let l = match err {
                 dom::Error::DuplicateKey { first, second } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(&format!(
-                                r#"duplicate key "{}""#,
-                                second.full_key_string()
-                            ))
-                            .at({
-                                let r = mapper.range(second.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("defined here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(first.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("already defined here"),
-                            )
-                            .to_string(),
+                    let mut l = lint(
+                        err.code(),
+                        &format!(r#"duplicate key "{}""#, second.full_key_string()),
                     );
+                    l.labels
+                        .push(label(second.text_ranges()[0], "defined here"));
+                    l.labels
+                        .push(label(first.text_ranges()[0], "already defined here"));
+                    l
                 }
                 dom::Error::ExpectedTableArray { target, key } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(&format!(
-                                r#"entry "{}" conflicts with array of tables"#,
-                                key.full_key_string()
-                            ))
-                            .at({
-                                let r = mapper.range(key.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("entry defined here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(target.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("array of tables here"),
-                            )
-                            .to_string(),
+                    let mut l = lint(
+                        err.code(),
+                        &format!(
+                            r#"entry "{}" conflicts with array of tables"#,
+                            key.full_key_string()
+                        ),
                     );
+                    l.labels
+                        .push(label(key.text_ranges()[0], "entry defined here"));
+                    l.labels
+                        .push(label(target.text_ranges()[0], "array of tables here"));
+                    l
                 }
                 dom::Error::ExpectedTable { target, key } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(&format!(
-                                r#"expected "{}" to be a table"#,
-                                target.full_key_string()
-                            ))
-                            .at({
-                                let r = mapper.range(target.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("entry defined here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(key.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("required by this"),
-                            )
-                            .to_string(),
+                    let mut l = lint(
+                        err.code(),
+                        &format!(r#"expected "{}" to be a table"#, target.full_key_string()),
                     );
+                    l.labels
+                        .push(label(target.text_ranges()[0], "entry defined here"));
+                    l.labels
+                        .push(label(key.text_ranges()[0], "required by this"));
+                    l
                 }
                 dom::Error::InlineTable { target, key } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(&format!(
-                                r#"inline table "{}" cannot be modified"#,
-                                target.full_key_string()
-                            ))
-                            .at({
-                                let r = mapper.range(target.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("inline table here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(key.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("modified here"),
-                            )
-                            .to_string(),
+                    let mut l = lint(
+                        err.code(),
+                        &format!(
+                            r#"inline table "{}" cannot be modified"#,
+                            target.full_key_string()
+                        ),
                     );
+                    l.labels
+                        .push(label(target.text_ranges()[0], "inline table here"));
+                    l.labels.push(label(key.text_ranges()[0], "modified here"));
+                    l
                 }
                 dom::Error::Spanned { range, message } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(message)
-                            .at({
-                                let r = mapper.range(*range).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("inline table here")
-                            .to_string(),
-                    );
-                }
-                dom::Error::Generic(g) => {
-                    if fpath.is_empty() {
-                        print_message(Severity::Error, "error", g);
-                    } else {
-                        print_message(Severity::Error, "error", &format!("({}) {}", fpath, g));
-                    }
+                    let mut l = lint(err.code(), message);
+                    l.labels.push(label(*range, "inline table here"));
+                    l
                 }
+                dom::Error::Generic(g) => lint(err.code(), g),
                 dom::Error::DottedKeyConflict { first, second } => {
-                    eprintln!("{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(
-                                r#"conflicting dotted keys"#,
-                            )
-                            .at({
-                                let r = mapper.range(first.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("conflicting keys here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(second.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("already defined here"),
-                            )
-                            .with_notes(
-                                &[
-                                    "entries sharing dotted keys cannot fully define intermediate tables and values",
-                                    "make sure that the path consists of equal amount of keys to avoid conflicts",
-                                ]
-                            )
-                            .to_string(),
-                    );
+                    let mut l = lint(err.code(), "conflicting dotted keys");
+                    l.labels
+                        .push(label(first.text_ranges()[0], "conflicting keys here"));
+                    l.labels
+                        .push(label(second.text_ranges()[0], "already defined here"));
+                    l.notes = vec![
+                        "entries sharing dotted keys cannot fully define intermediate tables and values",
+                        "make sure that the path consists of equal amount of keys to avoid conflicts",
+                    ];
+                    l
                 }
                 dom::Error::SubTableBeforeTableArray { target, key } => {
-                    eprintln!(
-                        "{}",
-                        &PrettyLint::error(src)
-                            .with_file_path(fpath)
-                            .with_message(r#"subtable is before array of tables"#)
-                            .at({
-                                let r = mapper.range(target.text_ranges()[0]).unwrap();
-                                pretty_lint::Span {
-                                    start: pretty_lint::Position {
-                                        line: r.start.line as usize + 1,
-                                        col: r.start.character as usize + 1,
-                                    },
-                                    end: pretty_lint::Position {
-                                        line: r.end.line as usize + 1,
-                                        col: r.end.character as usize,
-                                    },
-                                }
-                            })
-                            .with_inline_message("array of tables here")
-                            .and(
-                                PrettyLint::error(src)
-                                    .with_file_path(fpath)
-                                    .at({
-                                        let r = mapper.range(key.text_ranges()[0]).unwrap();
-                                        pretty_lint::Span {
-                                            start: pretty_lint::Position {
-                                                line: r.start.line as usize + 1,
-                                                col: r.start.character as usize + 1,
-                                            },
-                                            end: pretty_lint::Position {
-                                                line: r.end.line as usize + 1,
-                                                col: r.end.character as usize,
-                                            },
-                                        }
-                                    })
-                                    .with_inline_message("already defined here"),
-                            )
-                            .to_string(),
-                    );
+                    let mut l = lint(err.code(), "subtable is before array of tables");
+                    l.labels
+                        .push(label(target.text_ranges()[0], "array of tables here"));
+                    l.labels
+                        .push(label(key.text_ranges()[0], "already defined here"));
+                    l
                 }
             };
 
-            res.error_count += 1;
+            lints.push(l);
         }
 
-        return;
+        return lints;
     }
 
     if let Some(s) = schema {
@@ -607,33 +731,15 @@ fn lint_source(path: Option<&str>, schema: Option<&RootSchema>, src: &str, res:
                     }
                 };
 
-                let err_str: &str = &err.value.to_string();
-
-                let mut p_lint = PrettyLint::error(src)
-                    .with_message("failed schema validation")
-                    .at({
-                        let r = mapper.range(range).unwrap();
-                        pretty_lint::Span {
-                            start: pretty_lint::Position {
-                                line: r.start.line as usize + 1,
-                                col: r.start.character as usize + 1,
-                            },
-                            end: pretty_lint::Position {
-                                line: r.end.line as usize + 1,
-                                col: r.end.character as usize,
-                            },
-                        }
-                    })
-                    .with_inline_message(err_str);
-
-                if let Some(p) = path {
-                    p_lint = p_lint.with_file_path(p);
-                }
-
-                eprintln!("{}", &p_lint.to_string());
+                let err_str = err.value.to_string();
 
-                res.error_count += 1;
+                let mut l = lint(Code::Schema, "failed schema validation");
+                l.labels.push(label(range, &err_str));
+                l.details = Some(err_str);
+                lints.push(l);
             }
         }
     }
+
+    lints
 }
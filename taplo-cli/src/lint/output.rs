@@ -0,0 +1,311 @@
+//! Printing lints in the supported output formats.
+//!
+//! Locations in the machine-readable formats are one-based,
+//! and the end columns are exclusive.
+
+use super::{Label, Lint, OutputFormat};
+use crate::print_message;
+use pretty_lint::{PrettyLint, Severity};
+use serde_json::{json, Value};
+use taplo::{code::Code, util::coords::Range};
+
+/// Prints a lint in a human-readable format to the standard error.
+pub(super) fn print_pretty(src: &str, lint: &Lint) {
+    let fpath = lint.file.as_deref().unwrap_or("");
+
+    let mut labels = lint.labels.iter();
+
+    let primary = match labels.next() {
+        Some(l) => l,
+        None => {
+            if fpath.is_empty() {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("{} [{}]", lint.message, lint.code),
+                );
+            } else {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("({}) {} [{}]", fpath, lint.message, lint.code),
+                );
+            }
+            return;
+        }
+    };
+
+    let message = format!("{} [{}]", lint.message, lint.code);
+
+    let mut p_lint = PrettyLint::error(src)
+        .with_file_path(fpath)
+        .with_message(&message)
+        .at(span(&primary.range))
+        .with_inline_message(&primary.message);
+
+    for label in labels {
+        p_lint = p_lint.and(
+            PrettyLint::error(src)
+                .with_file_path(fpath)
+                .at(span(&label.range))
+                .with_inline_message(&label.message),
+        );
+    }
+
+    if !lint.notes.is_empty() {
+        p_lint = p_lint.with_notes(&lint.notes);
+    }
+
+    eprintln!("{}", &p_lint.to_string());
+}
+
+/// Prints all the lints in the given format to the standard output.
+pub(super) fn print(format: OutputFormat, lints: &[Lint]) {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&json(lints)).unwrap()),
+        OutputFormat::Sarif => println!("{}", serde_json::to_string_pretty(&sarif(lints)).unwrap()),
+        OutputFormat::Checkstyle => print!("{}", checkstyle(lints)),
+        OutputFormat::Github => print!("{}", github(lints)),
+    }
+}
+
+pub(crate) fn json(lints: &[Lint]) -> Value {
+    Value::Array(
+        lints
+            .iter()
+            .map(|lint| {
+                json!({
+                    "file": lint.file,
+                    "code": lint.code.id(),
+                    "name": lint.code.name(),
+                    "severity": match lint.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                        Severity::Info | Severity::Success => "info",
+                    },
+                    "message": lint.full_message(),
+                    "range": lint.labels.first().map(|l| json_range(&l.range)),
+                    "labels": lint.labels.iter().map(|l| json!({
+                        "range": json_range(&l.range),
+                        "message": l.message,
+                    })).collect::<Vec<_>>(),
+                    "notes": lint.notes,
+                })
+            })
+            .collect(),
+    )
+}
+
+fn json_range(range: &Range) -> Value {
+    json!({
+        "start": {
+            "line": range.start.line + 1,
+            "column": range.start.character + 1,
+        },
+        "end": {
+            "line": range.end.line + 1,
+            "column": range.end.character + 1,
+        },
+    })
+}
+
+pub(crate) fn sarif(lints: &[Lint]) -> Value {
+    let mut rules: Vec<Code> = lints.iter().map(|l| l.code).collect();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let location = |file: &Option<String>, label: &Label| {
+        json!({
+            "physicalLocation": {
+                "artifactLocation": {
+                    "uri": file.as_deref().unwrap_or("-").replace('\\', "/"),
+                },
+                "region": {
+                    "startLine": label.range.start.line + 1,
+                    "startColumn": label.range.start.character + 1,
+                    "endLine": label.range.end.line + 1,
+                    "endColumn": label.range.end.character + 1,
+                },
+            },
+        })
+    };
+
+    let results: Vec<Value> = lints
+        .iter()
+        .map(|lint| {
+            let mut labels = lint.labels.iter();
+
+            json!({
+                "ruleId": lint.code.id(),
+                "level": match lint.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info | Severity::Success => "note",
+                },
+                "message": {
+                    "text": lint.full_message(),
+                },
+                "locations": labels
+                    .next()
+                    .map(|l| location(&lint.file, l))
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+                "relatedLocations": labels.enumerate().map(|(i, l)| {
+                    let mut loc = location(&lint.file, l);
+                    loc["id"] = json!(i);
+                    loc["message"] = json!({ "text": l.message });
+                    loc
+                }).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "taplo",
+                    "informationUri": "https://taplo.tamasfe.dev",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|r| json!({
+                        "id": r.id(),
+                        "name": r.name(),
+                    })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+pub(crate) fn checkstyle(lints: &[Lint]) -> String {
+    let mut files: Vec<(&str, Vec<&Lint>)> = Vec::new();
+
+    for lint in lints {
+        let file = lint.file.as_deref().unwrap_or("-");
+
+        match files.iter_mut().find(|(f, _)| *f == file) {
+            Some((_, file_lints)) => file_lints.push(lint),
+            None => files.push((file, vec![lint])),
+        }
+    }
+
+    let mut s =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+
+    for (file, file_lints) in files {
+        s += &format!("  <file name=\"{}\">\n", xml_escape(file));
+
+        for lint in file_lints {
+            s += "    <error";
+
+            if let Some(label) = lint.labels.first() {
+                s += &format!(
+                    " line=\"{}\" column=\"{}\"",
+                    label.range.start.line + 1,
+                    label.range.start.character + 1
+                );
+            }
+
+            s += &format!(
+                " severity=\"{}\" message=\"{}\" source=\"taplo.{}\"/>\n",
+                match lint.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info | Severity::Success => "info",
+                },
+                xml_escape(&lint.full_message()),
+                lint.code
+            );
+        }
+
+        s += "  </file>\n";
+    }
+
+    s += "</checkstyle>\n";
+    s
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            '\n' => escaped += "&#10;",
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// GitHub Actions workflow commands that annotate the files.
+pub(crate) fn github(lints: &[Lint]) -> String {
+    let mut s = String::new();
+
+    for lint in lints {
+        let mut properties = Vec::new();
+
+        if let Some(file) = &lint.file {
+            properties.push(format!("file={}", github_escape_property(file)));
+        }
+
+        if let Some(label) = lint.labels.first() {
+            properties.push(format!("line={}", label.range.start.line + 1));
+            properties.push(format!("col={}", label.range.start.character + 1));
+            properties.push(format!("endLine={}", label.range.end.line + 1));
+            properties.push(format!("endColumn={}", label.range.end.character + 1));
+        }
+
+        properties.push(format!(
+            "title={}",
+            github_escape_property(&format!("taplo {} ({})", lint.code.id(), lint.code.name()))
+        ));
+
+        s += &format!(
+            "::{} {}::{}\n",
+            match lint.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info | Severity::Success => "notice",
+            },
+            properties.join(","),
+            github_escape_data(&lint.full_message())
+        );
+    }
+
+    s
+}
+
+fn github_escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn github_escape_property(s: &str) -> String {
+    github_escape_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+fn span(range: &Range) -> pretty_lint::Span {
+    pretty_lint::Span {
+        start: pretty_lint::Position {
+            line: range.start.line as usize + 1,
+            col: range.start.character as usize + 1,
+        },
+        end: pretty_lint::Position {
+            line: range.end.line as usize + 1,
+            col: range.end.character as usize,
+        },
+    }
+}
@@ -0,0 +1,374 @@
+//! Listing, downloading, exporting and verifying cached schemas.
+//!
+//! The cache folder contains the schema index as `schema_index.json`,
+//! and every schema as `schemas/<url hash>.json`, where the hash
+//! is the SHA-256 hash of the URL of the schema.
+//!
+//! Exported schemas have the same layout, so the export
+//! can be used as the cache folder without network access.
+
+use crate::{
+    external::{
+        download_schema, download_schema_index, file_exists, get_paths_by_glob, load_schema_index,
+        read_file,
+    },
+    is_error, is_offline, print_message,
+    util::{cache_schema, cache_schema_index, cached_schema_path, url_hash},
+    SCHEMA_REPOSITORY,
+};
+use anyhow::anyhow;
+use clap::ArgMatches;
+use pretty_lint::Severity;
+use schemars::schema::RootSchema;
+use std::path::Path;
+use taplo::schema::{bundle::SchemaBundle, CachedSchema, SchemaIndex};
+
+pub(crate) async fn schema(matches: &ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("list", m)) => list(m).await,
+        Some(("fetch", m)) => fetch(m).await,
+        Some(("export", m)) => export(m).await,
+        Some(("verify", m)) => verify(m).await,
+        _ => unreachable!(),
+    }
+}
+
+async fn list(m: &ArgMatches) -> bool {
+    let index = match schema_index(m).await {
+        Ok(idx) => idx,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            return false;
+        }
+    };
+
+    for schema in &index.schemas {
+        let cached = m
+            .value_of("cache-path")
+            .and_then(|p| cached_schema_path(p, &schema.url).to_str().map(file_exists))
+            .unwrap_or(false);
+
+        println!(
+            "{}\t{}{}",
+            schema.url,
+            schema.title.as_deref().unwrap_or(""),
+            if cached { "\t(cached)" } else { "" }
+        );
+    }
+
+    true
+}
+
+async fn fetch(m: &ArgMatches) -> bool {
+    let cache_path = m.value_of("cache-path").unwrap();
+
+    let urls = match schema_urls(m).await {
+        Ok(urls) => urls,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            return false;
+        }
+    };
+
+    let mut stored = 0;
+
+    for url in urls {
+        match store_schema(&url, cache_path, None).await {
+            Ok(count) => stored += count,
+            Err(err) => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("failed to download schema {}: {}", url, err),
+                );
+            }
+        }
+    }
+
+    print_message(
+        Severity::Info,
+        "info",
+        &format!("downloaded {} schemas into {}", stored, cache_path),
+    );
+
+    !is_error()
+}
+
+pub(crate) async fn export(m: &ArgMatches) -> bool {
+    let output = m.value_of("output").unwrap();
+    let cache_path = m.value_of("cache-path");
+
+    let urls = if m.is_present("urls") {
+        match schema_urls(m).await {
+            Ok(urls) => urls,
+            Err(err) => {
+                print_message(Severity::Error, "error", &err.to_string());
+                return false;
+            }
+        }
+    } else {
+        let index = match schema_index(m).await {
+            Ok(idx) => idx,
+            Err(err) => {
+                print_message(Severity::Error, "error", &err.to_string());
+                return false;
+            }
+        };
+
+        if let Err(err) = cache_schema_index(&index, output).await {
+            print_message(
+                Severity::Error,
+                "error",
+                &format!("failed to export the schema index: {}", err),
+            );
+            return false;
+        }
+
+        index.schemas.into_iter().map(|s| s.url).collect()
+    };
+
+    let mut stored = 0;
+
+    for url in urls {
+        match store_schema(&url, output, cache_path).await {
+            Ok(count) => stored += count,
+            Err(err) => {
+                print_message(
+                    Severity::Error,
+                    "error",
+                    &format!("failed to export schema {}: {}", url, err),
+                );
+            }
+        }
+    }
+
+    print_message(
+        Severity::Info,
+        "info",
+        &format!("exported {} schemas into {}", stored, output),
+    );
+
+    !is_error()
+}
+
+async fn verify(m: &ArgMatches) -> bool {
+    let cache_path = m.value_of("cache-path").unwrap();
+
+    let verification = match verify_cache(cache_path).await {
+        Ok(v) => v,
+        Err(err) => {
+            print_message(Severity::Error, "error", &err.to_string());
+            return false;
+        }
+    };
+
+    for warning in &verification.warnings {
+        print_message(Severity::Warning, "warning", warning);
+    }
+
+    for error in &verification.errors {
+        print_message(Severity::Error, "error", error);
+    }
+
+    if is_error() {
+        return false;
+    }
+
+    print_message(
+        Severity::Success,
+        "success",
+        &format!("verified {} cached schemas", verification.schema_count),
+    );
+
+    true
+}
+
+/// The problems found in a cache folder.
+#[derive(Debug, Default)]
+pub(crate) struct CacheVerification {
+    /// The amount of cached schemas.
+    pub schema_count: usize,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Verifies the cached schema index and the cached schemas.
+///
+/// Returns an error only if the cached schemas could not be listed.
+pub(crate) async fn verify_cache(cache_path: &str) -> Result<CacheVerification, anyhow::Error> {
+    let mut verification = CacheVerification::default();
+
+    match load_schema_index(cache_path).await {
+        Ok(Some(index)) => {
+            for schema in &index.schemas {
+                if url_hash(&schema.url) != schema.url_hash {
+                    verification.errors.push(format!(
+                        "the URL hash of {} in the schema index is incorrect",
+                        schema.url
+                    ));
+                }
+            }
+        }
+        Ok(None) => verification
+            .warnings
+            .push(format!("there is no schema index in {}", cache_path)),
+        Err(err) => verification.errors.push(err.to_string()),
+    }
+
+    let pattern = Path::new(&glob::Pattern::escape(cache_path))
+        .join("schemas")
+        .join("*.json");
+
+    let paths = pattern
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid cache path"))
+        .and_then(get_paths_by_glob)
+        .map_err(|err| anyhow!("failed to read the cached schemas: {}", err))?;
+
+    for path in &paths {
+        let p = path.to_string_lossy();
+
+        let cached: CachedSchema = match read_file(&p)
+            .await
+            .and_then(|data| serde_json::from_slice(&data).map_err(Into::into))
+        {
+            Ok(cached) => cached,
+            Err(err) => {
+                verification
+                    .errors
+                    .push(format!("invalid cached schema {}: {}", p, err));
+                continue;
+            }
+        };
+
+        let hash = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+
+        match cached.url {
+            Some(url) if url_hash(&url) == hash => {}
+            Some(url) => verification.errors.push(format!(
+                "cached schema {} is not stored under the hash of {}",
+                p, url
+            )),
+            None => verification
+                .errors
+                .push(format!("cached schema {} has no URL", p)),
+        }
+    }
+
+    verification.schema_count = paths.len();
+
+    Ok(verification)
+}
+
+/// Downloads the schema index, or loads it from
+/// the cache if there is no repository or offline.
+///
+/// The downloaded index is also cached.
+async fn schema_index(m: &ArgMatches) -> Result<SchemaIndex, anyhow::Error> {
+    let schema_repository = if m.is_present("default-schema-repository") {
+        Some(SCHEMA_REPOSITORY)
+    } else {
+        m.value_of("schema-repository")
+    };
+
+    match (schema_repository, m.value_of("cache-path")) {
+        (Some(url), cache_path) if !is_offline() => {
+            let index = download_schema_index(url).await?;
+
+            if let Some(cache_path) = cache_path {
+                cache_schema_index(&index, cache_path).await?;
+            }
+
+            Ok(index)
+        }
+        (_, Some(cache_path)) => load_schema_index(cache_path)
+            .await?
+            .ok_or_else(|| anyhow!("there is no schema index in {}", cache_path)),
+        (Some(_), None) => Err(anyhow!(
+            "a cache path is required for the schema index in offline mode"
+        )),
+        (None, None) => Err(anyhow!("a schema repository or a cache path is required")),
+    }
+}
+
+/// The given schema URLs, or all the URLs in the index with `--all`.
+async fn schema_urls(m: &ArgMatches) -> Result<Vec<String>, anyhow::Error> {
+    if m.is_present("all") {
+        return Ok(schema_index(m)
+            .await?
+            .schemas
+            .into_iter()
+            .map(|s| s.url)
+            .collect());
+    }
+
+    match m.values_of("urls") {
+        Some(urls) => Ok(urls.map(String::from).collect()),
+        None => Err(anyhow!("no schemas were given")),
+    }
+}
+
+/// Stores the schema at the URL along with the schemas it references
+/// in the given folder, schemas are downloaded unless they are in the cache.
+///
+/// Returns the amount of stored schemas.
+async fn store_schema(
+    url: &str,
+    dir: &str,
+    cache_path: Option<&str>,
+) -> Result<usize, anyhow::Error> {
+    let schema = load_schema(url, cache_path).await?;
+    cache_schema(dir, url, schema.clone()).await?;
+
+    let mut stored = 1;
+    let mut bundle = SchemaBundle::new(url, schema);
+
+    loop {
+        let missing = bundle.missing_documents();
+
+        if missing.is_empty() {
+            break;
+        }
+
+        for doc_url in missing {
+            match load_schema(&doc_url, cache_path).await {
+                Ok(schema) => {
+                    cache_schema(dir, &doc_url, schema.clone()).await?;
+                    bundle.add_document(&doc_url, schema);
+                    stored += 1;
+                }
+                Err(err) => {
+                    print_message(
+                        Severity::Warning,
+                        "warning",
+                        &format!(
+                            "failed to load schema {} referenced in {}: {}",
+                            doc_url, url, err
+                        ),
+                    );
+                    bundle.skip_document(&doc_url);
+                }
+            }
+        }
+    }
+
+    Ok(stored)
+}
+
+async fn load_schema(url: &str, cache_path: Option<&str>) -> Result<RootSchema, anyhow::Error> {
+    if let Some(cache_path) = cache_path {
+        let path = cached_schema_path(cache_path, url);
+        let p = path.to_str().unwrap();
+
+        if file_exists(p) {
+            let cached: CachedSchema = serde_json::from_slice(&read_file(p).await?)?;
+            return Ok(cached.schema);
+        }
+    }
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(anyhow!("only remote schemas can be cached"));
+    }
+
+    download_schema(url).await
+}
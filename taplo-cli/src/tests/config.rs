@@ -0,0 +1,195 @@
+use super::temp_dir;
+use crate::{
+    config::{discovered_base_dir, load_config_file, resolve_patterns, Config},
+    util::Configs,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+fn resolved(patterns: &[&str], dir: &str) -> Vec<String> {
+    let mut patterns = Some(patterns.iter().map(|p| p.to_string()).collect());
+    resolve_patterns(&mut patterns, Path::new(dir));
+    patterns.unwrap()
+}
+
+/// Loads the configuration at the path from the given files.
+fn load(files: &[(&str, &str)], path: &str, base_dir: &str) -> Config {
+    let files: HashMap<PathBuf, String> = files
+        .iter()
+        .map(|(path, src)| (PathBuf::from(path), src.to_string()))
+        .collect();
+
+    futures::executor::block_on(load_config_file(
+        Path::new(path),
+        Path::new(base_dir),
+        |path| {
+            let src = files.get(&path).cloned();
+            async move { Ok(taplo::de::from_str(&src.unwrap())?) }
+        },
+    ))
+    .unwrap()
+}
+
+#[test]
+fn resolve_relative_patterns() {
+    assert_eq!(
+        resolved(&["*.toml", "src/**/*.toml"], "/project"),
+        vec!["/project/*.toml", "/project/src/**/*.toml"]
+    );
+}
+
+#[test]
+fn resolve_current_dir_patterns() {
+    assert_eq!(
+        resolved(&["./*.toml", "./src/*.toml"], "/project"),
+        vec!["/project/*.toml", "/project/src/*.toml"]
+    );
+}
+
+#[test]
+fn resolve_absolute_patterns() {
+    assert_eq!(
+        resolved(&["/other/*.toml"], "/project"),
+        vec!["/other/*.toml"]
+    );
+}
+
+#[test]
+fn resolve_patterns_escaped_dir() {
+    let patterns = resolved(&["*.toml"], "/project [1]/*");
+
+    assert_eq!(patterns, vec!["/project [[]1[]]/[*]/*.toml"]);
+
+    let pattern = glob::Pattern::new(&patterns[0]).unwrap();
+    assert!(pattern.matches("/project [1]/*/Cargo.toml"));
+    assert!(!pattern.matches("/project 1/a/Cargo.toml"));
+}
+
+#[test]
+fn resolve_config_paths() {
+    let mut config: Config = taplo::de::from_str(
+        r#"
+include = ["*.toml"]
+exclude = ["target/**"]
+schema.path = "schema.json"
+
+[[rule]]
+include = ["/abs/*.toml"]
+schema.path = "https://example.com/schema.json"
+"#,
+    )
+    .unwrap();
+
+    config.resolve_paths(Path::new("/project"));
+
+    assert_eq!(config.include.unwrap(), vec!["/project/*.toml"]);
+    assert_eq!(config.exclude.unwrap(), vec!["/project/target/**"]);
+    assert_eq!(
+        config.global_options.schema.unwrap().path.unwrap(),
+        "/project/schema.json"
+    );
+
+    let rule = &config.rule.unwrap()[0];
+    assert_eq!(rule.include.as_ref().unwrap(), &vec!["/abs/*.toml"]);
+    assert_eq!(
+        rule.options.schema.as_ref().unwrap().path.as_ref().unwrap(),
+        "https://example.com/schema.json"
+    );
+}
+
+#[test]
+fn load_config_base_dir() {
+    let config = load(
+        &[("/project/config/taplo.toml", r#"include = ["*.toml"]"#)],
+        "/project/config/taplo.toml",
+        "/project",
+    );
+
+    assert_eq!(config.include.unwrap(), vec!["/project/*.toml"]);
+}
+
+#[test]
+fn load_config_relative_paths() {
+    let config = load(
+        &[
+            (
+                "/project/config/taplo.toml",
+                r#"
+extends = ["base.toml"]
+config_relative_paths = true
+include = ["*.toml"]
+"#,
+            ),
+            ("/project/config/base.toml", r#"exclude = ["target/**"]"#),
+        ],
+        "/project/config/taplo.toml",
+        "/cwd",
+    );
+
+    assert_eq!(config.include.unwrap(), vec!["/project/config/*.toml"]);
+    // The extended configuration does not set the option.
+    assert_eq!(config.exclude.unwrap(), vec!["/cwd/target/**"]);
+}
+
+#[test]
+fn discovered_config_base_dir() {
+    let cwd = Path::new("/project/src");
+
+    assert_eq!(
+        discovered_base_dir(Path::new("/project/src/.taplo.toml"), cwd),
+        cwd
+    );
+    assert_eq!(
+        discovered_base_dir(Path::new("/project/.taplo.toml"), cwd),
+        cwd
+    );
+    assert_eq!(
+        discovered_base_dir(Path::new("/project/src/nested/.taplo.toml"), cwd),
+        Path::new("/project/src/nested")
+    );
+    assert_eq!(
+        discovered_base_dir(Path::new("/other/.taplo.toml"), cwd),
+        Path::new("/other")
+    );
+}
+
+#[tokio::test]
+async fn nested_configs_from_other_dirs() {
+    let dir = temp_dir(
+        "nested-configs",
+        &[
+            (".taplo.toml", "include = [\"*.toml\"]\n"),
+            ("nested/.taplo.toml", "include = [\"*.toml\"]\n"),
+        ],
+    );
+
+    let included = |configs: &Configs, config: &Config, path: &str| {
+        config.is_included(&configs.absolute_path(path)).unwrap()
+    };
+
+    // The nested configuration is relative to its own directory.
+    let mut configs = Configs::load_in(dir.clone(), None).await.unwrap();
+    let config = configs
+        .for_document(Path::new("nested/a.toml"))
+        .await
+        .unwrap();
+    assert!(included(&configs, &config, "nested/a.toml"));
+    assert!(!included(&configs, &config, "a.toml"));
+    assert!(included(&configs, configs.root(), "a.toml"));
+
+    let mut configs = Configs::load_in(dir.join("nested"), None).await.unwrap();
+    let config = configs.for_document(Path::new("a.toml")).await.unwrap();
+    assert!(included(&configs, &config, "a.toml"));
+
+    // The configuration in an ancestor is relative to the current directory.
+    let mut configs = Configs::load_in(dir.join("other"), None).await.unwrap();
+    let config = configs.for_document(Path::new("a.toml")).await.unwrap();
+    assert!(included(&configs, &config, "a.toml"));
+    assert!(!config
+        .is_included(&dir.join("a.toml").to_string_lossy())
+        .unwrap());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,119 @@
+use crate::format::unified_diff;
+use taplo::{
+    formatter::TextEdit,
+    rowan::{TextRange, TextSize},
+};
+
+fn edit(src: &str, old: &str, new_text: &str) -> TextEdit {
+    let start = src.find(old).unwrap();
+
+    TextEdit {
+        range: TextRange::new(
+            TextSize::from(start as u32),
+            TextSize::from((start + old.len()) as u32),
+        ),
+        new_text: new_text.into(),
+    }
+}
+
+fn numbered_lines(count: usize) -> String {
+    (1..=count).map(|i| format!("l{} = {}\n", i, i)).collect()
+}
+
+fn hunk_headers(diff: &str) -> Vec<&str> {
+    diff.lines().filter(|l| l.starts_with("@@")).collect()
+}
+
+#[test]
+fn unified_diff_context() {
+    let src = numbered_lines(10);
+
+    assert_eq!(
+        unified_diff("a.toml", &src, &[edit(&src, "l5 = 5", "l5=5")]),
+        "--- a.toml
++++ a.toml
+@@ -2,7 +2,7 @@
+ l2 = 2
+ l3 = 3
+ l4 = 4
+-l5 = 5
++l5=5
+ l6 = 6
+ l7 = 7
+ l8 = 8
+"
+    );
+}
+
+#[test]
+fn unified_diff_document_bounds() {
+    let src = "a = 1\nb = 2\nc = 3";
+
+    assert_eq!(
+        unified_diff(
+            "a.toml",
+            src,
+            &[edit(src, "a = 1", "a=1"), edit(src, "c = 3", "c = 3\n")]
+        ),
+        "--- a.toml
++++ a.toml
+@@ -1,3 +1,3 @@
+-a = 1
++a=1
+ b = 2
+-c = 3
+\\ No newline at end of file
++c = 3
+"
+    );
+}
+
+#[test]
+fn unified_diff_separate_hunks() {
+    let src = numbered_lines(20);
+
+    // 7 unchanged lines between the changes are too many for one hunk.
+    let diff = unified_diff(
+        "a.toml",
+        &src,
+        &[edit(&src, "l2 = 2\n", ""), edit(&src, "l10 = 10", "l10=10")],
+    );
+
+    assert_eq!(
+        hunk_headers(&diff),
+        vec!["@@ -1,5 +1,4 @@", "@@ -7,7 +6,7 @@"]
+    );
+
+    // 6 unchanged lines are still shared.
+    let diff = unified_diff(
+        "a.toml",
+        &src,
+        &[
+            edit(&src, "l2 = 2\n", ""),
+            edit(&src, "l9 = 9", "l9=9\nl9b = 9"),
+        ],
+    );
+
+    assert_eq!(hunk_headers(&diff), vec!["@@ -1,12 +1,12 @@"]);
+}
+
+#[test]
+fn unified_diff_merged_edits() {
+    let src = numbered_lines(3);
+
+    assert_eq!(
+        unified_diff(
+            "a.toml",
+            &src,
+            &[edit(&src, "l2 ", "l2"), edit(&src, " 2", "2")]
+        ),
+        "--- a.toml
++++ a.toml
+@@ -1,3 +1,3 @@
+ l1 = 1
+-l2 = 2
++l2=2
+ l3 = 3
+"
+    );
+}
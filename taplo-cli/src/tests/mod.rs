@@ -0,0 +1,28 @@
+mod config;
+mod format;
+mod output;
+mod schema;
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
+
+use std::path::PathBuf;
+
+/// A temporary directory for the test with the given files,
+/// the returned path is canonical.
+fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("taplo-cli-{}-{}", name, std::process::id()));
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (path, src) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+
+    dir.canonicalize().unwrap()
+}
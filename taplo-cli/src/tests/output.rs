@@ -0,0 +1,201 @@
+use crate::lint::{
+    output::{checkstyle, github, json, sarif},
+    Label, Lint,
+};
+use pretty_lint::Severity;
+use serde_json::json;
+use taplo::{
+    code::Code,
+    util::coords::{Position, Range},
+};
+
+/// A label from zero-based lines and characters.
+fn label(start: (u64, u64), end: (u64, u64), message: &str) -> Label {
+    Label {
+        range: Range {
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+        },
+        message: message.into(),
+    }
+}
+
+fn lints() -> Vec<Lint> {
+    vec![
+        Lint {
+            file: Some("dir/a.toml".into()),
+            code: Code::DuplicateKey,
+            severity: Severity::Error,
+            message: "duplicate key".into(),
+            details: Some("`a`".into()),
+            labels: vec![
+                label((2, 0), (2, 1), "duplicate key"),
+                label((0, 0), (0, 1), "first defined here"),
+            ],
+            notes: vec![],
+        },
+        Lint {
+            file: Some("dir/a.toml".into()),
+            code: Code::Schema,
+            severity: Severity::Warning,
+            message: "invalid type".into(),
+            details: None,
+            labels: vec![label((4, 6), (4, 10), "invalid type")],
+            notes: vec![],
+        },
+        Lint {
+            file: None,
+            code: Code::DuplicateKey,
+            severity: Severity::Error,
+            message: "no labels".into(),
+            details: None,
+            labels: vec![],
+            notes: vec![],
+        },
+    ]
+}
+
+#[test]
+fn json_output() {
+    let output = json(&lints());
+
+    assert_eq!(output.as_array().unwrap().len(), 3);
+    assert_eq!(output[0]["file"], "dir/a.toml");
+    assert_eq!(output[0]["code"], "TOML001");
+    assert_eq!(output[0]["name"], "duplicate-key");
+    assert_eq!(output[0]["severity"], "error");
+    assert_eq!(output[0]["message"], "duplicate key: `a`");
+    assert_eq!(
+        output[0]["range"],
+        json!({
+            "start": { "line": 3, "column": 1 },
+            "end": { "line": 3, "column": 2 },
+        })
+    );
+    assert_eq!(output[0]["labels"].as_array().unwrap().len(), 2);
+    assert_eq!(output[0]["labels"][1]["message"], "first defined here");
+    assert_eq!(output[0]["labels"][1]["range"]["start"]["line"], 1);
+
+    assert_eq!(output[1]["severity"], "warning");
+    assert_eq!(output[1]["range"]["start"]["column"], 7);
+    assert_eq!(output[1]["range"]["end"]["column"], 11);
+
+    assert_eq!(output[2]["file"], json!(null));
+    assert_eq!(output[2]["range"], json!(null));
+}
+
+#[test]
+fn sarif_output() {
+    let output = sarif(&lints());
+    let run = &output["runs"][0];
+
+    assert_eq!(output["version"], "2.1.0");
+
+    // Every code appears once in the rules.
+    assert_eq!(
+        run["tool"]["driver"]["rules"],
+        json!([
+            { "id": "TOML001", "name": "duplicate-key" },
+            { "id": "TOML200", "name": "schema" },
+        ])
+    );
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+
+    assert_eq!(results[0]["ruleId"], "TOML001");
+    assert_eq!(results[0]["level"], "error");
+    assert_eq!(results[0]["message"]["text"], "duplicate key: `a`");
+    assert_eq!(
+        results[0]["locations"][0]["physicalLocation"],
+        json!({
+            "artifactLocation": { "uri": "dir/a.toml" },
+            "region": {
+                "startLine": 3,
+                "startColumn": 1,
+                "endLine": 3,
+                "endColumn": 2,
+            },
+        })
+    );
+
+    let related = &results[0]["relatedLocations"][0];
+    assert_eq!(related["id"], 0);
+    assert_eq!(related["message"]["text"], "first defined here");
+    assert_eq!(related["physicalLocation"]["region"]["startLine"], 1);
+
+    assert_eq!(results[1]["level"], "warning");
+    assert_eq!(
+        results[1]["locations"][0]["physicalLocation"]["region"],
+        json!({
+            "startLine": 5,
+            "startColumn": 7,
+            "endLine": 5,
+            "endColumn": 11,
+        })
+    );
+
+    assert_eq!(results[2]["locations"], json!([]));
+}
+
+#[test]
+fn checkstyle_output() {
+    let output = checkstyle(&lints());
+
+    assert_eq!(
+        output,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="dir/a.toml">
+    <error line="3" column="1" severity="error" message="duplicate key: `a`" source="taplo.TOML001"/>
+    <error line="5" column="7" severity="warning" message="invalid type" source="taplo.TOML200"/>
+  </file>
+  <file name="-">
+    <error severity="error" message="no labels" source="taplo.TOML001"/>
+  </file>
+</checkstyle>
+"#
+    );
+}
+
+#[test]
+fn checkstyle_escaping() {
+    let mut lint = lints().remove(1);
+    lint.file = Some(r#"a&b<c>"d'.toml"#.into());
+    lint.message = "expected <\"a\" & 'b'>\nfound".into();
+
+    let output = checkstyle(&[lint]);
+
+    assert!(output.contains(r#"<file name="a&amp;b&lt;c&gt;&quot;d&apos;.toml">"#));
+    assert!(output
+        .contains(r#"message="expected &lt;&quot;a&quot; &amp; &apos;b&apos;&gt;&#10;found""#));
+}
+
+#[test]
+fn github_output() {
+    let output = github(&lints());
+
+    assert_eq!(
+        output,
+        "::error file=dir/a.toml,line=3,col=1,endLine=3,endColumn=2,\
+         title=taplo TOML001 (duplicate-key)::duplicate key: `a`\n\
+         ::warning file=dir/a.toml,line=5,col=7,endLine=5,endColumn=11,\
+         title=taplo TOML200 (schema)::invalid type\n\
+         ::error title=taplo TOML001 (duplicate-key)::no labels\n"
+    );
+}
+
+#[test]
+fn github_escaping() {
+    let mut lint = lints().remove(1);
+    lint.file = Some("C:/a,b%c\nd.toml".into());
+    lint.message = "100% wrong: a, b\r\nc".into();
+
+    let output = github(&[lint]);
+
+    assert_eq!(
+        output,
+        "::warning file=C%3A/a%2Cb%25c%0Ad.toml,line=5,col=7,endLine=5,endColumn=11,\
+         title=taplo TOML200 (schema)::100%25 wrong: a, b%0D%0Ac\n"
+    );
+}
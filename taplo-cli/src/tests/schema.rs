@@ -0,0 +1,176 @@
+use super::temp_dir;
+use crate::{
+    app, lint, lint_schema_index,
+    schema::{export, verify_cache},
+    set_offline,
+    util::{cache_schema, cache_schema_index, cached_schema_path, url_hash, Configs},
+};
+use schemars::schema::RootSchema;
+use std::path::Path;
+use taplo::schema::{CachedSchema, SchemaIndex};
+
+const SCHEMA_URL: &str = "https://example.com/schema.json";
+
+fn schema() -> RootSchema {
+    serde_json::from_str(
+        r#"{
+    "type": "object",
+    "properties": {
+        "name": { "type": "string" }
+    }
+}"#,
+    )
+    .unwrap()
+}
+
+/// An index with a single schema for `example.toml` documents.
+fn schema_index(url: &str, url_hash: &str) -> SchemaIndex {
+    serde_json::from_value(serde_json::json!({
+        "schemas": [{
+            "url": url,
+            "urlHash": url_hash,
+            "patterns": ["example\\.toml$"],
+        }]
+    }))
+    .unwrap()
+}
+
+fn write_cached(path: &Path, cached: &CachedSchema) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, serde_json::to_vec(cached).unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn verify_cached_schemas() {
+    let dir = temp_dir("schema-verify", &[]);
+    let cache = dir.to_str().unwrap();
+
+    let mut index = schema_index(SCHEMA_URL, &url_hash(SCHEMA_URL));
+    index.schemas.extend(
+        schema_index("https://example.com/other.json", "hash")
+            .schemas
+            .into_iter(),
+    );
+    cache_schema_index(&index, cache).await.unwrap();
+
+    cache_schema(cache, SCHEMA_URL, schema()).await.unwrap();
+
+    // Stored under the hash of another URL.
+    write_cached(
+        &cached_schema_path(cache, "https://example.com/moved.json"),
+        &CachedSchema {
+            url: Some("https://example.com/original.json".into()),
+            schema: schema(),
+        },
+    );
+
+    write_cached(
+        &cached_schema_path(cache, "https://example.com/unknown.json"),
+        &CachedSchema {
+            url: None,
+            schema: schema(),
+        },
+    );
+
+    let verification = verify_cache(cache).await.unwrap();
+
+    assert_eq!(verification.schema_count, 3);
+    assert!(verification.warnings.is_empty());
+
+    let mut errors = verification.errors;
+    errors.sort();
+
+    let moved = cached_schema_path(cache, "https://example.com/moved.json");
+    let unknown = cached_schema_path(cache, "https://example.com/unknown.json");
+
+    let mut expected = vec![
+        "the URL hash of https://example.com/other.json in the schema index is incorrect"
+            .to_string(),
+        format!(
+            "cached schema {} is not stored under the hash of https://example.com/original.json",
+            moved.display()
+        ),
+        format!("cached schema {} has no URL", unknown.display()),
+    ];
+    expected.sort();
+
+    assert_eq!(errors, expected);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn verify_cache_without_index() {
+    let dir = temp_dir("schema-verify-index", &[]);
+    let cache = dir.to_str().unwrap();
+
+    cache_schema(cache, SCHEMA_URL, schema()).await.unwrap();
+
+    let verification = verify_cache(cache).await.unwrap();
+
+    assert_eq!(verification.schema_count, 1);
+    assert!(verification.errors.is_empty());
+    assert_eq!(
+        verification.warnings,
+        vec![format!("there is no schema index in {}", cache)]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn export_and_lint_offline() {
+    let dir = temp_dir(
+        "schema-export",
+        &[("example.toml", "name = 1\n"), ("other.toml", "name = 1\n")],
+    );
+    let cache = dir.join("cache");
+    let output = dir.join("export");
+
+    cache_schema_index(&schema_index(SCHEMA_URL, &url_hash(SCHEMA_URL)), &cache)
+        .await
+        .unwrap();
+    cache_schema(&cache, SCHEMA_URL, schema()).await.unwrap();
+
+    let matches = app().get_matches_from(vec![
+        "schema",
+        "export",
+        "--cache-path",
+        cache.to_str().unwrap(),
+        "--output",
+        output.to_str().unwrap(),
+    ]);
+    let (_, schema_matches) = matches.subcommand().unwrap();
+    let (_, export_matches) = schema_matches.subcommand().unwrap();
+
+    export(export_matches).await;
+
+    let verification = verify_cache(output.to_str().unwrap()).await.unwrap();
+    assert_eq!(verification.schema_count, 1);
+    assert!(verification.errors.is_empty());
+    assert!(verification.warnings.is_empty());
+
+    // The export is used as the cache without network access.
+    set_offline(true);
+
+    let matches = app().get_matches_from(vec![
+        "--offline",
+        "lint",
+        "--cache-path",
+        output.to_str().unwrap(),
+        dir.join("*.toml").to_str().unwrap(),
+    ]);
+    let (_, lint_matches) = matches.subcommand().unwrap();
+
+    let index = lint_schema_index(lint_matches).await.unwrap();
+    assert_eq!(index.as_ref().unwrap().schemas[0].url, SCHEMA_URL);
+
+    let configs = Configs::load(None).await.unwrap();
+    let result = lint::lint(configs, lint_matches, index, None).await;
+
+    // Only `example.toml` has a schema.
+    assert_eq!(result.matched_document_count, 2);
+    assert_eq!(result.error_count, 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,119 @@
+use super::temp_dir;
+use crate::{changed_documents, util::Configs, watch::WatchedRoots};
+use clap::{App, Arg};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn watched_relative_paths() {
+    let roots = WatchedRoots::new(PathBuf::from("/project"), None);
+
+    assert_eq!(
+        roots.relative(Path::new("/project/src/a.toml")),
+        Path::new("src/a.toml")
+    );
+    assert_eq!(
+        roots.relative(Path::new("/other/a.toml")),
+        Path::new("/other/a.toml")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn watched_relative_canonical_paths() {
+    let dir = temp_dir("watch-link", &[("a.toml", "")]);
+    let link = dir.with_extension("link");
+
+    std::fs::remove_file(&link).ok();
+    std::os::unix::fs::symlink(&dir, &link).unwrap();
+
+    let roots = WatchedRoots::new(link.clone(), None);
+
+    assert_eq!(roots.relative(&dir.join("a.toml")), Path::new("a.toml"));
+    assert_eq!(roots.relative(&link.join("a.toml")), Path::new("a.toml"));
+
+    std::fs::remove_file(link).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn watched_config_files() {
+    let roots = WatchedRoots::new(PathBuf::from("/project"), None);
+
+    assert!(roots.is_config(Path::new("/project/.taplo.toml")));
+    assert!(roots.is_config(Path::new("/project/nested/taplo.toml")));
+    assert!(!roots.is_config(Path::new("/project/Cargo.toml")));
+    assert!(!roots.is_config(Path::new("/project/.taplo.toml/a.toml")));
+}
+
+#[test]
+fn watched_explicit_config_file() {
+    let roots = WatchedRoots::new(PathBuf::from("/project"), Some("config/taplo.toml"));
+
+    assert!(roots.is_config(Path::new("/project/config/taplo.toml")));
+
+    // Other configuration files are not used.
+    assert!(!roots.is_config(Path::new("/project/.taplo.toml")));
+    assert!(!roots.is_config(Path::new("/project/taplo.toml")));
+}
+
+/// The changed documents for the lint command with the given arguments.
+async fn changed(dir: &Path, args: &[&str], changed: &[&str]) -> Option<Vec<String>> {
+    let matches = App::new("lint")
+        .arg(Arg::new("files").multiple(true))
+        .get_matches_from(std::iter::once("lint").chain(args.iter().copied()));
+
+    let mut configs = Configs::load_in(dir.to_path_buf(), None).await.unwrap();
+
+    changed_documents(
+        &mut configs,
+        &matches,
+        &changed.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn changed_documents_nested_configs() {
+    let dir = temp_dir(
+        "changed",
+        &[
+            (".taplo.toml", "include = [\"*.toml\"]\n"),
+            (
+                "sub/.taplo.toml",
+                "config_relative_paths = true\ninclude = [\"*.toml\"]\n",
+            ),
+            (
+                "other/.taplo.toml",
+                "config_relative_paths = true\ninclude = [\"a.toml\"]\n",
+            ),
+        ],
+    );
+
+    let paths = [
+        "a.toml",
+        "a.json",
+        "sub/b.toml",
+        "other/a.toml",
+        "other/b.toml",
+    ];
+
+    // Every path is matched against the configuration nearest to it.
+    assert_eq!(
+        changed(&dir, &[], &paths).await,
+        Some(vec![
+            "a.toml".to_string(),
+            "sub/b.toml".to_string(),
+            "other/a.toml".to_string(),
+        ])
+    );
+
+    // The given patterns replace the configurations.
+    assert_eq!(
+        changed(&dir, &["**/b.toml"], &paths).await,
+        Some(vec!["sub/b.toml".to_string(), "other/b.toml".to_string()])
+    );
+
+    assert_eq!(changed(&dir, &[], &["a.json", "other/b.toml"]).await, None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,14 +1,119 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use anyhow::anyhow;
+use clap::ArgMatches;
+use hex::ToHex;
+use pretty_lint::PrettyLint;
 use schemars::schema::RootSchema;
-use taplo::schema::SchemaIndex;
-use crate::external::{mkdir, write_file};
+use sha2::{Digest, Sha256};
+use taplo::{
+    schema::{bundle::SchemaBundle, CachedSchema, SchemaIndex},
+    util::coords::Mapper,
+};
+use crate::{
+    config::{self, Config, CONFIG_FILE_NAMES},
+    external::{current_dir, file_exists, is_absolute_path, mkdir, read_file, write_file},
+};
 
+/// Loads a schema along with the schemas it references in other documents.
+///
+/// Referenced documents are loaded the same way as the schema, relative
+/// references are resolved against the URL or the path of the schema.
+/// If any of them could not be loaded, the references to them are left
+/// unresolved, and the error is returned along with the schema.
 pub async fn get_schema(
     path: &str,
     cache_path: Option<impl AsRef<Path>>,
 ) -> Result<(RootSchema, Option<anyhow::Error>), anyhow::Error> {
-    crate::external::get_schema(path, cache_path).await
+    let cache_path: Option<&Path> = cache_path.as_ref().map(|p| p.as_ref());
+
+    let (schema, err) = crate::external::get_schema(path, cache_path).await?;
+
+    let mut errors: Vec<String> = err.into_iter().map(|err| err.to_string()).collect();
+    let mut bundle = SchemaBundle::new(&schema_url(path)?, schema);
+
+    loop {
+        let missing = bundle.missing_documents();
+
+        if missing.is_empty() {
+            break;
+        }
+
+        for url in missing {
+            match crate::external::get_schema(&url, cache_path).await {
+                Ok((schema, err)) => {
+                    bundle.add_document(&url, schema);
+                    errors.extend(err.map(|err| err.to_string()));
+                }
+                Err(err) => {
+                    bundle.skip_document(&url);
+                    errors.push(format!("failed to load referenced schema {}: {}", url, err));
+                }
+            }
+        }
+    }
+
+    let err = if errors.is_empty() {
+        None
+    } else {
+        Some(anyhow!("{}", errors.join(", ")))
+    };
+
+    Ok((bundle.into_schema()?, err))
+}
+
+/// The URL of a schema, or its absolute path
+/// with `/` separators for local files.
+fn schema_url(path: &str) -> Result<String, anyhow::Error> {
+    if path.contains("://") {
+        return Ok(path.to_string());
+    }
+
+    let path = if is_absolute_path(path) {
+        PathBuf::from(path)
+    } else {
+        current_dir()?.join(path)
+    };
+
+    Ok(path.to_string_lossy().replace('\\', "/"))
+}
+
+/// The hash of a schema URL, cached schemas are stored under it.
+pub(crate) fn url_hash(url: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    hasher.finalize().encode_hex::<String>()
+}
+
+/// The path of a cached schema.
+pub(crate) fn cached_schema_path(cache_path: impl AsRef<Path>, url: &str) -> PathBuf {
+    cache_path
+        .as_ref()
+        .join("schemas")
+        .join(url_hash(url))
+        .with_extension("json")
+}
+
+/// Stores a schema in the cache.
+pub(crate) async fn cache_schema(
+    cache_path: impl AsRef<Path>,
+    url: &str,
+    schema: RootSchema,
+) -> Result<(), anyhow::Error> {
+    mkdir(cache_path.as_ref().join("schemas").to_str().unwrap())?;
+    write_file(
+        cached_schema_path(cache_path, url).to_str().unwrap(),
+        &serde_json::to_vec(&CachedSchema {
+            url: Some(url.into()),
+            schema,
+        })
+        .unwrap(),
+    )
+    .await
 }
 
 pub async fn cache_schema_index(
@@ -27,3 +132,223 @@ pub async fn cache_schema_index(
     )
     .await
 }
+
+/// Parse the contents of a configuration file.
+///
+/// Errors are also printed with their location in the file.
+pub(crate) fn parse_config(path: &str, src: &[u8]) -> Result<Config, anyhow::Error> {
+    let src = std::str::from_utf8(src)?;
+
+    taplo::de::from_str(src).map_err(|err| {
+        if let Some(range) = err.range {
+            let mapper = Mapper::new_utf16(src, false);
+
+            eprintln!(
+                "{}",
+                &PrettyLint::error(src)
+                    .with_file_path(path)
+                    .with_message("invalid configuration")
+                    .at({
+                        let r = mapper.range(range).unwrap();
+                        pretty_lint::Span {
+                            start: pretty_lint::Position {
+                                line: r.start.line as usize + 1,
+                                col: r.start.character as usize + 1,
+                            },
+                            end: pretty_lint::Position {
+                                line: r.end.line as usize + 1,
+                                col: r.end.character as usize,
+                            },
+                        }
+                    })
+                    .with_inline_message(&err.message)
+                    .to_string(),
+            );
+        }
+
+        anyhow!("{}", err.message)
+    })
+}
+
+/// Loads a configuration file along with the configurations it extends.
+///
+/// Relative paths are resolved against `base_dir`
+/// unless the files set otherwise.
+pub(crate) async fn load_config(path: &Path, base_dir: &Path) -> Result<Config, anyhow::Error> {
+    config::load_config_file(path, base_dir, |path| async move {
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid file path: {:?}", path))?;
+
+        let src = read_file(path)
+            .await
+            .map_err(|err| anyhow!("failed to read {}: {}", path, err))?;
+
+        parse_config(path, &src)
+    })
+    .await
+}
+
+/// The configurations of the documents.
+///
+/// Unless a configuration file is given explicitly,
+/// every document uses the configuration file nearest to it.
+pub(crate) struct Configs {
+    cwd: PathBuf,
+    /// The configuration for the current directory or the explicit one.
+    root: Arc<Config>,
+    explicit: bool,
+    /// The configurations by directory.
+    dirs: HashMap<PathBuf, Arc<Config>>,
+    /// The configurations by file, so that every file is loaded only once.
+    files: HashMap<PathBuf, Arc<Config>>,
+}
+
+impl Configs {
+    /// Loads the configuration file at the given path,
+    /// or the one for the current directory if there is none.
+    pub(crate) async fn load(path: Option<&str>) -> Result<Self, anyhow::Error> {
+        Self::load_in(current_dir()?, path).await
+    }
+
+    /// Loads the configurations as if `cwd` was the current directory.
+    pub(crate) async fn load_in(cwd: PathBuf, path: Option<&str>) -> Result<Self, anyhow::Error> {
+        let mut configs = Self {
+            root: Default::default(),
+            explicit: path.is_some(),
+            dirs: HashMap::new(),
+            files: HashMap::new(),
+            cwd,
+        };
+
+        configs.root = match path {
+            Some(p) => Arc::new(load_config(&configs.cwd.join(p), &configs.cwd).await?),
+            None => configs.for_dir(configs.cwd.clone()).await?,
+        };
+
+        Ok(configs)
+    }
+
+    /// The configuration for the current directory or the explicit one.
+    pub(crate) fn root(&self) -> &Config {
+        &self.root
+    }
+
+    /// The absolute path of a document, the paths
+    /// in the configurations are matched against these.
+    pub(crate) fn absolute_path(&self, path: &str) -> String {
+        self.cwd.join(path).to_string_lossy().into_owned()
+    }
+
+    /// The path relative to the current directory if it is in it.
+    fn relative_path(&self, path: PathBuf) -> PathBuf {
+        match path.strip_prefix(&self.cwd) {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => path,
+        }
+    }
+
+    /// The configuration for a document.
+    pub(crate) async fn for_document(&mut self, path: &Path) -> Result<Arc<Config>, anyhow::Error> {
+        if self.explicit {
+            return Ok(self.root.clone());
+        }
+
+        match self.cwd.join(path).parent() {
+            Some(dir) => self.for_dir(dir.to_path_buf()).await,
+            None => Ok(self.root.clone()),
+        }
+    }
+
+    async fn for_dir(&mut self, dir: PathBuf) -> Result<Arc<Config>, anyhow::Error> {
+        if let Some(config) = self.dirs.get(&dir) {
+            return Ok(config.clone());
+        }
+
+        let config = match config::find_config_file(&dir, |p| {
+            p.to_str().map(file_exists).unwrap_or(false)
+        }) {
+            Some(path) => match self.files.get(&path) {
+                Some(config) => config.clone(),
+                None => {
+                    let base_dir = config::discovered_base_dir(&path, &self.cwd);
+                    let config = Arc::new(load_config(&path, base_dir).await?);
+                    config.check_patterns().map_err(|err| {
+                        anyhow!("invalid glob pattern in {}: {}", path.display(), err)
+                    })?;
+                    self.files.insert(path, config.clone());
+                    config
+                }
+            },
+            None => Default::default(),
+        };
+
+        self.dirs.insert(dir, config.clone());
+        Ok(config)
+    }
+}
+
+/// Sorts and deduplicates the document paths,
+/// and returns them along with their configurations.
+///
+/// If `allow_exclude` is set, the configuration files and the paths
+/// excluded in the configuration are removed, and their count is also returned.
+pub(crate) async fn document_paths(
+    configs: &mut Configs,
+    paths: Vec<PathBuf>,
+    allow_exclude: bool,
+) -> Result<(Vec<(PathBuf, Arc<Config>)>, usize), anyhow::Error> {
+    // The paths are shown relative to the current directory
+    // even if they were found with absolute patterns.
+    let mut paths: Vec<PathBuf> = paths
+        .into_iter()
+        .map(|p| configs.relative_path(p))
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut documents = Vec::with_capacity(paths.len());
+    let mut excluded = 0;
+
+    for path in paths {
+        let config = configs.for_document(&path).await?;
+
+        if !allow_exclude {
+            documents.push((path, config));
+            continue;
+        }
+
+        // Don't process taplo config files unless asked explicitly.
+        if let Some(file_name) = path.file_name() {
+            if CONFIG_FILE_NAMES.iter().any(|name| file_name == *name) {
+                // Don't count it as excluded.
+                continue;
+            }
+        }
+
+        if let Some(p) = path.to_str() {
+            if config.is_excluded(&configs.absolute_path(p))? {
+                excluded += 1;
+                continue;
+            }
+        }
+
+        documents.push((path, config));
+    }
+
+    Ok((documents, excluded))
+}
+
+/// The maximum amount of documents to process at the same time,
+/// the available parallelism by default.
+pub(crate) fn jobs(m: &ArgMatches) -> Result<usize, anyhow::Error> {
+    match m.value_of("jobs") {
+        Some(jobs) => match jobs.parse::<usize>() {
+            Ok(j) if j > 0 => Ok(j),
+            _ => Err(anyhow!("invalid number of jobs: {}", jobs)),
+        },
+        None => Ok(std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)),
+    }
+}
@@ -0,0 +1,192 @@
+//! Running commands again whenever documents or the configuration change.
+
+use crate::{config::CONFIG_FILE_NAMES, print_message};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use pretty_lint::Severity;
+use std::{
+    env,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+use tokio::sync::mpsc::unbounded_channel;
+
+/// File system events within this duration are reported together.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(200);
+
+/// Runs the command, then watches the current directory
+/// and runs it again after every change until the process is terminated.
+///
+/// The command is given the changed paths relative to the current directory,
+/// or `None` if all the documents should be processed,
+/// which is the case for the first run and after the configuration file changed.
+///
+/// Returns only if the watcher could not be started.
+pub(crate) async fn watch<F, Fut>(config_path: Option<&str>, mut run: F) -> bool
+where
+    F: FnMut(Option<Vec<String>>) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let cwd = match env::current_dir() {
+        Ok(cwd) => cwd,
+        Err(err) => {
+            print_message(
+                Severity::Error,
+                "error",
+                &format!("failed to read the current directory: {}", err),
+            );
+            return false;
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+
+    let mut watcher: RecommendedWatcher = match notify::watcher(tx, DEBOUNCE_DELAY) {
+        Ok(w) => w,
+        Err(err) => {
+            print_message(
+                Severity::Error,
+                "error",
+                &format!("failed to start watching: {}", err),
+            );
+            return false;
+        }
+    };
+
+    if let Err(err) = watcher.watch(&cwd, RecursiveMode::Recursive) {
+        print_message(
+            Severity::Error,
+            "error",
+            &format!("failed to start watching: {}", err),
+        );
+        return false;
+    }
+
+    // The events are received on a blocking channel.
+    let (events_tx, mut events) = unbounded_channel();
+    thread::spawn(move || {
+        for event in rx {
+            if events_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let roots = WatchedRoots::new(cwd, config_path);
+
+    run(None).await;
+    print_message(Severity::Info, "info", "watching for changes");
+
+    while let Some(event) = events.recv().await {
+        let mut changes = vec![event];
+
+        while let Ok(event) = events.try_recv() {
+            changes.push(event);
+        }
+
+        let mut config_changed = false;
+        let mut documents = Vec::new();
+
+        for change in changes {
+            let path = match change {
+                DebouncedEvent::Create(p)
+                | DebouncedEvent::Write(p)
+                | DebouncedEvent::Rename(_, p) => p,
+                DebouncedEvent::Remove(p) => {
+                    config_changed |= roots.is_config(&p);
+                    continue;
+                }
+                DebouncedEvent::Rescan => {
+                    // Some events might have been missed.
+                    config_changed = true;
+                    continue;
+                }
+                DebouncedEvent::Error(err, _) => {
+                    print_message(
+                        Severity::Warning,
+                        "warning",
+                        &format!("failed to watch for changes: {}", err),
+                    );
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if roots.is_config(&path) {
+                config_changed = true;
+            } else if path.is_file() {
+                if let Some(p) = roots.relative(&path).to_str() {
+                    documents.push(p.to_string());
+                }
+            }
+        }
+
+        if config_changed {
+            print_message(
+                Severity::Info,
+                "info",
+                "configuration changed, processing all documents",
+            );
+            run(None).await;
+        } else if !documents.is_empty() {
+            documents.sort();
+            documents.dedup();
+            run(Some(documents)).await;
+        } else {
+            continue;
+        }
+
+        print_message(Severity::Info, "info", "watching for changes");
+    }
+
+    false
+}
+
+/// The watched directory and the configuration files that are in use.
+pub(crate) struct WatchedRoots {
+    cwd: PathBuf,
+    /// The canonical current directory, the events
+    /// might contain paths with resolved links.
+    canonical_cwd: Option<PathBuf>,
+    /// The explicitly given configuration file.
+    config_path: Option<PathBuf>,
+}
+
+impl WatchedRoots {
+    pub(crate) fn new(cwd: PathBuf, config_path: Option<&str>) -> Self {
+        Self {
+            canonical_cwd: cwd.canonicalize().ok(),
+            config_path: config_path.map(|p| cwd.join(p)),
+            cwd,
+        }
+    }
+
+    pub(crate) fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(&self.cwd)
+            .ok()
+            .or_else(|| {
+                self.canonical_cwd
+                    .as_ref()
+                    .and_then(|cwd| path.strip_prefix(cwd).ok())
+            })
+            .unwrap_or(path)
+    }
+
+    /// Whether the path is a configuration file that might be in use,
+    /// every directory can have its own configuration file.
+    pub(crate) fn is_config(&self, path: &Path) -> bool {
+        match &self.config_path {
+            Some(config_path) => {
+                self.relative(path) == self.relative(config_path)
+                    || config_path.canonicalize().ok().as_deref() == Some(path)
+            }
+            None => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| CONFIG_FILE_NAMES.contains(&name))
+                .unwrap_or(false),
+        }
+    }
+}
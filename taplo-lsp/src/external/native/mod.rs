@@ -1,5 +1,8 @@
 use futures::Future;
-use std::{path::Path, time::UNIX_EPOCH};
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
 
 #[macro_export]
 macro_rules! log_info {
@@ -58,6 +61,22 @@ pub(crate) fn mkdir(p: &str) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+pub(crate) fn get_paths_by_glob(pattern: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let options = glob::MatchOptions {
+        case_sensitive: !is_windows(),
+        require_literal_leading_dot: false,
+        require_literal_separator: false,
+    };
+
+    let mut paths = Vec::new();
+
+    for path in glob::glob_with(pattern, options)? {
+        paths.push(path?);
+    }
+
+    Ok(paths)
+}
+
 pub(crate) fn needs_update(p: &str, new_date_ms: u64) -> Result<bool, anyhow::Error> {
     Ok(std::fs::metadata(p)?
         .modified()?
@@ -5,7 +5,7 @@ use js_sys::Uint8Array;
 use lsp_async_stub::{rpc::Message, Server};
 use lsp_types::Url;
 use once_cell::sync::Lazy;
-use std::{io, task::Poll};
+use std::{io, path::PathBuf, task::Poll};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 
@@ -71,6 +71,9 @@ extern {
 
     #[wasm_bindgen(js_namespace = global, js_name = isWindows)]
     fn js_is_windows() -> bool;
+
+    #[wasm_bindgen(js_namespace = global, js_name = globPaths, catch)]
+    fn js_glob_paths(pattern: &str) -> Result<JsValue, JsValue>;
 }
 
 struct ImplSend<T>(pub T);
@@ -149,6 +152,13 @@ pub(crate) fn needs_update(p: &str, new_date_ms: u64) -> Result<bool, anyhow::Er
     js_needs_update(p, new_date_ms).map_err(|e| anyhow!("{:?}", e))
 }
 
+pub(crate) fn get_paths_by_glob(pattern: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    js_glob_paths(pattern)
+        .map_err(|e| anyhow!("{:?}", e))?
+        .into_serde()
+        .map_err(Into::into)
+}
+
 #[derive(Clone)]
 struct MessageWriter;
 
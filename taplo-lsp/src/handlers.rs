@@ -9,14 +9,21 @@ use itertools::Itertools;
 use lsp_async_stub::{rpc::Error, Context, Params, RequestWriter};
 use lsp_types::*;
 use regex::Regex;
+use rowan::{TextRange, TextSize};
 use schemars::schema::RootSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, convert::TryFrom};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
 use taplo::{
     analytics::NodeRef,
     formatter,
-    schema::{util::get_schema_objects, CachedSchema, SchemaIndex},
+    schema::{
+        util::{get_schema_objects, schema_from_slice},
+        CachedSchema, SchemaIndex,
+    },
     util::{coords::Mapper, syntax::join_ranges},
     value::Value,
 };
@@ -26,9 +33,18 @@ mod completion;
 mod diagnostics;
 mod document_symbols;
 mod folding_ranges;
+pub(crate) mod references;
+pub(crate) mod rename;
+pub(crate) mod selection_ranges;
 mod semantic_tokens;
+pub(crate) mod workspace;
 
 pub(crate) use code_action::code_action;
+pub(crate) use diagnostics::{document_diagnostic, workspace_diagnostic};
+pub(crate) use references::{definition, document_highlight, references};
+pub(crate) use rename::{prepare_rename, rename};
+pub(crate) use selection_ranges::selection_ranges;
+pub(crate) use workspace::{watched_files_change, workspace_symbols};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,12 +54,19 @@ struct InitializationOptions {
 
 pub(crate) async fn initialize(
     mut context: Context<World>,
-    params: Params<InitializeParams>,
+    params: Params<serde_json::Value>,
 ) -> Result<InitializeResult, Error> {
     let p = params.required()?;
 
+    let capabilities = p.get("capabilities").cloned().unwrap_or_default();
+
+    let p: InitializeParams = serde_json::from_value(p)
+        .map_err(|e| Error::new(&format!("invalid initialize parameters: {}", e)))?;
+
     let mut w = context.world().lock().await;
 
+    w.client_capabilities = capabilities;
+
     w.workspace_uri = p.root_uri.map(|mut uri| {
         uri.set_path(&(uri.path().to_string() + "/"));
         uri
@@ -62,7 +85,9 @@ pub(crate) async fn initialize(
 
     Ok(InitializeResult {
         capabilities: ServerCapabilities {
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::Full)),
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                TextDocumentSyncKind::Incremental,
+            )),
             semantic_tokens_provider: Some(
                 SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
                     work_done_progress_options: WorkDoneProgressOptions {
@@ -77,14 +102,31 @@ pub(crate) async fn initialize(
                 }),
             ),
             code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
-                code_action_kinds: Some(vec![CodeActionKind::REFACTOR]),
+                code_action_kinds: Some(vec![
+                    CodeActionKind::QUICKFIX,
+                    CodeActionKind::REFACTOR,
+                ]),
                 resolve_provider: None,
                 work_done_progress_options: Default::default(),
             })),
             folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
             document_symbol_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
             document_formatting_provider: Some(OneOf::Left(true)),
+            document_range_formatting_provider: Some(OneOf::Left(true)),
+            document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                first_trigger_character: "]".into(),
+                more_trigger_character: Some(vec!["}".into(), "\n".into()]),
+            }),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            definition_provider: Some(OneOf::Left(true)),
+            references_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: Default::default(),
+            })),
             completion_provider: Some(CompletionOptions {
                 resolve_provider: Some(false),
                 trigger_characters: Some(vec![
@@ -114,6 +156,10 @@ pub(crate) async fn cache_path(mut context: Context<World>, params: Params<Cache
     if let Some(params) = params.optional() {
         let mut w = context.world().lock().await;
         w.cache_path = Some(params.path.into());
+        drop(w);
+
+        // The cached schemas might be different.
+        diagnostics::refresh_diagnostics(context).await;
     }
 }
 
@@ -293,11 +339,13 @@ async fn update_configuration(mut context: Context<World>, configuration: Option
                                 spawn(async move {
                                     let res = client.get(&path).send().await.unwrap();
 
-                                    let schema: RootSchema = match res
-                                        .json()
+                                    let schema = match res
+                                        .bytes()
                                         .await
                                         .map_err::<anyhow::Error, _>(Into::into)
-                                    {
+                                        .and_then(|bytes| {
+                                            schema_from_slice(&bytes).map_err(Into::into)
+                                        }) {
                                         Ok(s) => s,
                                         Err(e) => {
                                             log_error!("failed to retrieve schema from {}: {}", &path, e);
@@ -346,17 +394,29 @@ async fn update_configuration(mut context: Context<World>, configuration: Option
     }
 }
 
+pub(crate) async fn initialized(context: Context<World>, _params: Params<InitializedParams>) {
+    spawn(async move {
+        diagnostics::register_pull_diagnostics(context.clone()).await;
+        workspace::index_workspace(context.clone()).await;
+        diagnostics::publish_workspace_diagnostics(context).await;
+    });
+}
+
 pub(crate) async fn configuration_change(
     context: Context<World>,
     _params: Params<DidChangeConfigurationParams>,
 ) {
-    spawn(update_configuration(
-        context,
-        match _params.optional() {
-            Some(c) => serde_json::from_value(c.settings).ok(),
-            None => None,
-        },
-    ));
+    let configuration = match _params.optional() {
+        Some(c) => serde_json::from_value(c.settings).ok(),
+        None => None,
+    };
+
+    spawn(async move {
+        update_configuration(context.clone(), configuration).await;
+        workspace::index_workspace(context.clone()).await;
+        diagnostics::publish_workspace_diagnostics(context.clone()).await;
+        diagnostics::refresh_diagnostics(context).await;
+    });
 }
 
 pub(crate) async fn document_open(
@@ -379,6 +439,10 @@ pub(crate) async fn document_open(
         .documents
         .insert(p.text_document.uri, Document { parse, mapper });
 
+    if let Err(err) = discover_config_file(context.clone(), &uri).await {
+        log_error!("failed to load configuration file: {}", err);
+    }
+
     spawn(diagnostics::publish_diagnostics(context.clone(), uri));
 }
 
@@ -386,27 +450,53 @@ pub(crate) async fn document_change(
     mut context: Context<World>,
     params: Params<DidChangeTextDocumentParams>,
 ) {
-    let mut p = match params.optional() {
+    let p = match params.optional() {
         None => return,
         Some(p) => p,
     };
 
-    // We expect one full change
-    let change = match p.content_changes.pop() {
-        None => return,
-        Some(c) => c,
+    let uri = p.text_document.uri.clone();
+
+    let mut w = context.world().lock().await;
+
+    let mut doc = match w.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => Document {
+            parse: taplo::parser::parse(""),
+            mapper: Mapper::new_utf16("", false),
+        },
     };
 
-    let parse = taplo::parser::parse(&change.text);
-    let mapper = Mapper::new_utf16(&change.text, false);
-    let uri = p.text_document.uri.clone();
+    // The changes have to be applied in order,
+    // each range is relative to the document
+    // after the previous change.
+    for change in p.content_changes {
+        match change.range {
+            Some(range) => match doc
+                .mapper
+                .text_range(taplo::util::coords::Range::from_lsp(range))
+            {
+                Some(text_range) => {
+                    doc.parse = doc.parse.reparse(text_range, &change.text);
+                    doc.mapper.edit(text_range, &change.text);
+                }
+                None => {
+                    log_error!("invalid document change range: {:?}", range);
+                    return;
+                }
+            },
+            None => {
+                doc = Document {
+                    parse: taplo::parser::parse(&change.text),
+                    mapper: Mapper::new_utf16(&change.text, false),
+                };
+            }
+        }
+    }
 
-    context
-        .world()
-        .lock()
-        .await
-        .documents
-        .insert(p.text_document.uri, Document { parse, mapper });
+    w.documents.insert(p.text_document.uri, doc);
+
+    drop(w);
 
     spawn(diagnostics::publish_diagnostics(context.clone(), uri));
 }
@@ -427,7 +517,11 @@ pub(crate) async fn document_close(
         .documents
         .remove(&p.text_document.uri);
 
-    spawn(diagnostics::clear_diagnostics(context, p.text_document.uri));
+    // The document might still be in the workspace index.
+    spawn(diagnostics::publish_closed_diagnostics(
+        context,
+        p.text_document.uri,
+    ));
 }
 
 pub(crate) async fn semantic_tokens(
@@ -502,6 +596,118 @@ pub(crate) async fn format(
         .get(&p.text_document.uri)
         .ok_or_else(Error::invalid_params)?;
 
+    let (format_opts, scopes) = formatter_options(&w, &p.text_document.uri, &p.options);
+
+    Ok(Some(
+        formatter::format_edits(doc.parse.clone().into_dom(), format_opts, scopes)
+            .into_iter()
+            .map(|edit| TextEdit {
+                range: doc.mapper.range(edit.range).unwrap().into_lsp(),
+                new_text: edit.new_text,
+            })
+            .collect(),
+    ))
+}
+
+pub(crate) async fn format_range(
+    mut context: Context<World>,
+    params: Params<DocumentRangeFormattingParams>,
+) -> Result<Option<Vec<TextEdit>>, Error> {
+    let p = params.required()?;
+
+    let w = context.world().lock().await;
+
+    let doc = w
+        .documents
+        .get(&p.text_document.uri)
+        .ok_or_else(Error::invalid_params)?;
+
+    let range = doc
+        .mapper
+        .text_range(taplo::util::coords::Range::from_lsp(p.range))
+        .ok_or_else(Error::invalid_params)?;
+
+    let (format_opts, scopes) = formatter_options(&w, &p.text_document.uri, &p.options);
+
+    Ok(Some(
+        formatter::format_range(doc.parse.clone().into_dom(), range, format_opts, scopes)
+            .into_iter()
+            .map(|edit| TextEdit {
+                range: doc.mapper.range(edit.range).unwrap().into_lsp(),
+                new_text: edit.new_text,
+            })
+            .collect(),
+    ))
+}
+
+pub(crate) async fn format_on_type(
+    mut context: Context<World>,
+    params: Params<DocumentOnTypeFormattingParams>,
+) -> Result<Option<Vec<TextEdit>>, Error> {
+    let p = params.required()?;
+
+    let w = context.world().lock().await;
+
+    let uri = &p.text_document_position.text_document.uri;
+    let pos = p.text_document_position.position;
+
+    let doc = w.documents.get(uri).ok_or_else(Error::invalid_params)?;
+
+    let offset = doc
+        .mapper
+        .offset(taplo::util::coords::Position::from_lsp(pos))
+        .ok_or_else(Error::invalid_params)?;
+
+    let line_start = doc
+        .mapper
+        .offset(taplo::util::coords::Position::new(pos.line as u64, 0))
+        .ok_or_else(Error::invalid_params)?;
+
+    let range = match p.ch.as_str() {
+        // Format the line that was just finished,
+        // but never touch the new line that is being edited.
+        "\n" => {
+            if pos.line == 0 {
+                return Ok(None);
+            }
+
+            let prev_line_start = doc
+                .mapper
+                .offset(taplo::util::coords::Position::new(pos.line as u64 - 1, 0))
+                .ok_or_else(Error::invalid_params)?;
+
+            TextRange::new(prev_line_start, line_start - TextSize::from(1))
+        }
+        "]" | "}" if offset > TextSize::from(0) => {
+            TextRange::new(offset - TextSize::from(1), offset)
+        }
+        _ => return Ok(None),
+    };
+
+    let (format_opts, scopes) = formatter_options(&w, uri, &p.options);
+
+    Ok(Some(
+        formatter::format_range(doc.parse.clone().into_dom(), range, format_opts, scopes)
+            .into_iter()
+            .filter(|edit| p.ch != "\n" || edit.range.end() < line_start)
+            .map(|edit| TextEdit {
+                range: doc.mapper.range(edit.range).unwrap().into_lsp(),
+                new_text: edit.new_text,
+            })
+            .collect(),
+    ))
+}
+
+/// Formatter options for a document based on the configuration
+/// and the editor options.
+fn formatter_options(
+    w: &WorldState,
+    uri: &Url,
+    options: &FormattingOptions,
+) -> (
+    formatter::Options,
+    Vec<(String, formatter::OptionsIncomplete)>,
+) {
     let mut format_opts = formatter::Options::default();
 
     format_opts.update_camel(w.configuration.formatter.clone());
@@ -509,23 +715,14 @@ pub(crate) async fn format(
     if let Some(v) = w.configuration.formatter.indent_string.clone() {
         format_opts.indent_string = v;
     } else {
-        format_opts.indent_string = if p.options.insert_spaces {
-            " ".repeat(p.options.tab_size as usize)
+        format_opts.indent_string = if options.insert_spaces {
+            " ".repeat(options.tab_size as usize)
         } else {
             "\t".into()
         }
     }
 
-    let (format_opts, scopes) = w.get_config_formatter_options(&p.text_document.uri, format_opts);
-
-    Ok(Some(vec![TextEdit {
-        range: doc.mapper.all_range().into_lsp(),
-        new_text: taplo::formatter::format_with_path_scopes(
-            doc.parse.clone().into_dom(),
-            format_opts,
-            scopes,
-        ),
-    }]))
+    w.get_config_formatter_options(uri, format_opts)
 }
 
 pub(crate) async fn completion(
@@ -548,23 +745,15 @@ pub(crate) async fn completion(
         None => return Err(Error::new("document not found")),
     };
 
-    let schema_path = match w.get_schema_name(&uri) {
-        Some(s) => s,
-        None => return Ok(None),
-    };
-
     drop(w);
 
-    let schema: RootSchema = match WorldState::get_schema(
-        &uri,
-        &schema_path,
-        context.clone(),
-    ).await {
-        Ok(s) => s,
-        Err(err) => {
-            log_error!("failed to load schema ({}): {}", &schema_path, err);
+    let schema: RootSchema = match WorldState::get_document_schema(&uri, context.clone()).await {
+        Some(Ok(s)) => s,
+        Some(Err(err)) => {
+            log_error!("failed to load schema: {}", err);
             return Ok(None);
         }
+        None => return Ok(None),
     };
 
     Ok(Some(CompletionResponse::List(CompletionList {
@@ -593,23 +782,15 @@ pub(crate) async fn hover(
         None => return Err(Error::new("document not found")),
     };
 
-    let schema_path = match w.get_schema_name(&uri) {
-        Some(s) => s,
-        None => return Ok(None),
-    };
-
     drop(w);
 
-    let schema: RootSchema = match WorldState::get_schema(
-        &uri,
-        &schema_path,
-        context.clone()
-    ).await {
-        Ok(s) => s,
-        Err(err) => {
-            log_error!("failed to load schema ({}): {}", &schema_path, err);
+    let schema: RootSchema = match WorldState::get_document_schema(&uri, context.clone()).await {
+        Some(Ok(s)) => s,
+        Some(Err(err)) => {
+            log_error!("failed to load schema: {}", err);
             return Ok(None);
         }
+        None => return Ok(None),
     };
 
     let w = context.world().lock().await;
@@ -834,23 +1015,15 @@ pub(crate) async fn links(
         None => return Err(Error::new("document not found")),
     };
 
-    let schema_path = match w.get_schema_name(&uri) {
-        Some(s) => s,
-        None => return Ok(None),
-    };
-
     drop(w);
 
-    let schema: RootSchema = match WorldState::get_schema(
-        &uri,
-        &schema_path,
-        context.clone()
-    ).await {
-        Ok(s) => s,
-        Err(err) => {
+    let schema: RootSchema = match WorldState::get_document_schema(&uri, context.clone()).await {
+        Some(Ok(s)) => s,
+        Some(Err(err)) => {
             log_error!("failed to load schema: {}", err);
             return Ok(None);
         }
+        None => return Ok(None),
     };
 
     let dom = doc.parse.clone().into_dom();
@@ -1021,22 +1194,14 @@ pub(crate) async fn toml_to_json(
     })
 }
 
-// Required because JSON Keys can be in any order,
-// and toml-rs cannot handle it by default.
-#[derive(Default, Serialize, Deserialize)]
-#[serde(transparent)]
-struct JsonVal(
-    #[serde(serialize_with = "toml::ser::tables_last")] HashMap<String, serde_json::Value>,
-);
-
 pub(crate) async fn json_to_toml(
     _context: Context<World>,
     params: Params<msg_ext::JsonToTomlParams>,
 ) -> Result<msg_ext::JsonToTomlResponse, Error> {
     let p = params.required()?;
 
-    match serde_json::from_str::<JsonVal>(&p.text) {
-        Ok(v) => match toml::to_string_pretty(&v) {
+    match serde_json::from_str::<Value>(&p.text) {
+        Ok(v) => match taplo::ser::value_to_string(&v, formatter::Options::default()) {
             Ok(v) => Ok(msg_ext::JsonToTomlResponse {
                 text: Some(v),
                 error: None,
@@ -1071,6 +1236,7 @@ pub(crate) async fn syntax_tree(
 async fn load_config_file(mut context: Context<World>) -> Result<(), anyhow::Error> {
     let mut w = context.world().lock().await;
     w.taplo_config = None;
+    w.dir_configs.clear();
 
     if !w.configuration.taplo_config_enabled.unwrap_or(false) {
         return Ok(());
@@ -1079,9 +1245,9 @@ async fn load_config_file(mut context: Context<World>) -> Result<(), anyhow::Err
     if let Some(config_path) = &w.configuration.taplo_config {
         if !config_path.is_empty() {
             if is_absolute_path(&config_path) {
-                let f = read_file(&config_path).await?;
-
-                w.taplo_config = Some(toml::from_slice(&f)?);
+                let config_path = PathBuf::from(config_path);
+                let base_dir = w.workspace_path();
+                w.taplo_config = Some(read_config_file(&config_path, base_dir).await?);
 
                 return Ok(());
             } else {
@@ -1089,9 +1255,8 @@ async fn load_config_file(mut context: Context<World>) -> Result<(), anyhow::Err
 
                 match workspace_path {
                     Some(ws_path) => {
-                        let f = read_file(ws_path.join(&config_path).to_str().unwrap()).await?;
-
-                        w.taplo_config = Some(toml::from_slice(&f)?);
+                        let config_path = ws_path.join(&config_path);
+                        w.taplo_config = Some(read_config_file(&config_path, Some(ws_path)).await?);
 
                         return Ok(());
                     }
@@ -1104,22 +1269,77 @@ async fn load_config_file(mut context: Context<World>) -> Result<(), anyhow::Err
         }
     }
 
-    let workspace_path = w.workspace_path();
+    let uris: Vec<Url> = w.documents.keys().cloned().collect();
+    drop(w);
 
-    match workspace_path {
-        Some(ws_path) => {
-            for name in taplo_cli::config::CONFIG_FILE_NAMES {
-                if file_exists(ws_path.join(name).to_str().unwrap()) {
-                    if let Ok(cfg_file) = read_file(ws_path.join(name).to_str().unwrap()).await {
-                        w.taplo_config = Some(toml::from_slice(&cfg_file)?);
-                        drop(w);
+    for uri in uris {
+        discover_config_file(context.clone(), &uri).await?;
+    }
 
-                        return Ok(());
-                    }
-                }
-            }
-            Ok(())
-        }
-        None => Ok(()),
+    Ok(())
+}
+
+/// Loads the configuration file nearest to the document
+/// unless there is one set explicitly.
+async fn discover_config_file(mut context: Context<World>, uri: &Url) -> Result<(), anyhow::Error> {
+    let mut w = context.world().lock().await;
+
+    if !w.configuration.taplo_config_enabled.unwrap_or(false) || w.taplo_config.is_some() {
+        return Ok(());
     }
+
+    let dir = match uri
+        .to_file_path()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    if w.dir_configs.contains_key(&dir) {
+        return Ok(());
+    }
+
+    let config = match taplo_cli::config::find_config_file(&dir, |p| {
+        p.to_str().map(file_exists).unwrap_or(false)
+    }) {
+        Some(config_path) => {
+            // Configuration files nested in the workspace use their own directory.
+            let base_dir = w.workspace_path().map(|ws| {
+                taplo_cli::config::discovered_base_dir(&config_path, &ws).to_path_buf()
+            });
+
+            Some(read_config_file(&config_path, base_dir).await?)
+        }
+        None => None,
+    };
+
+    w.dir_configs.insert(dir, config);
+
+    Ok(())
+}
+
+/// Reads a configuration file along with the ones it extends.
+///
+/// Relative paths are resolved against the workspace, or the directory
+/// of the configuration file if there is no workspace open.
+async fn read_config_file(
+    path: &Path,
+    workspace_path: Option<PathBuf>,
+) -> Result<taplo_cli::config::Config, anyhow::Error> {
+    let base_dir = workspace_path
+        .or_else(|| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    taplo_cli::config::load_config_file(path, &base_dir, |path| async move {
+        let f = read_file(
+            path.to_str()
+                .ok_or_else(|| anyhow::anyhow!("invalid file path: {:?}", path))?,
+        )
+        .await?;
+
+        Ok::<_, anyhow::Error>(taplo::de::from_str(std::str::from_utf8(&f)?)?)
+    })
+    .await
 }
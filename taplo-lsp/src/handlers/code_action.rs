@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
-use crate::{utils::LspExt, World};
+use crate::{utils::LspExt, Document, World, WorldState};
 use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::*;
 use rowan::TextRange;
 use taplo::{
-    analytics::{NodeRef, PositionInfo},
-    dom::{ArrayNode, Entries, EntryNode, KeyNode, NodeSyntax, TableNode, ValueNode},
+    analytics::{NodeRef, PositionInfo, Suppressions},
+    code::Code,
+    dom::{rewrite::Editor, ArrayNode, Entries, EntryNode, NodeSyntax, TableNode, ValueNode},
     formatter,
+    schema::fix::{collect_fixes, FixKind, FixOptions},
     syntax::SyntaxKind,
     util::{coords::Mapper, syntax::join_ranges},
 };
@@ -25,45 +27,150 @@ pub(crate) async fn code_action(
         .ok_or_else(Error::invalid_params)?
         .clone();
     let format_opts_cfg = w.configuration.formatter.clone();
+    let schema_enabled = w.configuration.schema.enabled.unwrap_or_default();
     drop(w);
 
     let mut format_opts = formatter::Options::default();
     format_opts.update_camel(format_opts_cfg);
 
-    if p.range.start != p.range.end {
-        return Ok(Some(Vec::new()));
-    }
-
     let range = doc
         .mapper
         .text_range(taplo::util::coords::Range::from_lsp(p.range))
         .unwrap();
 
+    let mut actions = Vec::new();
+
+    if schema_enabled {
+        schema_fixes(&mut actions, &doc, range, &p, context.clone()).await;
+    }
+
+    if p.range.start != p.range.end {
+        return Ok(Some(actions));
+    }
+
     let dom = doc.parse.into_dom();
 
     let query = dom.query_position(range.start());
 
-    let mut actions = Vec::new();
+    let mut refactors = Vec::new();
 
     actions_for_position(
         query.after,
-        &mut actions,
+        &mut refactors,
         format_opts.clone(),
         &doc.mapper,
         &p,
     );
 
-    if !actions.is_empty() {
-        return Ok(Some(actions));
+    if refactors.is_empty() {
+        if let Some(pos) = query.before {
+            actions_for_position(pos, &mut refactors, format_opts.clone(), &doc.mapper, &p);
+        }
     }
 
-    if let Some(pos) = query.before {
-        actions_for_position(pos, &mut actions, format_opts.clone(), &doc.mapper, &p);
-    }
+    actions.extend(refactors);
 
     return Ok(Some(actions));
 }
 
+/// Quick fixes for the schema errors in the given range,
+/// each fix is a separate action.
+async fn schema_fixes(
+    actions: &mut Vec<CodeActionOrCommand>,
+    doc: &Document,
+    range: TextRange,
+    params: &CodeActionParams,
+    context: Context<World>,
+) {
+    let uri = &params.text_document.uri;
+
+    let schema = match WorldState::get_document_schema(uri, context).await {
+        Some(Ok(s)) => s,
+        Some(Err(err)) => {
+            log_error!("failed to load schema: {}", err);
+            return;
+        }
+        None => return,
+    };
+
+    if !doc.parse.errors.is_empty() {
+        return;
+    }
+
+    let dom = doc.parse.clone().into_dom();
+
+    if !dom.errors().is_empty() {
+        return;
+    }
+
+    let src = dom.syntax().to_string();
+    let suppressions = Suppressions::collect_from_syntax(doc.parse.clone().into_syntax());
+
+    // Removing keys is only a suggestion here,
+    // so it is always offered.
+    let options = FixOptions {
+        remove_additional: true,
+    };
+
+    let schema_code = NumberOrString::String(Code::Schema.id().into());
+
+    for fix in collect_fixes(&dom, &schema, options) {
+        if fix.range.intersect(range).is_none()
+            || suppressions.is_suppressed(Code::Schema, Some(fix.range.start()))
+        {
+            continue;
+        }
+
+        let mut editor = Editor::new(doc.parse.clone());
+
+        if fix.apply(&mut editor).is_err() {
+            continue;
+        }
+
+        let edits = formatter::diff(&src, &editor.to_string())
+            .into_iter()
+            .map(|edit| TextEdit {
+                range: doc.mapper.range(edit.range).unwrap().into_lsp(),
+                new_text: edit.new_text,
+            })
+            .collect();
+
+        let diagnostics: Vec<Diagnostic> = params
+            .context
+            .diagnostics
+            .iter()
+            .filter(|d| {
+                d.code.as_ref() == Some(&schema_code)
+                    && doc
+                        .mapper
+                        .text_range(taplo::util::coords::Range::from_lsp(d.range))
+                        .and_then(|r| r.intersect(fix.range))
+                        .is_some()
+            })
+            .cloned()
+            .collect();
+
+        let mut changes = HashMap::new();
+        changes.insert(uri.clone(), edits);
+
+        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+            title: fix.message,
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: if diagnostics.is_empty() {
+                None
+            } else {
+                Some(diagnostics)
+            },
+            edit: Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            }),
+            is_preferred: Some(fix.kind != FixKind::RemoveProperty),
+            ..Default::default()
+        }));
+    }
+}
+
 fn actions_for_position(
     pos: PositionInfo,
     actions: &mut Vec<CodeActionOrCommand>,
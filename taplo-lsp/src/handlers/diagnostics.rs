@@ -1,8 +1,13 @@
-use crate::{utils::LspExt, World, WorldState};
-use lsp_async_stub::{Context, RequestWriter};
-use lsp_types::*;
+use super::discover_config_file;
+use crate::{external::spawn, msg_ext, utils::LspExt, Document, World, WorldState};
+use futures::{stream, StreamExt};
+use lsp_async_stub::{rpc, Context, Params, RequestWriter};
+use lsp_types::{request::Request, *};
 use schemars::schema::{InstanceType, Metadata, RootSchema, SingleOrVec};
+use std::{collections::HashSet, path::Path};
 use taplo::{
+    analytics::Suppressions,
+    code::Code,
     dom::{self, NodeSyntax},
     parser::Parse,
     util::coords::Mapper,
@@ -16,9 +21,17 @@ use verify::{
 #[cfg(target_arch = "wasm32")]
 use crate::external::UrlExt;
 
+/// The amount of documents that are linted at the same time
+/// in the workspace, e.g. while waiting for their schemas.
+const LINT_CONCURRENCY: usize = 8;
+
 pub async fn publish_diagnostics(mut context: Context<World>, uri: Url) {
     let w = context.world().lock().await;
 
+    if w.pull_diagnostics {
+        return;
+    }
+
     let doc = match w.documents.get(&uri) {
         Some(d) => d.clone(),
         None => {
@@ -27,43 +40,7 @@ pub async fn publish_diagnostics(mut context: Context<World>, uri: Url) {
         }
     };
 
-    let excluded = w
-        .taplo_config
-        .as_ref()
-        .map(|c| {
-            c.is_excluded(uri.to_file_path().unwrap().to_str().unwrap())
-                .ok()
-                .unwrap_or(false)
-        })
-        .unwrap_or(false);
-
-    if excluded {
-        drop(w);
-        context
-            .write_notification::<notification::PublishDiagnostics, _>(Some(
-                PublishDiagnosticsParams {
-                    uri: uri.clone(),
-                    diagnostics: vec![Diagnostic {
-                        range: Default::default(),
-                        severity: Some(DiagnosticSeverity::Hint),
-                        code: None,
-                        code_description: None,
-                        source: Some("Even Better TOML".into()),
-                        message: "this file was excluded based on Taplo config".to_string(),
-                        tags: None,
-                        data: None,
-                        ..Default::default()
-                    }],
-                    version: None,
-                },
-            ))
-            .await
-            .unwrap_or_else(|err| log_error!("{}", err));
-
-        return;
-    }
-
-    let mut diags = collect_toml_diagnostics(&uri, &doc.parse, &doc.mapper);
+    let (mut diags, validate) = toml_diagnostics(&w, &uri, &doc);
     drop(w);
 
     context
@@ -75,35 +52,11 @@ pub async fn publish_diagnostics(mut context: Context<World>, uri: Url) {
         .await
         .unwrap_or_else(|err| log_error!("{}", err));
 
-    // Schema-related validations
-
-    // If there are errors already, further ones
-    // could be misleading
-    if !diags.is_empty() {
+    if !validate {
         return;
     }
 
-    let w = context.world().lock().await;
-
-    if !w.configuration.schema.enabled.unwrap_or_default() {
-        return;
-    }
-
-    let mut schema_diag = Vec::new();
-    match w.get_schema_name(&uri) {
-        Some(schema_path) => {
-            drop(w);
-            match WorldState::get_schema(&uri, &schema_path, context.clone()).await {
-                Ok(s) => {
-                    schema_diag = collect_schema_diagnostics(&s, &doc.parse, &uri, &doc.mapper);
-                }
-                Err(err) => {
-                    log_error!("failed to load schema: {}", err);
-                }
-            }
-        }
-        None => drop(w),
-    };
+    let schema_diag = schema_diagnostics(context.clone(), &uri, &doc).await;
 
     if !schema_diag.is_empty() {
         diags.extend(schema_diag.into_iter());
@@ -120,6 +73,63 @@ pub async fn publish_diagnostics(mut context: Context<World>, uri: Url) {
     }
 }
 
+/// Publishes the diagnostics of the documents in
+/// the workspace index that are not open.
+pub async fn publish_workspace_diagnostics(mut context: Context<World>) {
+    let w = context.world().lock().await;
+
+    if w.pull_diagnostics {
+        return;
+    }
+
+    let uris: Vec<Url> = w
+        .workspace_documents
+        .keys()
+        .filter(|uri| !w.documents.contains_key(uri))
+        .cloned()
+        .collect();
+    drop(w);
+
+    discover_config_files(context.clone(), &uris).await;
+
+    stream::iter(uris)
+        .for_each_concurrent(LINT_CONCURRENCY, |uri| {
+            publish_closed_diagnostics(context.clone(), uri)
+        })
+        .await;
+}
+
+/// Publishes the diagnostics of an indexed document that is not open,
+/// the diagnostics are cleared if it is not indexed.
+pub async fn publish_closed_diagnostics(mut context: Context<World>, uri: Url) {
+    let w = context.world().lock().await;
+
+    if w.pull_diagnostics || w.documents.contains_key(&uri) {
+        return;
+    }
+
+    let doc = match w.workspace_documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => {
+            drop(w);
+            clear_diagnostics(context, uri).await;
+            return;
+        }
+    };
+    drop(w);
+
+    let diagnostics = collect_diagnostics(context.clone(), &uri, &doc).await;
+
+    context
+        .write_notification::<notification::PublishDiagnostics, _>(Some(PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        }))
+        .await
+        .unwrap_or_else(|err| log_error!("{}", err));
+}
+
 pub async fn clear_diagnostics(mut context: Context<World>, uri: Url) {
     context
         .write_notification::<notification::PublishDiagnostics, _>(Some(PublishDiagnosticsParams {
@@ -131,6 +141,275 @@ pub async fn clear_diagnostics(mut context: Context<World>, uri: Url) {
         .unwrap_or_else(|err| log_error!("{}", err));
 }
 
+/// Registers pull diagnostics, the diagnostics are only
+/// published if the client does not support them.
+pub async fn register_pull_diagnostics(mut context: Context<World>) {
+    let supported = context
+        .world()
+        .lock()
+        .await
+        .client_capabilities
+        .pointer("/textDocument/diagnostic/dynamicRegistration")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    if !supported {
+        return;
+    }
+
+    let res = context
+        .write_request::<request::RegisterCapability, _>(Some(RegistrationParams {
+            registrations: vec![Registration {
+                id: "taplo-diagnostics".into(),
+                method: msg_ext::DocumentDiagnosticRequest::METHOD.into(),
+                register_options: Some(serde_json::json!({
+                    "documentSelector": [{ "language": "toml" }],
+                    "identifier": "taplo",
+                    "interFileDependencies": false,
+                    "workspaceDiagnostics": true,
+                })),
+            }],
+        }))
+        .await;
+
+    match res.map(|r| r.into_result()) {
+        Ok(Ok(_)) => {
+            context.world().lock().await.pull_diagnostics = true;
+        }
+        Ok(Err(err)) => {
+            log_debug!("pull diagnostics are not supported: {}", err);
+        }
+        Err(err) => {
+            log_error!("{}", err);
+        }
+    }
+}
+
+/// Asks the client to pull the diagnostics again if it pulls them,
+/// e.g. after the configuration, the schemas or the indexed files changed.
+pub async fn refresh_diagnostics(mut context: Context<World>) {
+    let w = context.world().lock().await;
+
+    let supported = w.pull_diagnostics
+        && w.client_capabilities
+            .pointer("/workspace/diagnostics/refreshSupport")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+    drop(w);
+
+    if !supported {
+        return;
+    }
+
+    // The handlers should not wait for the response of the client.
+    spawn(async move {
+        if let Err(err) = context
+            .write_request::<msg_ext::WorkspaceDiagnosticRefresh, _>(None)
+            .await
+        {
+            log_error!("{}", err);
+        }
+    });
+}
+
+pub(crate) async fn document_diagnostic(
+    mut context: Context<World>,
+    params: Params<msg_ext::DocumentDiagnosticParams>,
+) -> Result<msg_ext::DocumentDiagnosticReport, rpc::Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document.uri;
+
+    let w = context.world().lock().await;
+    let doc = w
+        .documents
+        .get(&uri)
+        .or_else(|| w.workspace_documents.get(&uri))
+        .cloned()
+        .ok_or_else(|| rpc::Error::new("document not found"))?;
+    drop(w);
+
+    Ok(msg_ext::DocumentDiagnosticReport::Full {
+        result_id: None,
+        items: collect_diagnostics(context, &uri, &doc).await,
+    })
+}
+
+pub(crate) async fn workspace_diagnostic(
+    mut context: Context<World>,
+    _params: Params<msg_ext::WorkspaceDiagnosticParams>,
+) -> Result<msg_ext::WorkspaceDiagnosticReport, rpc::Error> {
+    let w = context.world().lock().await;
+
+    // The open documents are pulled one by one.
+    let documents: Vec<(Url, Document)> = w
+        .workspace_documents
+        .iter()
+        .filter(|(uri, _)| !w.documents.contains_key(uri))
+        .map(|(uri, doc)| (uri.clone(), doc.clone()))
+        .collect();
+    drop(w);
+
+    let uris: Vec<Url> = documents.iter().map(|(uri, _)| uri.clone()).collect();
+    discover_config_files(context.clone(), &uris).await;
+
+    let items = stream::iter(documents)
+        .map(|(uri, doc)| {
+            let context = context.clone();
+            async move {
+                let diagnostics = lint_document(context, &uri, &doc).await;
+
+                msg_ext::WorkspaceDocumentDiagnosticReport {
+                    uri,
+                    version: None,
+                    report: msg_ext::DocumentDiagnosticReport::Full {
+                        result_id: None,
+                        items: diagnostics,
+                    },
+                }
+            }
+        })
+        .buffered(LINT_CONCURRENCY)
+        .collect()
+        .await;
+
+    Ok(msg_ext::WorkspaceDiagnosticReport { items })
+}
+
+/// Loads the configuration files of the documents,
+/// each directory is only looked up once.
+async fn discover_config_files(context: Context<World>, uris: &[Url]) {
+    let mut dirs = HashSet::new();
+
+    for uri in uris {
+        let dir = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf));
+
+        if !dirs.insert(dir) {
+            continue;
+        }
+
+        if let Err(err) = discover_config_file(context.clone(), uri).await {
+            log_error!("failed to load configuration file: {}", err);
+        }
+    }
+}
+
+/// All the diagnostics of a document, including schema validation.
+pub(crate) async fn collect_diagnostics(
+    context: Context<World>,
+    uri: &Url,
+    doc: &Document,
+) -> Vec<Diagnostic> {
+    discover_config_files(context.clone(), std::slice::from_ref(uri)).await;
+    lint_document(context, uri, doc).await
+}
+
+/// The diagnostics of a document with the configuration
+/// files that were already loaded for its directory.
+async fn lint_document(mut context: Context<World>, uri: &Url, doc: &Document) -> Vec<Diagnostic> {
+    let w = context.world().lock().await;
+    let (mut diags, validate) = toml_diagnostics(&w, uri, doc);
+    drop(w);
+
+    if validate {
+        diags.extend(schema_diagnostics(context, uri, doc).await);
+    }
+
+    diags
+}
+
+/// Syntax and TOML diagnostics, and whether
+/// the document should be validated with its schema.
+fn toml_diagnostics(w: &WorldState, uri: &Url, doc: &Document) -> (Vec<Diagnostic>, bool) {
+    let excluded = w
+        .taplo_config(uri)
+        .map(|c| {
+            c.is_excluded(uri.to_file_path().unwrap().to_str().unwrap())
+                .ok()
+                .unwrap_or(false)
+        })
+        .unwrap_or(false);
+
+    if excluded {
+        let diagnostics = vec![Diagnostic {
+            range: Default::default(),
+            severity: Some(DiagnosticSeverity::Hint),
+            code: None,
+            code_description: None,
+            source: Some("Even Better TOML".into()),
+            message: "this file was excluded based on Taplo config".to_string(),
+            tags: None,
+            data: None,
+            ..Default::default()
+        }];
+
+        return (diagnostics, false);
+    }
+
+    let suppressions = Suppressions::collect_from_syntax(doc.parse.clone().into_syntax());
+
+    let toml_diags = collect_toml_diagnostics(uri, &doc.parse, &doc.mapper);
+
+    // Suppressed errors still prevent further validation.
+    //
+    // If there are errors already, further ones
+    // could be misleading.
+    let validate = toml_diags.is_empty() && w.configuration.schema.enabled.unwrap_or_default();
+
+    (
+        unsuppressed(toml_diags, &suppressions, &doc.mapper),
+        validate,
+    )
+}
+
+// Schema-related validations
+async fn schema_diagnostics(context: Context<World>, uri: &Url, doc: &Document) -> Vec<Diagnostic> {
+    match WorldState::get_document_schema(uri, context).await {
+        Some(Ok(s)) => unsuppressed(
+            collect_schema_diagnostics(&s, &doc.parse, uri, &doc.mapper),
+            &Suppressions::collect_from_syntax(doc.parse.clone().into_syntax()),
+            &doc.mapper,
+        ),
+        Some(Err(err)) => {
+            log_error!("failed to load schema: {}", err);
+            Vec::new()
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Removes the diagnostics suppressed by `#:taplo-ignore` directives.
+fn unsuppressed(
+    diags: Vec<Diagnostic>,
+    suppressions: &Suppressions,
+    mapper: &Mapper,
+) -> Vec<Diagnostic> {
+    if suppressions.is_empty() {
+        return diags;
+    }
+
+    diags
+        .into_iter()
+        .filter(|d| {
+            let code = match &d.code {
+                Some(NumberOrString::String(code)) => match code.parse::<Code>() {
+                    Ok(code) => code,
+                    Err(_) => return true,
+                },
+                _ => return true,
+            };
+
+            let offset = mapper.offset(taplo::util::coords::Position::from_lsp(d.range.start));
+
+            !suppressions.is_suppressed(code, offset)
+        })
+        .collect()
+}
+
 // Syntax and TOML rules validations
 fn collect_toml_diagnostics(uri: &Url, parse: &Parse, mapper: &Mapper) -> Vec<Diagnostic> {
     let mut diag: Vec<Diagnostic> = parse
@@ -141,7 +420,7 @@ fn collect_toml_diagnostics(uri: &Url, parse: &Parse, mapper: &Mapper) -> Vec<Di
             Diagnostic {
                 range,
                 severity: Some(DiagnosticSeverity::Error),
-                code: None,
+                code: Some(diagnostic_code(e.code)),
                 code_description: None,
                 source: Some("Even Better TOML".into()),
                 message: e.message.clone(),
@@ -160,6 +439,8 @@ fn collect_toml_diagnostics(uri: &Url, parse: &Parse, mapper: &Mapper) -> Vec<Di
     let dom = parse.clone().into_dom();
 
     for err in dom.errors() {
+        let start = diag.len();
+
         match err {
             dom::Error::DuplicateKey { first, second } => {
                 let first_range = mapper.range(first.syntax().text_range()).unwrap();
@@ -410,11 +691,19 @@ fn collect_toml_diagnostics(uri: &Url, parse: &Parse, mapper: &Mapper) -> Vec<Di
                 });
             }
         }
+
+        for d in &mut diag[start..] {
+            d.code = Some(diagnostic_code(err.code()));
+        }
     }
 
     diag
 }
 
+fn diagnostic_code(code: Code) -> NumberOrString {
+    NumberOrString::String(code.id().into())
+}
+
 // JSON Schema validation
 fn collect_schema_diagnostics(
     schema: &RootSchema,
@@ -431,6 +720,10 @@ fn collect_schema_diagnostics(
                 diag.extend(diags_from_error(error, uri, mapper))
             }
 
+            for d in &mut diag {
+                d.code = Some(diagnostic_code(Code::Schema));
+            }
+
             diag
         }
     }
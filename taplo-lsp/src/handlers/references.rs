@@ -0,0 +1,153 @@
+//! Keys of the same table or value can be spread over multiple
+//! headers, dotted keys and arrays of tables, every one of
+//! these keys is treated as both a definition and a reference.
+
+use crate::{utils::LspExt, Document, World};
+use lsp_async_stub::{rpc::Error, Context, Params};
+use lsp_types::*;
+use rowan::{TextRange, TextSize};
+use taplo::{analytics::NodeRef, dom};
+
+pub(crate) async fn definition(
+    mut context: Context<World>,
+    params: Params<GotoDefinitionParams>,
+) -> Result<Option<GotoDefinitionResponse>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document_position_params.text_document.uri;
+    let pos = p.text_document_position_params.position;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    Ok(key_references(&doc, pos).map(|ranges| {
+        GotoDefinitionResponse::Array(
+            ranges
+                .into_iter()
+                .map(|range| Location {
+                    uri: uri.clone(),
+                    range,
+                })
+                .collect(),
+        )
+    }))
+}
+
+pub(crate) async fn references(
+    mut context: Context<World>,
+    params: Params<ReferenceParams>,
+) -> Result<Option<Vec<Location>>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document_position.text_document.uri;
+    let pos = p.text_document_position.position;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    Ok(key_references(&doc, pos).map(|ranges| {
+        ranges
+            .into_iter()
+            .map(|range| Location {
+                uri: uri.clone(),
+                range,
+            })
+            .collect()
+    }))
+}
+
+pub(crate) async fn document_highlight(
+    mut context: Context<World>,
+    params: Params<DocumentHighlightParams>,
+) -> Result<Option<Vec<DocumentHighlight>>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document_position_params.text_document.uri;
+    let pos = p.text_document_position_params.position;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    Ok(key_references(&doc, pos).map(|ranges| {
+        ranges
+            .into_iter()
+            .map(|range| DocumentHighlight {
+                range,
+                kind: Some(DocumentHighlightKind::Text),
+            })
+            .collect()
+    }))
+}
+
+/// The ranges of all the keys with the same path as the key at the position,
+/// in the order they appear in the document.
+///
+/// Returns [`None`] if there is no key at the position.
+pub(crate) fn key_references(doc: &Document, pos: Position) -> Option<Vec<Range>> {
+    let offset = doc
+        .mapper
+        .offset(taplo::util::coords::Position::from_lsp(pos))?;
+    let dom = doc.parse.clone().into_dom();
+
+    let (_, _, ranges) = key_ranges_at(&dom, offset)?;
+
+    Some(
+        ranges
+            .into_iter()
+            .filter_map(|range| doc.mapper.range(range).map(|r| r.into_lsp()))
+            .collect(),
+    )
+}
+
+/// Resolves the path of the key at the offset, and returns it with
+/// the range of the key along with the ranges of every key with the same path.
+///
+/// The cursor can also be right after the key.
+pub(crate) fn key_ranges_at(
+    dom: &dom::RootNode,
+    offset: TextSize,
+) -> Option<(dom::Path, TextRange, Vec<TextRange>)> {
+    let query = dom.query_position(offset);
+    let before = query.before;
+
+    let after_path = query.after.path;
+
+    let (path, range) = key_at(dom, &after_path, offset)
+        .map(|range| (after_path, range))
+        .or_else(|| {
+            let path = before?.path;
+            let range = key_at(dom, &path, offset - TextSize::from(1))?;
+            Some((path, range))
+        })?;
+
+    let mut ranges = key_ranges(dom, &path);
+    ranges.sort_by_key(|r| r.start());
+    ranges.dedup();
+
+    Some((path, range, ranges))
+}
+
+/// The range of the key with the given path that contains the offset.
+fn key_at(dom: &dom::RootNode, path: &dom::Path, offset: TextSize) -> Option<TextRange> {
+    key_ranges(dom, path)
+        .into_iter()
+        .find(|r| r.contains(offset))
+}
+
+/// The ranges of every key with the given path.
+pub(crate) fn key_ranges(dom: &dom::RootNode, path: &dom::Path) -> Vec<TextRange> {
+    dom.iter()
+        .filter(|(p, _)| p == path)
+        .filter_map(|(_, node)| match node {
+            NodeRef::Key(k) => Some(k.text_ranges()),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
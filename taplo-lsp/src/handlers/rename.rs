@@ -0,0 +1,150 @@
+use super::references::key_ranges_at;
+use crate::{utils::LspExt, Document, World};
+use lsp_async_stub::{rpc::Error, Context, Params};
+use lsp_types::*;
+use rowan::TextRange;
+use std::collections::HashMap;
+use taplo::{
+    dom,
+    syntax::SyntaxKind::*,
+    util::{quote_key, StrExt},
+};
+
+pub(crate) async fn prepare_rename(
+    mut context: Context<World>,
+    params: Params<TextDocumentPositionParams>,
+) -> Result<Option<PrepareRenameResponse>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document.uri;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    let (_, range, _) = match renamed_keys(&doc, p.position)? {
+        Some(keys) => keys,
+        None => return Ok(None),
+    };
+
+    Ok(Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: doc.mapper.range(range).unwrap().into_lsp(),
+        placeholder: doc
+            .parse
+            .clone()
+            .into_syntax()
+            .text()
+            .slice(range)
+            .to_string(),
+    }))
+}
+
+pub(crate) async fn rename(
+    mut context: Context<World>,
+    params: Params<RenameParams>,
+) -> Result<Option<WorkspaceEdit>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document_position.text_document.uri;
+    let pos = p.text_document_position.position;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    let (path, _, ranges) = match renamed_keys(&doc, pos)? {
+        Some(keys) => keys,
+        None => return Ok(None),
+    };
+
+    let new_key = if is_single_key(&p.new_name) {
+        p.new_name
+    } else {
+        quote_key(&p.new_name)
+    };
+
+    if is_taken(&doc.parse.clone().into_dom(), &path, &new_key) {
+        return Err(Error::new(&format!(
+            "a key named {} already exists",
+            new_key
+        )));
+    }
+
+    let edits = ranges
+        .into_iter()
+        .map(|range| TextEdit {
+            range: doc.mapper.range(range).unwrap().into_lsp(),
+            new_text: new_key.clone(),
+        })
+        .collect();
+
+    let mut changes = HashMap::new();
+    changes.insert(uri, edits);
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }))
+}
+
+/// The path of the key at the position, the key itself
+/// and all the keys that would be renamed with it.
+///
+/// Documents with errors are not renamed, as the DOM
+/// might be missing some of the keys.
+pub(crate) fn renamed_keys(
+    doc: &Document,
+    pos: Position,
+) -> Result<Option<(dom::Path, TextRange, Vec<TextRange>)>, Error> {
+    let offset = match doc
+        .mapper
+        .offset(taplo::util::coords::Position::from_lsp(pos))
+    {
+        Some(o) => o,
+        None => return Ok(None),
+    };
+
+    let dom = doc.parse.clone().into_dom();
+
+    if !doc.parse.errors.is_empty() || !dom.errors().is_empty() {
+        return Err(Error::new("cannot rename keys in a document with errors"));
+    }
+
+    Ok(key_ranges_at(&dom, offset))
+}
+
+/// Whether renaming the key with the given path to the new key
+/// would merge it with a sibling key that already exists.
+pub(crate) fn is_taken(dom: &dom::RootNode, path: &dom::Path, new_key: &str) -> bool {
+    let new_path = path.skip_right(1).join(new_key.strip_quotes());
+
+    if new_path.keys().eq(path.keys()) {
+        return false;
+    }
+
+    dom.iter().any(|(p, _)| p.keys().eq(new_path.keys()))
+}
+
+/// Whether the name is already a valid bare or quoted key,
+/// other names are quoted before they are used.
+pub(crate) fn is_single_key(name: &str) -> bool {
+    let parse = taplo::parser::parse(&format!("{} = 0", name));
+
+    if !parse.errors.is_empty() {
+        return false;
+    }
+
+    let key = match parse.into_syntax().descendants().find(|n| n.kind() == KEY) {
+        Some(k) => k,
+        None => return false,
+    };
+
+    let mut idents = key.children_with_tokens().filter(|t| t.kind() == IDENT);
+
+    match (idents.next(), idents.next()) {
+        (Some(ident), None) => ident.as_token().map(|t| t.text() == name).unwrap_or(false),
+        _ => false,
+    }
+}
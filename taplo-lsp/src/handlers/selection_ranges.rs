@@ -0,0 +1,150 @@
+//! Selection ranges follow the syntax tree, with an additional
+//! range for the table sections, as the entries of a table are
+//! not children of its header.
+
+use crate::{utils::LspExt, World};
+use lsp_async_stub::{rpc::Error, Context, Params};
+use lsp_types::*;
+use rowan::{Direction, TextRange, TextSize, TokenAtOffset};
+use taplo::syntax::{SyntaxKind::*, SyntaxNode};
+
+pub(crate) async fn selection_ranges(
+    mut context: Context<World>,
+    params: Params<SelectionRangeParams>,
+) -> Result<Option<Vec<SelectionRange>>, Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document.uri;
+
+    let doc = match context.world().lock().await.documents.get(&uri) {
+        Some(d) => d.clone(),
+        None => return Err(Error::new("document not found")),
+    };
+
+    let syntax = doc.parse.clone().into_syntax();
+
+    // There must be a selection range for every position.
+    Ok(Some(
+        p.positions
+            .into_iter()
+            .map(|pos| {
+                let ranges = doc
+                    .mapper
+                    .offset(taplo::util::coords::Position::from_lsp(pos))
+                    .map(|offset| syntax_ranges(&syntax, offset))
+                    .unwrap_or_default();
+
+                let mut selection: Option<SelectionRange> = None;
+
+                for range in ranges.into_iter().rev() {
+                    if let Some(range) = doc.mapper.range(range) {
+                        selection = Some(SelectionRange {
+                            range: range.into_lsp(),
+                            parent: selection.map(Box::new),
+                        });
+                    }
+                }
+
+                selection.unwrap_or(SelectionRange {
+                    range: Range::new(pos, pos),
+                    parent: None,
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// The ranges containing the offset, from the innermost to the document.
+pub(crate) fn syntax_ranges(syntax: &SyntaxNode, offset: TextSize) -> Vec<TextRange> {
+    let token = match syntax.token_at_offset(offset) {
+        TokenAtOffset::None => return Vec::new(),
+        TokenAtOffset::Single(t) => t,
+        TokenAtOffset::Between(left, right) => match right.kind() {
+            WHITESPACE | NEWLINE => left,
+            _ => right,
+        },
+    };
+
+    let mut ranges: Vec<TextRange> = Vec::new();
+
+    // Punctuation is only selected along with its parent.
+    if !matches!(
+        token.kind(),
+        WHITESPACE
+            | NEWLINE
+            | PERIOD
+            | COMMA
+            | EQ
+            | BRACKET_START
+            | BRACKET_END
+            | BRACE_START
+            | BRACE_END
+    ) {
+        ranges.push(token.text_range());
+    }
+
+    let mut top_level = None;
+
+    // Every range must contain the previous ones.
+    let mut push = |range: TextRange| {
+        let range = match ranges.last() {
+            Some(last) => range.cover(*last),
+            None => range,
+        };
+        ranges.push(range);
+    };
+
+    for node in token.ancestors() {
+        if node.kind() == ROOT {
+            if let Some(range) = top_level.as_ref().and_then(section_range) {
+                push(range);
+            }
+        }
+
+        // The cursor might be in the whitespace around the node.
+        let trimmed = trimmed_range(&node);
+        if trimmed.contains_inclusive(offset) {
+            push(trimmed);
+        } else {
+            push(node.text_range());
+        }
+
+        top_level = Some(node);
+    }
+
+    ranges.dedup();
+    ranges
+}
+
+/// The range of the table section that contains the top-level node,
+/// from the header to the last entry before the next header.
+pub(crate) fn section_range(node: &SyntaxNode) -> Option<TextRange> {
+    let header = node
+        .siblings(Direction::Prev)
+        .find(|n| matches!(n.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))?;
+
+    let end = header
+        .siblings(Direction::Next)
+        .skip(1)
+        .take_while(|n| !matches!(n.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))
+        .last()
+        .map(|n| trimmed_range(&n).end())
+        .unwrap_or_else(|| trimmed_range(&header).end());
+
+    Some(TextRange::new(header.text_range().start(), end))
+}
+
+/// The range of the node without the surrounding whitespace,
+/// e.g. keys include the whitespace before `=`.
+fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(|t| t.into_token())
+        .filter(|t| !matches!(t.kind(), WHITESPACE | NEWLINE))
+        .map(|t| t.text_range());
+
+    match tokens.next() {
+        Some(first) => first.cover(tokens.last().unwrap_or(first)),
+        None => node.text_range(),
+    }
+}
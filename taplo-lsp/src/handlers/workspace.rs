@@ -0,0 +1,332 @@
+//! Indexing of the TOML files in the workspace.
+//!
+//! The files are included and excluded by the taplo configuration,
+//! and the index is updated with the file events sent by the client.
+
+use super::{
+    diagnostics::{publish_closed_diagnostics, publish_workspace_diagnostics, refresh_diagnostics},
+    load_config_file, read_config_file,
+};
+use crate::{external::*, utils::LspExt, Document, World};
+use lsp_async_stub::{rpc::Error, Context, Params};
+use lsp_types::*;
+use std::path::{Path, PathBuf};
+use taplo::{
+    analytics::NodeRef,
+    dom::{self, ValueNode},
+    util::coords::Mapper,
+};
+use taplo_cli::config::{find_config_file, Config, CONFIG_FILE_NAMES};
+
+/// The most symbols returned for a workspace symbol query,
+/// a short query could match every key in a large workspace.
+pub(crate) const MAX_WORKSPACE_SYMBOLS: usize = 512;
+
+/// Indexes all the included TOML files in the workspace,
+/// replacing the previous index.
+pub(crate) async fn index_workspace(mut context: Context<World>) {
+    let w = context.world().lock().await;
+
+    let (ws_uri, ws_path) = match (w.workspace_uri.clone(), w.workspace_path()) {
+        (Some(uri), Some(path)) => (uri, path),
+        _ => return,
+    };
+
+    let config_enabled = w.configuration.taplo_config_enabled.unwrap_or(false);
+    let explicit_config = w.taplo_config.clone();
+    drop(w);
+
+    let config = match explicit_config {
+        Some(c) => c,
+        None if config_enabled => {
+            match find_config_file(&ws_path, |p| p.to_str().map(file_exists).unwrap_or(false)) {
+                Some(config_path) => {
+                    match read_config_file(&config_path, Some(ws_path.clone())).await {
+                        Ok(c) => c,
+                        Err(err) => {
+                            log_error!("failed to load configuration file: {}", err);
+                            Config::default()
+                        }
+                    }
+                }
+                None => Config::default(),
+            }
+        }
+        None => Config::default(),
+    };
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    for pattern in config.get_include_paths() {
+        let pattern = if is_absolute_path(&pattern) {
+            pattern
+        } else {
+            Path::new(&glob::Pattern::escape(&ws_path.to_string_lossy()))
+                .join(&pattern)
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        match get_paths_by_glob(&pattern) {
+            Ok(p) => paths.extend(p),
+            Err(err) => {
+                log_warn!("failed to index the workspace: {}", err);
+                return;
+            }
+        }
+    }
+
+    paths.sort();
+    paths.dedup();
+
+    let mut documents = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        if !is_included(&config, &path) {
+            continue;
+        }
+
+        let uri = match file_url(&ws_uri, &ws_path, &path) {
+            Some(uri) => uri,
+            None => continue,
+        };
+
+        if let Some(doc) = read_document(&path).await {
+            documents.push((uri, doc));
+        }
+    }
+
+    let mut w = context.world().lock().await;
+
+    log_info!("indexed {} documents in the workspace", documents.len());
+
+    w.workspace_documents = documents.into_iter().collect();
+    w.workspace_config = Some(config);
+}
+
+pub(crate) async fn watched_files_change(
+    mut context: Context<World>,
+    params: Params<DidChangeWatchedFilesParams>,
+) {
+    let p = match params.optional() {
+        None => return,
+        Some(p) => p,
+    };
+
+    let w = context.world().lock().await;
+
+    let (ws_uri, ws_path, config) = match (
+        w.workspace_uri.clone(),
+        w.workspace_path(),
+        w.workspace_config.clone(),
+    ) {
+        (Some(uri), Some(path), Some(config)) => (uri, path, config),
+        _ => return,
+    };
+
+    drop(w);
+
+    let config_changed = p.changes.iter().any(|change| {
+        change
+            .uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|name| CONFIG_FILE_NAMES.contains(&name))
+            .unwrap_or(false)
+    });
+
+    // The included files might have changed.
+    if config_changed {
+        if let Err(err) = load_config_file(context.clone()).await {
+            log_error!("failed to load configuration file: {}", err);
+        }
+
+        index_workspace(context.clone()).await;
+        publish_workspace_diagnostics(context.clone()).await;
+        refresh_diagnostics(context).await;
+        return;
+    }
+
+    for change in p.changes {
+        let path = match change.uri.to_file_path() {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+
+        let doc = match change.typ {
+            FileChangeType::Deleted => None,
+            _ if is_included(&config, &path) => read_document(&path).await,
+            _ => None,
+        };
+
+        // Normalize the URL, so that it matches the indexed ones.
+        let uri = file_url(&ws_uri, &ws_path, &path).unwrap_or(change.uri);
+
+        let mut w = context.world().lock().await;
+
+        match doc {
+            Some(doc) => {
+                w.workspace_documents.insert(uri.clone(), doc);
+            }
+            None => {
+                w.workspace_documents.remove(&uri);
+            }
+        }
+
+        drop(w);
+
+        publish_closed_diagnostics(context.clone(), uri).await;
+    }
+
+    refresh_diagnostics(context).await;
+}
+
+pub(crate) async fn workspace_symbols(
+    mut context: Context<World>,
+    params: Params<WorkspaceSymbolParams>,
+) -> Result<Option<Vec<SymbolInformation>>, Error> {
+    let p = params.required()?;
+
+    let w = context.world().lock().await;
+
+    // Open documents take precedence over their contents on the disk.
+    let documents = w.documents.iter().chain(
+        w.workspace_documents
+            .iter()
+            .filter(|(uri, _)| !w.documents.contains_key(uri)),
+    );
+
+    let mut symbols = Vec::new();
+
+    for (uri, doc) in documents {
+        symbols_for_document(uri, doc, &p.query, &mut symbols);
+
+        if symbols.len() >= MAX_WORKSPACE_SYMBOLS {
+            break;
+        }
+    }
+
+    Ok(Some(symbols))
+}
+
+/// Adds the symbols of the document that match the query
+/// until there are [`MAX_WORKSPACE_SYMBOLS`] symbols.
+#[allow(deprecated)]
+pub(crate) fn symbols_for_document(
+    uri: &Url,
+    doc: &Document,
+    query: &str,
+    symbols: &mut Vec<SymbolInformation>,
+) {
+    let dom = doc.parse.clone().into_dom();
+
+    for (path, node) in dom.iter() {
+        if symbols.len() >= MAX_WORKSPACE_SYMBOLS {
+            return;
+        }
+
+        let entry = match node {
+            NodeRef::Entry(entry) => entry,
+            _ => continue,
+        };
+
+        let name = path.dotted();
+
+        if !matches_query(&name, query) {
+            continue;
+        }
+
+        let range = match entry
+            .key()
+            .text_ranges()
+            .first()
+            .and_then(|r| doc.mapper.range(*r))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+
+        symbols.push(SymbolInformation {
+            name,
+            kind: symbol_kind(entry.value()),
+            tags: Default::default(),
+            deprecated: None,
+            location: Location {
+                uri: uri.clone(),
+                range: range.into_lsp(),
+            },
+            container_name: container_name(&path),
+        });
+    }
+}
+
+fn symbol_kind(value: &ValueNode) -> SymbolKind {
+    match value {
+        ValueNode::Bool(_) => SymbolKind::Boolean,
+        ValueNode::String(_) => SymbolKind::String,
+        ValueNode::Integer(_) | ValueNode::Float(_) => SymbolKind::Number,
+        ValueNode::Array(_) => SymbolKind::Array,
+        ValueNode::Table(_) => SymbolKind::Object,
+        _ => SymbolKind::Field,
+    }
+}
+
+fn container_name(path: &dom::Path) -> Option<String> {
+    if path.len() > 1 {
+        Some(path.skip_right(1).dotted())
+    } else {
+        None
+    }
+}
+
+/// Case-insensitive fuzzy matching, the characters of the query
+/// must appear in the name in the same order.
+pub(crate) fn matches_query(name: &str, query: &str) -> bool {
+    let mut chars = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+pub(crate) fn is_included(config: &Config, path: &Path) -> bool {
+    match path.to_str().map(|p| config.is_included(p)) {
+        Some(Ok(included)) => included,
+        Some(Err(err)) => {
+            log_warn!("invalid config: {}", err);
+            false
+        }
+        None => false,
+    }
+}
+
+async fn read_document(path: &Path) -> Option<Document> {
+    let data = match read_file(path.to_str()?).await {
+        Ok(d) => d,
+        Err(err) => {
+            log_warn!("failed to read {:?}: {}", path, err);
+            return None;
+        }
+    };
+
+    let text = String::from_utf8_lossy(&data);
+
+    Some(Document {
+        parse: taplo::parser::parse(&text),
+        mapper: Mapper::new_utf16(&text, false),
+    })
+}
+
+/// The URL of a file in the workspace,
+/// [`None`] if the file is outside of it.
+pub(crate) fn file_url(ws_uri: &Url, ws_path: &Path, path: &Path) -> Option<Url> {
+    let relative = path.strip_prefix(ws_path).ok()?;
+
+    let mut uri = ws_uri.clone();
+    uri.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(relative.iter().filter_map(|s| s.to_str()));
+
+    Some(uri)
+}
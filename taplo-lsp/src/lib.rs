@@ -13,11 +13,16 @@ use lsp_types::{notification, request, Url};
 use schemars::{schema::RootSchema, schema_for};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, hash::Hash, path::Path, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, hash::Hash, path::PathBuf, sync::Arc};
 use taplo::{
     analytics::Directive,
     parser::Parse,
-    schema::{CachedSchema, BUILTIN_SCHEME},
+    schema::{
+        builtin::{associated_schema, builtin_schema},
+        bundle::SchemaBundle,
+        util::{attach_schema, schema_from_slice},
+        CachedSchema, BUILTIN_SCHEME,
+    },
     util::coords::Mapper,
 };
 
@@ -38,6 +43,9 @@ mod handlers;
 mod msg_ext;
 mod utils;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Clone)]
 pub struct Document {
     parse: Parse,
@@ -72,14 +80,37 @@ pub struct WorldState {
     cache_path: Option<PathBuf>,
     workspace_uri: Option<Url>,
     documents: HashMap<lsp_types::Url, Document>,
+    /// The TOML files in the workspace included by the configuration,
+    /// as they are on the disk.
+    workspace_documents: HashMap<lsp_types::Url, Document>,
+    /// The configuration that decides which files in the workspace are indexed.
+    workspace_config: Option<taplo_cli::config::Config>,
     schema_associations: IndexMap<HashRegex, String>,
     index_schema_associations: IndexMap<HashRegex, String>,
     http_client: reqwest::Client,
     configuration: Configuration,
+    /// The configuration file set explicitly in the settings.
     taplo_config: Option<taplo_cli::config::Config>,
+    /// The nearest configuration files for the directories of the documents.
+    dir_configs: HashMap<PathBuf, Option<taplo_cli::config::Config>>,
+    /// The client pulls the diagnostics, so they are not published.
+    pull_diagnostics: bool,
+    /// The capabilities of the client as they were sent.
+    client_capabilities: serde_json::Value,
 }
 
 impl WorldState {
+    /// The configuration for the document, either the one
+    /// set explicitly or the one nearest to the document.
+    fn taplo_config(&self, uri: &Url) -> Option<&taplo_cli::config::Config> {
+        if let Some(c) = &self.taplo_config {
+            return Some(c);
+        }
+
+        let path = uri.to_file_path().ok()?;
+        self.dir_configs.get(path.parent()?)?.as_ref()
+    }
+
     fn get_config_formatter_options(
         &self,
         uri: &Url,
@@ -90,15 +121,8 @@ impl WorldState {
     ) {
         let mut incomplete = Vec::new();
 
-        if let Some(c) = &self.taplo_config {
+        if let Some(c) = self.taplo_config(uri) {
             if let Some(p) = uri.to_file_path().ok() {
-                let p = self
-                    .workspace_uri
-                    .as_ref()
-                    .and_then(|ws| ws.to_file_path().ok())
-                    .and_then(|ws| pathdiff::diff_paths(Path::new(&p), ws))
-                    .unwrap_or(p);
-
                 match c.get_formatter_options(p.to_str(), Some(default_opts.clone())) {
                     Ok((opts, inc)) => {
                         default_opts = opts;
@@ -114,8 +138,8 @@ impl WorldState {
         (default_opts, incomplete)
     }
 
-    /// For the given `Url`, if it exists in the map of known documents,
-    /// try to parse the schema path from a toml comment.
+    /// For the given `Url`, if it exists in the map of known documents
+    /// or in the workspace index, try to parse the schema path from a toml comment.
     ///
     /// Example:
     ///
@@ -127,11 +151,18 @@ impl WorldState {
     /// returns `"/.foo/bar"`.
     ///
     /// If the file does not contain such a schema comment, we look into the taplo config,
-    /// which assigns file regexes (relative to the `workspace_uri`) to schema files.
+    /// which assigns file patterns (relative to the configuration file) to schema files.
+    ///
+    /// Otherwise the associations from the settings, the schema index
+    /// and the built-in schemas are used, in this order.
     ///
     /// If nothing is found, returns `None`.
     fn get_schema_name(&self, uri: &Url) -> Option<String> {
-        match self.documents.get(uri) {
+        match self
+            .documents
+            .get(uri)
+            .or_else(|| self.workspace_documents.get(uri))
+        {
             Some(doc) => {
                 for directive in Directive::collect_from_syntax(doc.parse.clone().into_syntax()) {
                     if directive.value.starts_with("schema") {
@@ -147,15 +178,8 @@ impl WorldState {
             None => {}
         }
 
-        if let Some(c) = &self.taplo_config {
+        if let Some(c) = self.taplo_config(uri) {
             if let Some(p) = uri.to_file_path().ok() {
-                let p = self
-                    .workspace_uri
-                    .as_ref()
-                    .and_then(|ws| ws.to_file_path().ok())
-                    .and_then(|ws| pathdiff::diff_paths(Path::new(&p), ws))
-                    .unwrap_or(p);
-
                 if let Some(p) = p.to_str() {
                     match c.get_schema_path(p) {
                         Ok(p) => {
@@ -185,7 +209,30 @@ impl WorldState {
             }
         }
 
-        None
+        associated_schema(s).map(|builtin| builtin.url())
+    }
+
+    /// The schemas attached to keys in the document by the taplo config,
+    /// as `(keys, schema path)` pairs.
+    fn get_key_schema_names(&self, uri: &Url) -> Vec<(String, String)> {
+        let c = match self.taplo_config(uri) {
+            Some(c) => c,
+            None => return Vec::new(),
+        };
+
+        let p = match uri.to_file_path().ok() {
+            Some(p) => p,
+            None => return Vec::new(),
+        };
+
+        match p.to_str().map(|p| c.get_key_schema_paths(p)) {
+            Some(Ok(schemas)) => schemas,
+            Some(Err(err)) => {
+                log_warn!("invalid config: {}", err);
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
     }
 
     fn workspace_path(&self) -> Option<PathBuf> {
@@ -195,8 +242,92 @@ impl WorldState {
         }
     }
 
-    /// Get the schema for a given file and schema path/url.
+    /// Get the schema for a document, including the schemas
+    /// attached to keys in it.
+    ///
+    /// Returns `None` if the document has no schemas.
+    ///
+    /// The world must not be locked.
+    async fn get_document_schema(
+        uri: &Url,
+        mut context: Context<World>,
+    ) -> Option<Result<RootSchema, anyhow::Error>> {
+        let w = context.world().lock().await;
+        let schema_name = w.get_schema_name(uri);
+        let key_schema_names = w.get_key_schema_names(uri);
+        drop(w);
+
+        if schema_name.is_none() && key_schema_names.is_empty() {
+            return None;
+        }
+
+        let mut schema = match schema_name {
+            Some(name) => match WorldState::get_schema(uri, &name, context.clone()).await {
+                Ok(s) => s,
+                Err(err) => return Some(Err(anyhow!("{}: {}", name, err))),
+            },
+            None => RootSchema::default(),
+        };
+
+        for (keys, name) in key_schema_names {
+            match WorldState::get_schema(uri, &name, context.clone()).await {
+                Ok(s) => attach_schema(&mut schema, &keys, s),
+                Err(err) => return Some(Err(anyhow!("{}: {}", name, err))),
+            }
+        }
+
+        Some(Ok(schema))
+    }
+
+    /// Get the schema for a given file and schema path/url,
+    /// along with the schemas it references in other documents.
+    ///
+    /// References to documents that could not be loaded are left unresolved.
     async fn get_schema(
+        for_url: &Url,
+        path: &str,
+        context: Context<World>,
+    ) -> Result<RootSchema, anyhow::Error> {
+        let schema = WorldState::get_schema_document(for_url, path, context.clone()).await?;
+
+        let url = if path.contains("://") {
+            path.to_string()
+        } else if is_absolute_path(path) {
+            path.replace('\\', "/")
+        } else {
+            for_url.join(path)?.to_string()
+        };
+
+        let mut bundle = SchemaBundle::new(&url, schema);
+
+        loop {
+            let missing = bundle.missing_documents();
+
+            if missing.is_empty() {
+                break;
+            }
+
+            for doc_url in missing {
+                match WorldState::get_schema_document(for_url, &doc_url, context.clone()).await {
+                    Ok(schema) => bundle.add_document(&doc_url, schema),
+                    Err(err) => {
+                        log_warn!(
+                            "failed to load schema {} referenced in {}: {}",
+                            doc_url,
+                            url,
+                            err
+                        );
+                        bundle.skip_document(&doc_url);
+                    }
+                }
+            }
+        }
+
+        bundle.into_schema().map_err(Into::into)
+    }
+
+    /// Get a single schema document for a given file and schema path/url.
+    async fn get_schema_document(
         // File to get the schema for.
         for_url: &Url,
         // Path of the schema file, as either
@@ -214,6 +345,8 @@ impl WorldState {
         if path.starts_with(&format!("{}://", BUILTIN_SCHEME)) {
             if path == "taplo://taplo.toml" {
                 Ok(schema_for!(taplo_cli::config::Config))
+            } else if let Some(builtin) = builtin_schema(path) {
+                Ok(builtin.schema())
             } else {
                 Err(anyhow!("invalid builtin schema: {}", path))
             }
@@ -243,7 +376,7 @@ impl WorldState {
             drop(w);
 
             let res = client.get(path).send().await?;
-            let schema: RootSchema = res.json().await.map_err::<anyhow::Error, _>(Into::into)?;
+            let schema = schema_from_slice(&res.bytes().await?)?;
 
             let w = context.world().lock().await;
 
@@ -284,10 +417,10 @@ impl WorldState {
         // resolve file://
         } else if path.starts_with("file://") {
             path = path.trim_start_matches("file://");
-            serde_json::from_slice(&read_file(path).await?).map_err(Into::into)
+            schema_from_slice(&read_file(path).await?).map_err(Into::into)
         // same as file:// for absolute paths
         } else if is_absolute_path(path) {
-            serde_json::from_slice(&read_file(path).await?).map_err(Into::into)
+            schema_from_slice(&read_file(path).await?).map_err(Into::into)
         // resolve relative paths, relative to the `for_url`.
         } else {
             // This should in theory work for any type of url, so if `for_url` is
@@ -305,7 +438,7 @@ impl WorldState {
                 ))
             }
             match for_url.join(path) {
-                Ok(schema) => serde_json::from_slice(
+                Ok(schema) => schema_from_slice(
                     &read_file(
                         schema.to_file_path().expect(&format!("{} has to be a file path here", schema))
                             // this should be utf-8 safe since it came from an URL
@@ -350,22 +483,35 @@ pub type World = Arc<AsyncMutex<WorldState>>;
 
 pub fn create_server() -> Server<World> {
     Server::new()
-        .on_request::<request::Initialize, _>(handlers::initialize)
+        .on_request::<msg_ext::InitializeRequest, _>(handlers::initialize)
         .on_request::<request::FoldingRangeRequest, _>(handlers::folding_ranges)
         .on_request::<request::DocumentSymbolRequest, _>(handlers::document_symbols)
+        .on_request::<request::WorkspaceSymbol, _>(handlers::workspace_symbols)
         .on_request::<request::Formatting, _>(handlers::format)
+        .on_request::<request::RangeFormatting, _>(handlers::format_range)
+        .on_request::<request::OnTypeFormatting, _>(handlers::format_on_type)
         .on_request::<request::Completion, _>(handlers::completion)
         .on_request::<request::HoverRequest, _>(handlers::hover)
+        .on_request::<request::GotoDefinition, _>(handlers::definition)
+        .on_request::<request::References, _>(handlers::references)
+        .on_request::<request::DocumentHighlightRequest, _>(handlers::document_highlight)
+        .on_request::<request::PrepareRenameRequest, _>(handlers::prepare_rename)
+        .on_request::<request::Rename, _>(handlers::rename)
+        .on_request::<request::SelectionRangeRequest, _>(handlers::selection_ranges)
         .on_request::<request::DocumentLinkRequest, _>(handlers::links)
         .on_request::<request::SemanticTokensFullRequest, _>(handlers::semantic_tokens)
         .on_request::<request::CodeActionRequest, _>(handlers::code_action)
         .on_request::<msg_ext::TomlToJsonRequest, _>(handlers::toml_to_json)
         .on_request::<msg_ext::JsonToTomlRequest, _>(handlers::json_to_toml)
         .on_request::<msg_ext::SyntaxTreeRequest, _>(handlers::syntax_tree)
+        .on_request::<msg_ext::DocumentDiagnosticRequest, _>(handlers::document_diagnostic)
+        .on_request::<msg_ext::WorkspaceDiagnosticRequest, _>(handlers::workspace_diagnostic)
+        .on_notification::<notification::Initialized, _>(handlers::initialized)
         .on_notification::<notification::DidOpenTextDocument, _>(handlers::document_open)
         .on_notification::<notification::DidChangeTextDocument, _>(handlers::document_change)
         .on_notification::<notification::DidCloseTextDocument, _>(handlers::document_close)
         .on_notification::<notification::DidChangeConfiguration, _>(handlers::configuration_change)
+        .on_notification::<notification::DidChangeWatchedFiles, _>(handlers::watched_files_change)
         .on_notification::<msg_ext::CachePath, _>(handlers::cache_path)
         .build()
 }
@@ -1,8 +1,23 @@
-//! Messages that are not part of the LSP spec.
-
-use lsp_types::{notification::Notification, request::Request, Url};
+//! Messages that are not part of the LSP spec,
+//! or not yet supported by `lsp_types`.
+
+use lsp_types::{
+    notification::Notification,
+    request::{Initialize, Request},
+    Diagnostic, InitializeResult, TextDocumentIdentifier, Url,
+};
 use serde::{Deserialize, Serialize};
 
+/// The initialize request with the parameters as they are sent,
+/// as `lsp_types` does not support all the client capabilities yet.
+pub(crate) enum InitializeRequest {}
+
+impl Request for InitializeRequest {
+    type Params = serde_json::Value;
+    type Result = InitializeResult;
+    const METHOD: &'static str = Initialize::METHOD;
+}
+
 /// Serialize a TOML text to JSON.
 pub(crate) enum TomlToJsonRequest {}
 
@@ -114,3 +129,70 @@ impl Notification for CachePath {
     type Params = CachePathParams;
     const METHOD: &'static str = "taplo/cachePath";
 }
+
+/// Pull diagnostics for a document (LSP 3.17).
+pub(crate) enum DocumentDiagnosticRequest {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DocumentDiagnosticParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Only full reports are sent, the diagnostics
+/// are not cached between requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum DocumentDiagnosticReport {
+    #[serde(rename_all = "camelCase")]
+    Full {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        result_id: Option<String>,
+        items: Vec<Diagnostic>,
+    },
+}
+
+impl Request for DocumentDiagnosticRequest {
+    type Params = DocumentDiagnosticParams;
+    type Result = DocumentDiagnosticReport;
+    const METHOD: &'static str = "textDocument/diagnostic";
+}
+
+/// Pull diagnostics for the workspace (LSP 3.17).
+pub(crate) enum WorkspaceDiagnosticRequest {}
+
+/// Result IDs are not used, so none of the parameters are needed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct WorkspaceDiagnosticParams {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceDiagnosticReport {
+    pub items: Vec<WorkspaceDocumentDiagnosticReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceDocumentDiagnosticReport {
+    pub uri: Url,
+    /// The version of the document, always `null`
+    /// as only the documents that are not open are reported.
+    pub version: Option<i64>,
+    #[serde(flatten)]
+    pub report: DocumentDiagnosticReport,
+}
+
+impl Request for WorkspaceDiagnosticRequest {
+    type Params = WorkspaceDiagnosticParams;
+    type Result = WorkspaceDiagnosticReport;
+    const METHOD: &'static str = "workspace/diagnostic";
+}
+
+/// Asks the client to pull the diagnostics again (LSP 3.17).
+pub(crate) enum WorkspaceDiagnosticRefresh {}
+
+impl Request for WorkspaceDiagnosticRefresh {
+    type Params = ();
+    type Result = ();
+    const METHOD: &'static str = "workspace/diagnostic/refresh";
+}
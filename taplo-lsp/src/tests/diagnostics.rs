@@ -0,0 +1,111 @@
+use super::{temp_dir, TestServer};
+use lsp_types::Url;
+use serde_json::{json, Value};
+
+const SCHEMA: &str = r#"{
+    "type": "object",
+    "properties": {
+        "name": { "type": "string" },
+        "version": { "type": "string" }
+    }
+}"#;
+
+/// The codes and lines of the diagnostics in a report.
+fn diagnostics(report: &Value) -> Vec<(String, u64)> {
+    report["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            (
+                d["code"].as_str().unwrap_or_default().to_string(),
+                d["range"]["start"]["line"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn pull_closed_document_diagnostics() {
+    let dir = temp_dir(
+        "diagnostics",
+        &[
+            ("schema.json", SCHEMA),
+            (
+                "nested/schema.toml",
+                "name = 1\n#:taplo-ignore-next-line schema\nversion = 2\n",
+            ),
+            (
+                "nested/duplicate.toml",
+                "a = 1\n#:taplo-ignore-next-line duplicate-key\na = 2\n",
+            ),
+        ],
+    );
+
+    std::fs::write(
+        dir.join("nested/.taplo.toml"),
+        format!(
+            "include = [\"*.toml\"]\n\n[schema]\nenabled = true\npath = {:?}\n",
+            dir.join("schema.json").to_str().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let configuration = json!({
+        "taploConfigEnabled": true,
+        "schema": { "enabled": true },
+        "formatter": {},
+    });
+
+    let mut server = TestServer::new(&dir.join("nested"), configuration.clone()).await;
+
+    server
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": configuration }),
+        )
+        .await;
+    server
+        .wait_until(|w| w.workspace_documents.len() == 3)
+        .await;
+
+    let schema_uri = Url::from_file_path(dir.join("nested/schema.toml")).unwrap();
+    let duplicate_uri = Url::from_file_path(dir.join("nested/duplicate.toml")).unwrap();
+
+    let report = server
+        .request(
+            "textDocument/diagnostic",
+            json!({ "textDocument": { "uri": schema_uri } }),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(diagnostics(&report), vec![("TOML200".into(), 0)]);
+
+    let report = server
+        .request("workspace/diagnostic", json!({ "previousResultIds": [] }))
+        .await
+        .unwrap();
+
+    let reports = report["items"].as_array().unwrap();
+    assert_eq!(reports.len(), 3);
+
+    let report_of = |uri: &Url| {
+        reports
+            .iter()
+            .find(|r| r["uri"] == json!(uri))
+            .unwrap()
+            .clone()
+    };
+
+    assert_eq!(
+        diagnostics(&report_of(&schema_uri)),
+        vec![("TOML200".into(), 0)]
+    );
+    assert_eq!(
+        diagnostics(&report_of(&duplicate_uri)),
+        vec![("TOML001".into(), 0)]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
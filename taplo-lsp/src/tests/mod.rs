@@ -0,0 +1,141 @@
+mod diagnostics;
+mod references;
+mod rename;
+mod selection_ranges;
+mod workspace;
+
+use crate::{create_server, create_world, Document, World};
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use lsp_async_stub::{rpc, Server};
+use lsp_types::{NumberOrString, Url};
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use taplo::util::coords::Mapper;
+
+fn document(src: &str) -> Document {
+    Document {
+        parse: taplo::parser::parse(src),
+        mapper: Mapper::new_utf16(src, false),
+    }
+}
+
+/// A server that records the messages it sends to the client.
+struct TestServer {
+    server: Server<World>,
+    world: World,
+    sender: mpsc::UnboundedSender<rpc::Message>,
+    messages: mpsc::UnboundedReceiver<rpc::Message>,
+    last_id: i32,
+}
+
+impl TestServer {
+    /// An initialized server with the given workspace and configuration.
+    async fn new(root: &Path, configuration: serde_json::Value) -> Self {
+        let (sender, messages) = mpsc::unbounded();
+
+        let mut server = Self {
+            server: create_server(),
+            world: create_world(),
+            sender,
+            messages,
+            last_id: 0,
+        };
+
+        server
+            .request(
+                "initialize",
+                serde_json::json!({
+                    "processId": null,
+                    "rootUri": Url::from_file_path(root).unwrap(),
+                    "capabilities": {},
+                    "initializationOptions": { "configuration": configuration },
+                }),
+            )
+            .await
+            .unwrap();
+
+        server
+    }
+
+    async fn handle(&self, message: rpc::Message) {
+        let writer = self
+            .sender
+            .clone()
+            .sink_map_err(|_| io::Error::new(io::ErrorKind::Other, "the client is gone"));
+
+        self.server
+            .handle_message(self.world.clone(), message, writer)
+            .await
+            .unwrap();
+    }
+
+    async fn request(
+        &mut self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<serde_json::Value, rpc::Error> {
+        self.last_id += 1;
+        let id = NumberOrString::Number(self.last_id);
+
+        self.handle(
+            rpc::Request::new()
+                .with_method(method)
+                .with_id(Some(id.clone()))
+                .with_params(Some(serde_json::to_value(params).unwrap()))
+                .into_message(),
+        )
+        .await;
+
+        while let Some(message) = self.messages.next().await {
+            if message.is_response() && message.id.as_ref() == Some(&id) {
+                return message.into_response().into_result();
+            }
+        }
+
+        unreachable!("the server did not respond")
+    }
+
+    async fn notify(&mut self, method: &str, params: impl Serialize) {
+        self.handle(
+            rpc::Request::new()
+                .with_method(method)
+                .with_params(Some(serde_json::to_value(params).unwrap()))
+                .into_message(),
+        )
+        .await;
+    }
+
+    /// Waits until the spawned tasks of the server have done their work.
+    async fn wait_until(&self, mut done: impl FnMut(&crate::WorldState) -> bool) {
+        for _ in 0..500 {
+            if done(&*self.world.lock().await) {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        panic!("timed out");
+    }
+}
+
+/// An empty temporary directory for the test with the given files.
+fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("taplo-lsp-{}-{}", name, std::process::id()));
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    for (path, src) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+
+    dir
+}
@@ -0,0 +1,205 @@
+use super::{document, temp_dir, TestServer};
+use crate::handlers::references::key_references;
+use lsp_types::Url;
+use serde_json::{json, Value};
+use taplo::util::coords::Position;
+
+/// The lines and texts of the keys referenced by the key at the first
+/// occurrence of `at`, the cursor is at the `|` in it.
+fn references(src: &str, at: &str) -> Vec<(u64, String)> {
+    let doc = document(src);
+    let offset = src.find(&at.replace('|', "")).unwrap() + at.find('|').unwrap();
+    let pos = doc.mapper.position((offset as u32).into()).unwrap();
+
+    key_references(
+        &doc,
+        lsp_types::Position::new(pos.line as u32, pos.character as u32),
+    )
+    .unwrap_or_default()
+    .into_iter()
+    .map(|range| {
+        let start = doc
+            .mapper
+            .offset(Position::new(
+                range.start.line as u64,
+                range.start.character as u64,
+            ))
+            .unwrap();
+        let end = doc
+            .mapper
+            .offset(Position::new(
+                range.end.line as u64,
+                range.end.character as u64,
+            ))
+            .unwrap();
+
+        (
+            range.start.line as u64,
+            src[usize::from(start)..usize::from(end)].to_string(),
+        )
+    })
+    .collect()
+}
+
+fn refs(items: &[(u64, &str)]) -> Vec<(u64, String)> {
+    items.iter().map(|(l, s)| (*l, s.to_string())).collect()
+}
+
+#[test]
+fn references_split_tables() {
+    let src = "[a]\nx = 1\n\n[a.b]\nc = 1\n\n[d]\na.b.c = 1\n";
+
+    assert_eq!(references(src, "[|a]"), refs(&[(0, "a"), (3, "a")]));
+    assert_eq!(references(src, "[|a.b]"), refs(&[(0, "a"), (3, "a")]));
+    assert_eq!(references(src, "[a.|b]"), refs(&[(3, "b")]));
+    assert_eq!(references(src, "[a.b|]"), refs(&[(3, "b")]));
+    assert_eq!(references(src, "|c = 1"), refs(&[(4, "c")]));
+
+    // Keys under another table have a different path.
+    assert_eq!(references(src, "|a.b.c"), refs(&[(7, "a")]));
+    assert_eq!(references(src, "a.|b.c"), refs(&[(7, "b")]));
+    assert_eq!(references(src, "a.b.|c"), refs(&[(7, "c")]));
+}
+
+#[test]
+fn references_dotted_keys() {
+    let src = "a.b.c = 1\na.b.d = 2\na.e = 3\n\n[f]\ng.h = 1\ng.i = 2\n";
+
+    assert_eq!(
+        references(src, "|a.b.c"),
+        refs(&[(0, "a"), (1, "a"), (2, "a")])
+    );
+    assert_eq!(references(src, "a.|b.d"), refs(&[(0, "b"), (1, "b")]));
+    assert_eq!(references(src, "a.b.|c"), refs(&[(0, "c")]));
+    assert_eq!(references(src, "a.e| "), refs(&[(2, "e")]));
+    assert_eq!(references(src, "|g.h"), refs(&[(5, "g"), (6, "g")]));
+    assert_eq!(references(src, "[|f]"), refs(&[(4, "f")]));
+}
+
+#[test]
+fn references_inline_tables() {
+    let src = "t = { a = 1, b.c = 2, b.d = 3 }\nu = { t = 1 }\n";
+
+    assert_eq!(references(src, "|t = {"), refs(&[(0, "t")]));
+    assert_eq!(references(src, "|a = 1"), refs(&[(0, "a")]));
+    assert_eq!(references(src, "|b.c"), refs(&[(0, "b"), (0, "b")]));
+    assert_eq!(references(src, "b.|d"), refs(&[(0, "d")]));
+    assert_eq!(references(src, "{ |t = 1"), refs(&[(1, "t")]));
+}
+
+#[test]
+fn references_arrays_of_tables() {
+    let src = "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n[[bin.sub]]\nx = 1\n";
+
+    let bins = refs(&[(0, "bin"), (3, "bin"), (6, "bin")]);
+
+    assert_eq!(references(src, "[[|bin]]\nname = \"a\""), bins);
+    assert_eq!(references(src, "[[|bin]]\nname = \"b\""), bins);
+    assert_eq!(references(src, "[[|bin.sub]]"), bins);
+    assert_eq!(references(src, "[[bin.|sub]]"), refs(&[(6, "sub")]));
+
+    // Every table of the array has its own keys.
+    assert_eq!(references(src, "|name = \"a\""), refs(&[(1, "name")]));
+    assert_eq!(references(src, "|name = \"b\""), refs(&[(4, "name")]));
+}
+
+#[test]
+fn references_outside_keys() {
+    let src = "a = 1\n\n[b]\n";
+
+    assert_eq!(references(src, "a = |1"), refs(&[]));
+    assert_eq!(references(src, "|\n[b]"), refs(&[]));
+}
+
+/// The start lines and characters of the ranges in a response.
+fn starts<'v>(ranges: impl IntoIterator<Item = &'v Value>) -> Vec<(u64, u64)> {
+    ranges
+        .into_iter()
+        .map(|range| {
+            (
+                range["start"]["line"].as_u64().unwrap(),
+                range["start"]["character"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn definition_references_and_highlights() {
+    let dir = temp_dir("references", &[]);
+    let uri = Url::from_file_path(dir.join("a.toml")).unwrap();
+
+    let mut server = TestServer::new(
+        &dir,
+        json!({
+            "taploConfigEnabled": false,
+            "schema": { "enabled": false },
+            "formatter": {},
+        }),
+    )
+    .await;
+
+    server
+        .notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "toml",
+                    "version": 0,
+                    "text": "[a]\nx = 1\n\n[a.b]\nc = 1\n\n[d]\na.b.c = 1\n",
+                }
+            }),
+        )
+        .await;
+    server.wait_until(|w| w.documents.contains_key(&uri)).await;
+
+    // The cursor is on `a` in `[a.b]`.
+    let params = json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": 3, "character": 1 },
+        "context": { "includeDeclaration": true },
+    });
+
+    let definition = server
+        .request("textDocument/definition", &params)
+        .await
+        .unwrap();
+    let references = server
+        .request("textDocument/references", &params)
+        .await
+        .unwrap();
+    let highlights = server
+        .request("textDocument/documentHighlight", &params)
+        .await
+        .unwrap();
+
+    let expected = vec![(0, 1), (3, 1)];
+
+    for locations in [&definition, &references].iter() {
+        let locations = locations.as_array().unwrap();
+
+        assert!(locations.iter().all(|l| l["uri"] == json!(uri)));
+        assert_eq!(starts(locations.iter().map(|l| &l["range"])), expected);
+    }
+
+    assert_eq!(
+        starts(highlights.as_array().unwrap().iter().map(|h| &h["range"])),
+        expected
+    );
+
+    // There is nothing to find outside of keys.
+    let params = json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": 1, "character": 4 },
+        "context": { "includeDeclaration": true },
+    });
+
+    assert_eq!(
+        server
+            .request("textDocument/definition", &params)
+            .await
+            .unwrap(),
+        Value::Null
+    );
+}
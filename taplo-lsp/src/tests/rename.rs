@@ -0,0 +1,101 @@
+use super::document;
+use crate::{
+    handlers::rename::{is_single_key, is_taken, renamed_keys},
+    Document,
+};
+use lsp_types::Position;
+use taplo::dom;
+
+/// The path of the key at the first occurrence of the given text,
+/// and the text of every key that would be renamed with it.
+fn renamed_at(doc: &Document, src: &str, at: &str) -> (dom::Path, Vec<String>) {
+    let pos = doc
+        .mapper
+        .position((src.find(at).unwrap() as u32).into())
+        .unwrap();
+
+    let (path, _, ranges) = renamed_keys(doc, Position::new(pos.line as u32, pos.character as u32))
+        .unwrap()
+        .unwrap();
+
+    (
+        path,
+        ranges
+            .into_iter()
+            .map(|range| src[range].to_string())
+            .collect(),
+    )
+}
+
+fn renamed(src: &str, at: &str) -> Vec<String> {
+    renamed_at(&document(src), src, at).1
+}
+
+/// Whether renaming the key at the first occurrence
+/// of the given text to the new key is rejected.
+fn taken(src: &str, at: &str, new_key: &str) -> bool {
+    let doc = document(src);
+    let (path, _) = renamed_at(&doc, src, at);
+
+    is_taken(&doc.parse.into_dom(), &path, new_key)
+}
+
+#[test]
+fn rename_dotted_keys() {
+    let src = "a.b = 1\na.c = 2\n\n[a.d]\ne = 3\n";
+
+    assert_eq!(renamed(src, "a.b"), vec!["a", "a", "a"]);
+    assert_eq!(renamed(src, "b ="), vec!["b"]);
+    assert_eq!(renamed(src, "d]"), vec!["d"]);
+}
+
+#[test]
+fn rename_quoted_keys() {
+    let src = "[\"a b\"]\nc = 1\n\n[\"a b\".d]\ne = 2\n";
+
+    assert_eq!(renamed(src, "a b\"]"), vec!["\"a b\"", "\"a b\""]);
+    assert_eq!(renamed(src, "d]"), vec!["d"]);
+}
+
+#[test]
+fn rename_inline_table_keys() {
+    let src = "t = { a = 1, b.c = 2 }\n\n[u]\nt.d = 3\n";
+
+    assert_eq!(renamed(src, "a ="), vec!["a"]);
+    assert_eq!(renamed(src, "c ="), vec!["c"]);
+    assert_eq!(renamed(src, "t ="), vec!["t"]);
+}
+
+#[test]
+fn rename_document_with_errors() {
+    let doc = document("a = \n");
+
+    assert!(renamed_keys(&doc, Position::new(0, 0)).is_err());
+}
+
+#[test]
+fn rename_conflicts() {
+    let src = "a = 1\nb = 2\n\n[t]\nc = 3\nd.e = 4\n\n[[arr]]\nf = 5\n\n[[arr]]\ng = 6\n";
+
+    assert!(taken(src, "a =", "b"));
+    assert!(!taken(src, "a =", "a"));
+    assert!(!taken(src, "a =", "c"));
+    assert!(taken(src, "c =", "d"));
+    assert!(taken(src, "c =", "\"d\""));
+    assert!(!taken(src, "e =", "c"));
+    assert!(!taken(src, "f =", "g"));
+}
+
+#[test]
+fn single_keys() {
+    assert!(is_single_key("key"));
+    assert!(is_single_key("bare-key_1"));
+    assert!(is_single_key("\"quoted key\""));
+    assert!(is_single_key("'literal key'"));
+
+    assert!(!is_single_key("a.b"));
+    assert!(!is_single_key("a b"));
+    assert!(!is_single_key("\"unclosed"));
+    assert!(!is_single_key(""));
+    assert!(!is_single_key("a = 1"));
+}
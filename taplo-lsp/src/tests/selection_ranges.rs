@@ -0,0 +1,114 @@
+use crate::handlers::selection_ranges::{section_range, syntax_ranges};
+use taplo::syntax::SyntaxKind::*;
+
+/// The text of every selection range at the first occurrence of `at`,
+/// the cursor is at the `|` in it.
+fn ranges(src: &str, at: &str) -> Vec<String> {
+    let offset = src.find(&at.replace('|', "")).unwrap() + at.find('|').unwrap();
+    let syntax = taplo::parser::parse(src).into_syntax();
+
+    let ranges = syntax_ranges(&syntax, (offset as u32).into());
+
+    for (inner, outer) in ranges.iter().zip(ranges.iter().skip(1)) {
+        assert!(outer.contains_range(*inner), "{:?} {:?}", inner, outer);
+    }
+
+    ranges
+        .into_iter()
+        .inspect(|range| assert!(range.contains_inclusive((offset as u32).into())))
+        .map(|range| src[range].to_string())
+        .collect()
+}
+
+const SRC: &str = r#"[table]
+x = 1
+inline = { a = 1, b = 2 }
+
+[other]
+y = 2
+"#;
+
+#[test]
+fn selection_ranges_nested() {
+    assert_eq!(
+        ranges(SRC, "b = |2"),
+        vec![
+            "2",
+            "b = 2",
+            "{ a = 1, b = 2 }",
+            "inline = { a = 1, b = 2 }",
+            "[table]\nx = 1\ninline = { a = 1, b = 2 }",
+            SRC.trim_end(),
+        ]
+    );
+
+    assert_eq!(
+        ranges(SRC, "y| = 2"),
+        vec!["y", "y = 2", "[other]\ny = 2", SRC.trim_end()]
+    );
+}
+
+#[test]
+fn selection_ranges_array_of_tables() {
+    let src = "[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\npath = \"b.rs\"\n";
+
+    assert_eq!(
+        ranges(src, "name = |\"b\""),
+        vec![
+            "\"b\"",
+            "name = \"b\"",
+            "[[bin]]\nname = \"b\"\npath = \"b.rs\"",
+            src.trim_end(),
+        ]
+    );
+
+    assert_eq!(
+        ranges(src, "[[b|in]]\nname = \"b\""),
+        vec![
+            "bin",
+            "[[bin]]",
+            "[[bin]]\nname = \"b\"\npath = \"b.rs\"",
+            src.trim_end(),
+        ]
+    );
+}
+
+#[test]
+fn selection_ranges_whitespace() {
+    let src = "a = 1\nkey   =   [ 1,   2 ]\n\n\n";
+
+    assert_eq!(
+        ranges(src, "key | "),
+        vec!["key   ", "key   =   [ 1,   2 ]", src.trim_end()]
+    );
+    assert_eq!(
+        ranges(src, "1, | "),
+        vec!["[ 1,   2 ]", "key   =   [ 1,   2 ]", src.trim_end()]
+    );
+    assert_eq!(ranges(src, "]\n\n|\n"), vec![src]);
+}
+
+#[test]
+fn section_ranges() {
+    let syntax = taplo::parser::parse(SRC).into_syntax();
+    let top_level: Vec<_> = syntax.children().collect();
+
+    assert_eq!(top_level[0].kind(), TABLE_HEADER);
+    assert_eq!(
+        section_range(&top_level[1]).map(|range| &SRC[range]),
+        Some("[table]\nx = 1\ninline = { a = 1, b = 2 }")
+    );
+    assert_eq!(
+        section_range(&top_level[3]).map(|range| &SRC[range]),
+        Some("[other]\ny = 2")
+    );
+
+    let syntax = taplo::parser::parse("a = 1\n[table]\n").into_syntax();
+    let top_level: Vec<_> = syntax.children().collect();
+
+    assert_eq!(section_range(&top_level[0]), None);
+    assert_eq!(
+        section_range(&top_level[1]).map(|range| &"a = 1\n[table]\n"[range]),
+        Some("[table]")
+    );
+}
@@ -0,0 +1,143 @@
+use super::{temp_dir, TestServer};
+use crate::{
+    handlers::workspace::{
+        file_url, is_included, matches_query, symbols_for_document, MAX_WORKSPACE_SYMBOLS,
+    },
+    Document,
+};
+use lsp_types::Url;
+use serde_json::json;
+use std::path::Path;
+use taplo::util::coords::Mapper;
+use taplo_cli::config::Config;
+
+#[test]
+fn query_matching() {
+    assert!(matches_query("package.name", ""));
+    assert!(matches_query("package.name", "package.name"));
+    assert!(matches_query("package.name", "pkgname"));
+    assert!(matches_query("package.name", "PKG.N"));
+    assert!(matches_query("Émoji", "ém"));
+
+    assert!(!matches_query("package.name", "namep"));
+    assert!(!matches_query("package.name", "package.names"));
+    assert!(!matches_query("", "a"));
+}
+
+#[test]
+fn workspace_file_urls() {
+    let ws_uri = Url::parse("file:///project").unwrap();
+    let ws_path = Path::new("/project");
+
+    assert_eq!(
+        file_url(&ws_uri, ws_path, Path::new("/project/Cargo.toml")).unwrap(),
+        Url::parse("file:///project/Cargo.toml").unwrap()
+    );
+    assert_eq!(
+        file_url(&ws_uri, ws_path, Path::new("/project/a b/c#d.toml")).unwrap(),
+        Url::parse("file:///project/a%20b/c%23d.toml").unwrap()
+    );
+    assert_eq!(
+        file_url(
+            &Url::parse("file:///project/").unwrap(),
+            ws_path,
+            Path::new("/project/src/a.toml")
+        )
+        .unwrap(),
+        Url::parse("file:///project/src/a.toml").unwrap()
+    );
+
+    assert_eq!(file_url(&ws_uri, ws_path, Path::new("/other/a.toml")), None);
+    assert_eq!(
+        file_url(&ws_uri, ws_path, Path::new("/projects/a.toml")),
+        None
+    );
+}
+
+#[test]
+fn included_files() {
+    let config = Config {
+        include: Some(vec!["/project/**/*.toml".into()]),
+        exclude: Some(vec!["/project/target/**".into()]),
+        ..Default::default()
+    };
+
+    assert!(is_included(&config, Path::new("/project/Cargo.toml")));
+    assert!(is_included(&config, Path::new("/project/src/a.toml")));
+    assert!(!is_included(&config, Path::new("/project/target/a.toml")));
+    assert!(!is_included(&config, Path::new("/project/src/a.json")));
+    assert!(!is_included(&config, Path::new("/other/a.toml")));
+
+    let config = Config {
+        include: Some(vec!["[".into()]),
+        ..Default::default()
+    };
+
+    assert!(!is_included(&config, Path::new("/project/Cargo.toml")));
+}
+
+#[test]
+fn workspace_symbols_limit() {
+    let src: String = (0..MAX_WORKSPACE_SYMBOLS + 10)
+        .map(|i| format!("key{} = {}\n", i, i))
+        .collect();
+
+    let doc = Document {
+        parse: taplo::parser::parse(&src),
+        mapper: Mapper::new_utf16(&src, false),
+    };
+    let uri = Url::parse("file:///project/a.toml").unwrap();
+
+    let mut symbols = Vec::new();
+    symbols_for_document(&uri, &doc, "key1", &mut symbols);
+    assert!(symbols.iter().all(|s| matches_query(&s.name, "key1")));
+    assert!(symbols.len() < MAX_WORKSPACE_SYMBOLS);
+
+    symbols_for_document(&uri, &doc, "", &mut symbols);
+    assert_eq!(symbols.len(), MAX_WORKSPACE_SYMBOLS);
+}
+
+#[tokio::test]
+async fn index_workspace_on_initialized() {
+    let dir = temp_dir(
+        "workspace",
+        &[
+            (
+                ".taplo.toml",
+                "include = [\"**/*.toml\"]\nexclude = [\"target/**\"]\n",
+            ),
+            ("Cargo.toml", "a = 1\n"),
+            ("src/b.toml", "b = 1\n"),
+            ("target/c.toml", "c = 1\n"),
+            ("d.json", "{}"),
+        ],
+    );
+
+    let configuration = json!({
+        "taploConfigEnabled": true,
+        "schema": {},
+        "formatter": {},
+    });
+
+    let mut server = TestServer::new(&dir, configuration).await;
+    server.notify("initialized", json!({})).await;
+    server.wait_until(|w| w.workspace_config.is_some()).await;
+
+    let mut indexed: Vec<String> = server
+        .world
+        .lock()
+        .await
+        .workspace_documents
+        .keys()
+        .map(|uri| {
+            let path = uri.to_file_path().unwrap();
+            let relative = path.strip_prefix(&dir).unwrap();
+            relative.to_str().unwrap().replace('\\', "/")
+        })
+        .collect();
+    indexed.sort();
+
+    assert_eq!(indexed, vec![".taplo.toml", "Cargo.toml", "src/b.toml"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
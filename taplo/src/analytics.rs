@@ -3,6 +3,7 @@
 use std::iter::FromIterator;
 
 use crate::{
+    code::Code,
     dom::{self, NodeSyntax, TextRanges},
     syntax::SyntaxToken,
     syntax::{
@@ -592,3 +593,107 @@ impl Directive {
             .collect()
     }
 }
+
+/// Problems suppressed by `#:taplo-ignore` directives in a document.
+///
+/// - `#:taplo-ignore` suppresses problems in the entire document.
+/// - `#:taplo-ignore-next-line` suppresses problems that start on the line
+///   after the comment.
+///
+/// Both directives accept an optional list of codes
+/// (either identifiers or names), all problems are suppressed if no codes are given:
+///
+/// ```toml
+/// #:taplo-ignore-next-line TOML200 duplicate-key
+/// version = 2
+/// ```
+///
+/// Unknown codes are ignored, so a directive
+/// with only unknown codes suppresses nothing.
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+    suppressions: Vec<Suppression>,
+}
+
+#[derive(Debug, Clone)]
+struct Suppression {
+    /// The range of the affected line, or `None` for the entire document.
+    range: Option<TextRange>,
+    /// The suppressed codes, `None` for all codes.
+    codes: Option<Vec<Code>>,
+}
+
+impl Suppressions {
+    pub fn collect_from_syntax(toml: SyntaxNode) -> Self {
+        let text = toml.to_string();
+
+        let suppressions = Directive::collect_from_syntax(toml)
+            .into_iter()
+            .filter_map(|directive| {
+                let mut words = directive.value.split_whitespace();
+
+                let range = match words.next() {
+                    Some("taplo-ignore") => None,
+                    Some("taplo-ignore-next-line") => {
+                        Some(next_line_range(&text, directive.syntax.text_range().end()))
+                    }
+                    _ => return None,
+                };
+
+                let words: Vec<&str> = words.collect();
+
+                let codes = if words.is_empty() {
+                    None
+                } else {
+                    Some(words.iter().filter_map(|w| w.parse().ok()).collect())
+                };
+
+                Some(Suppression { range, codes })
+            })
+            .collect();
+
+        Self { suppressions }
+    }
+
+    /// Returns whether a problem with the given code is suppressed.
+    ///
+    /// The offset is where the problem starts in the document,
+    /// without one only suppressions for the entire document apply.
+    pub fn is_suppressed(&self, code: Code, offset: Option<TextSize>) -> bool {
+        self.suppressions.iter().any(|s| {
+            let in_range = match (s.range, offset) {
+                (None, _) => true,
+                (Some(range), Some(offset)) => range.contains_inclusive(offset),
+                (Some(_), None) => false,
+            };
+
+            in_range
+                && s.codes
+                    .as_ref()
+                    .map(|codes| codes.contains(&code))
+                    .unwrap_or(true)
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.suppressions.is_empty()
+    }
+}
+
+/// The range of the line after the one containing the offset,
+/// excluding the line ending.
+fn next_line_range(text: &str, offset: TextSize) -> TextRange {
+    let offset = usize::from(offset);
+
+    let start = text[offset..]
+        .find('\n')
+        .map(|i| offset + i + 1)
+        .unwrap_or_else(|| text.len());
+
+    let end = text[start..]
+        .find('\n')
+        .map(|i| start + i)
+        .unwrap_or_else(|| text.len());
+
+    TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+}
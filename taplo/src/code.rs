@@ -0,0 +1,134 @@
+//! Stable codes for every problem that can be reported about a document.
+//!
+//! Each code has an identifier (e.g. `TOML001`) and a name (e.g. `duplicate-key`),
+//! both of them are stable and can be used to document, filter or suppress problems.
+//!
+//! - `TOML0xx` codes are for semantic errors found while building the [DOM](crate::dom).
+//! - `TOML1xx` codes are for [syntax errors](crate::parser::Error).
+//! - `TOML2xx` codes are for schema validation errors.
+
+use core::{fmt, str::FromStr};
+
+macro_rules! codes {
+    (
+        $(
+            $(#[$attr:meta])*
+            $variant:ident = ($id:literal, $name:literal),
+        )+
+    ) => {
+        /// A stable code of a problem.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum Code {
+            $(
+                $(#[$attr])*
+                $variant,
+            )+
+        }
+
+        impl Code {
+            /// All the codes.
+            pub const ALL: &'static [Code] = &[$(Code::$variant),+];
+
+            /// The identifier of the code, e.g. `TOML001`.
+            pub fn id(self) -> &'static str {
+                match self {
+                    $(Code::$variant => $id,)+
+                }
+            }
+
+            /// The human-readable name of the code, e.g. `duplicate-key`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Code::$variant => $name,)+
+                }
+            }
+        }
+    };
+}
+
+codes! {
+    /// A key was defined more than once.
+    DuplicateKey = ("TOML001", "duplicate-key"),
+    /// Entries with overlapping dotted keys have different amount of keys.
+    DottedKeyConflict = ("TOML002", "dotted-key-conflict"),
+    /// A value conflicts with an array of tables.
+    ExpectedArrayOfTables = ("TOML003", "expected-array-of-tables"),
+    /// A value is used as a table.
+    ExpectedTable = ("TOML004", "expected-table"),
+    /// An inline table is extended outside of its definition.
+    InlineTableModified = ("TOML005", "inline-table-modified"),
+    /// A subtable is declared before the array of tables it belongs to.
+    SubtableBeforeArrayOfTables = ("TOML006", "subtable-before-array-of-tables"),
+    /// Any other semantic error.
+    InvalidDocument = ("TOML007", "invalid-document"),
+
+    /// Unexpected or missing tokens.
+    InvalidSyntax = ("TOML100", "invalid-syntax"),
+    /// A new line is missing after an entry or a table header.
+    ExpectedNewline = ("TOML101", "expected-newline"),
+    /// A character that is not allowed in comments or strings.
+    InvalidCharacter = ("TOML102", "invalid-character"),
+    /// An invalid escape sequence in a string.
+    InvalidEscape = ("TOML103", "invalid-escape"),
+    /// A malformed integer or float.
+    InvalidNumber = ("TOML104", "invalid-number"),
+    /// A new line inside an inline table.
+    NewlineInInlineTable = ("TOML105", "newline-in-inline-table"),
+    /// A trailing comma in an inline table.
+    TrailingComma = ("TOML106", "trailing-comma"),
+
+    /// A value does not match its schema.
+    Schema = ("TOML200", "schema"),
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// An error returned when parsing an unknown code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownCode(pub String);
+
+impl fmt::Display for UnknownCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, r#"unknown code "{}""#, self.0)
+    }
+}
+
+impl std::error::Error for UnknownCode {}
+
+impl FromStr for Code {
+    type Err = UnknownCode;
+
+    /// Parses either the identifier (case-insensitive) or the name of a code.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Code::ALL
+            .iter()
+            .copied()
+            .find(|c| c.id().eq_ignore_ascii_case(s) || c.name() == s)
+            .ok_or_else(|| UnknownCode(s.into()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde_crate::Serialize for Code {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde_crate::Serializer,
+    {
+        serializer.serialize_str(self.id())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde_crate::Deserialize<'de> for Code {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde_crate::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde_crate::de::Error::custom)
+    }
+}
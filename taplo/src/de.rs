@@ -0,0 +1,448 @@
+//! Deserialization of Rust values from TOML documents.
+//!
+//! Values are deserialized directly from the [DOM](crate::dom), so every
+//! error can be traced back to the key or value that caused it.
+//!
+//! # Example
+//!
+//! ```
+//! # use taplo::de;
+//! # use std::collections::BTreeMap;
+//! const SOURCE: &str = r#"
+//! [package]
+//! name = "taplo"
+//! version = 6
+//! "#;
+//!
+//! let err = de::from_str::<BTreeMap<String, BTreeMap<String, String>>>(SOURCE).unwrap_err();
+//!
+//! // The error points to the integer value.
+//! let range = err.range.unwrap();
+//! assert_eq!(&SOURCE[range], "6");
+//! ```
+
+use crate::{
+    dom::{self, Entries, EntryNode, KeyNode, NodeSyntax, RootNode, ValueNode},
+    util::{unescape, StrExt},
+    value::Value,
+};
+use rowan::TextRange;
+use serde_crate::{
+    de::{self, value::StringDeserializer, DeserializeOwned, DeserializeSeed, IntoDeserializer},
+    forward_to_deserialize_any,
+};
+use std::convert::TryFrom;
+
+/// A deserialization error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Error {
+    /// The span of the key or value that caused the error.
+    ///
+    /// Errors that are not related to a specific part of the document
+    /// (e.g. a missing field in the root table) have no span.
+    pub range: Option<TextRange>,
+
+    /// Human-friendly error message.
+    pub message: String,
+}
+
+impl Error {
+    fn new(range: Option<TextRange>, message: impl Into<String>) -> Self {
+        Self {
+            range,
+            message: message.into(),
+        }
+    }
+
+    /// Set the range only if it is not known yet,
+    /// so that the innermost node is always reported.
+    fn or_range(mut self, range: Option<TextRange>) -> Self {
+        if self.range.is_none() {
+            self.range = range;
+        }
+        self
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.range {
+            Some(range) => write!(f, "{} ({:?})", &self.message, range),
+            None => self.message.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        Error::new(None, msg.to_string())
+    }
+}
+
+impl From<&crate::parser::Error> for Error {
+    fn from(err: &crate::parser::Error) -> Self {
+        Error::new(Some(err.range), err.message.clone())
+    }
+}
+
+impl From<&dom::Error> for Error {
+    fn from(err: &dom::Error) -> Self {
+        let range = match err {
+            dom::Error::DuplicateKey { second, .. } => Some(second.text_range()),
+            dom::Error::DottedKeyConflict { second, .. } => Some(second.text_range()),
+            dom::Error::ExpectedTableArray { key, .. } => Some(key.text_range()),
+            dom::Error::ExpectedTable { key, .. } => Some(key.text_range()),
+            dom::Error::InlineTable { key, .. } => Some(key.text_range()),
+            dom::Error::SubTableBeforeTableArray { key, .. } => Some(key.text_range()),
+            dom::Error::Spanned { range, .. } => Some(*range),
+            dom::Error::Generic(_) => None,
+        };
+
+        Error::new(range, err.to_string())
+    }
+}
+
+/// Parse a TOML document and deserialize it.
+///
+/// The first syntax or semantic error of the document
+/// is returned if there are any.
+pub fn from_str<T: DeserializeOwned>(source: &str) -> Result<T, Error> {
+    let parse = crate::parser::parse(source);
+
+    if let Some(err) = parse.errors.first() {
+        return Err(err.into());
+    }
+
+    let dom = parse.into_dom();
+
+    if let Some(err) = dom.errors().first() {
+        return Err(err.into());
+    }
+
+    from_dom(&dom)
+}
+
+/// Deserialize a value from a DOM tree.
+///
+/// Errors in the DOM are not checked, parts
+/// of the document that are missing due to errors
+/// are simply not visible during deserialization.
+pub fn from_dom<T: DeserializeOwned>(root: &RootNode) -> Result<T, Error> {
+    T::deserialize(TableDeserializer {
+        entries: root.entries(),
+        range: None,
+    })
+}
+
+fn value_range(node: &ValueNode) -> Option<TextRange> {
+    match node {
+        ValueNode::Table(t) if !t.is_inline() => t
+            .key()
+            .map(KeyNode::text_range)
+            .or_else(|| Some(t.syntax().text_range())),
+        ValueNode::Array(arr) if arr.is_array_of_tables() => arr
+            .key()
+            .map(|k| k.text_range())
+            .or_else(|| Some(arr.syntax().text_range())),
+        ValueNode::Empty => None,
+        _ => Some(node.syntax().text_range()),
+    }
+}
+
+struct TableDeserializer<'a> {
+    entries: &'a Entries,
+    range: Option<TextRange>,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for TableDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor
+            .visit_map(TableAccess::new(self.entries))
+            .map_err(|err| err.or_range(self.range))
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let range = self.range;
+
+        let mut iter = self.entries.iter();
+
+        match (iter.next(), iter.next()) {
+            (Some((key, entry)), None) => visitor
+                .visit_enum(TableEnum { key, entry })
+                .map_err(|err| err.or_range(range)),
+            _ => Err(Error::new(
+                range,
+                "expected a table with a single key for an enum",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ValueDeserializer<'a> {
+    node: &'a ValueNode,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let range = value_range(self.node);
+
+        let res = match self.node {
+            ValueNode::Bool(_)
+            | ValueNode::String(_)
+            | ValueNode::Integer(_)
+            | ValueNode::Float(_) => match Value::try_from(self.node.clone()) {
+                Ok(Value::Bool(v)) => visitor.visit_bool(v),
+                Ok(Value::Integer(v)) => visitor.visit_i64(v),
+                Ok(Value::UnsizedInteger(v)) => visitor.visit_u64(v),
+                Ok(Value::Float(v)) => visitor.visit_f64(v),
+                Ok(Value::String(v)) => visitor.visit_string(v),
+                Ok(_) => unreachable!(),
+                Err(err) => Err(Error::new(range, err.to_string())),
+            },
+            // Dates are passed as they appear in the document.
+            ValueNode::Date(d) => visitor.visit_string(d.syntax().to_string()),
+            ValueNode::Array(arr) => visitor.visit_seq(ArrayAccess {
+                iter: arr.items().iter(),
+            }),
+            ValueNode::Table(t) => visitor.visit_map(TableAccess::new(t.entries())),
+            ValueNode::Invalid(_) | ValueNode::Empty => Err(Error::new(range, "invalid value")),
+        };
+
+        res.map_err(|err| err.or_range(range))
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let range = value_range(self.node);
+        visitor.visit_some(self).map_err(|err| err.or_range(range))
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let range = value_range(self.node);
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|err| err.or_range(range))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let range = value_range(self.node);
+
+        match self.node {
+            ValueNode::String(s) => visitor
+                .visit_enum(IntoDeserializer::<Error>::into_deserializer(
+                    s.content().to_string(),
+                ))
+                .map_err(|err| err.or_range(range)),
+            ValueNode::Table(t) => TableDeserializer {
+                entries: t.entries(),
+                range,
+            }
+            .deserialize_enum(name, variants, visitor),
+            _ => Err(Error::new(
+                range,
+                "expected a string or a table for an enum",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ArrayAccess<'a> {
+    iter: std::slice::Iter<'a, ValueNode>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for ArrayAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.iter.next() {
+            Some(node) => seed.deserialize(ValueDeserializer { node }).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct TableAccess<'a> {
+    iter: std::vec::IntoIter<(&'a KeyNode, &'a EntryNode)>,
+    entry: Option<&'a EntryNode>,
+}
+
+impl<'a> TableAccess<'a> {
+    fn new(entries: &'a Entries) -> Self {
+        Self {
+            iter: entries.iter().collect::<Vec<_>>().into_iter(),
+            entry: None,
+        }
+    }
+}
+
+fn key_string(key: &KeyNode) -> Result<String, Error> {
+    let keys = key
+        .keys_str()
+        .map(|k| {
+            // Only basic quoted keys can contain escape sequences,
+            // literal keys are taken as they are.
+            if k.starts_with('"') {
+                unescape(k.strip_quotes())
+            } else {
+                Ok(k.strip_quotes().to_string())
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| Error::new(Some(key.text_range()), "invalid escape sequence in key"))?;
+
+    Ok(keys.join("."))
+}
+
+impl<'de, 'a> de::MapAccess<'de> for TableAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((key, entry)) => {
+                self.entry = Some(entry);
+
+                let deserializer: StringDeserializer<Error> = key_string(key)?.into_deserializer();
+
+                seed.deserialize(deserializer)
+                    .map(Some)
+                    .map_err(|err| err.or_range(Some(key.text_range())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.entry.take() {
+            Some(entry) => seed.deserialize(ValueDeserializer {
+                node: entry.value(),
+            }),
+            None => Err(Error::new(None, "value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// An enum represented as a table with a single key.
+struct TableEnum<'a> {
+    key: &'a KeyNode,
+    entry: &'a EntryNode,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for TableEnum<'a> {
+    type Error = Error;
+    type Variant = ValueDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let deserializer: StringDeserializer<Error> = key_string(self.key)?.into_deserializer();
+
+        let variant = seed
+            .deserialize(deserializer)
+            .map_err(|err| err.or_range(Some(self.key.text_range())))?;
+
+        Ok((
+            variant,
+            ValueDeserializer {
+                node: self.entry.value(),
+            },
+        ))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.node {
+            ValueNode::Table(t) if t.entries().is_empty() => Ok(()),
+            _ => Err(Error::new(
+                value_range(self.node),
+                "expected an empty table for a unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
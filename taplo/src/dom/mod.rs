@@ -12,9 +12,10 @@
 //! The current DOM doesn't have comment or whitespace information directly exposed,
 //! but these can be added anytime.
 //!
-//! The DOM is immutable right now, and only allows for semantic analysis,
-//! but the ability to partially rewrite it is planned.
+//! The DOM itself is immutable, documents can be edited
+//! with the [rewrite] module (requires the `rewrite` feature).
 use crate::{
+    code::Code,
     syntax::{SyntaxElement, SyntaxKind::*, SyntaxToken},
     util::{unescape, StrExt},
     value::Value,
@@ -386,15 +387,26 @@ impl Cast for TableNode {
             }
             // FIXME(recursion)
             INLINE_TABLE => Some(Self {
-                entries: Entries(
-                    syntax
+                entries: {
+                    let mut entries = Entries::new();
+
+                    // Dotted keys with common prefixes are merged
+                    // the same way as the entries of other tables.
+                    //
+                    // FIXME: the errors are not reported.
+                    let mut errors = Vec::new();
+
+                    for entry in syntax
                         .as_node()
                         .unwrap()
                         .children_with_tokens()
                         .filter_map(EntryNode::cast)
-                        .map(|entry| (entry.key().clone(), entry))
-                        .collect(),
-                ),
+                    {
+                        entries.insert(entry, &mut errors);
+                    }
+
+                    entries
+                },
                 key: None,
                 end_offset: syntax.text_range().end().into(),
                 inline: true,
@@ -1743,6 +1755,21 @@ impl core::fmt::Display for Error {
 }
 impl std::error::Error for Error {}
 
+impl Error {
+    /// The stable code of the error.
+    pub fn code(&self) -> Code {
+        match self {
+            Error::DuplicateKey { .. } => Code::DuplicateKey,
+            Error::DottedKeyConflict { .. } => Code::DottedKeyConflict,
+            Error::ExpectedTableArray { .. } => Code::ExpectedArrayOfTables,
+            Error::ExpectedTable { .. } => Code::ExpectedTable,
+            Error::InlineTable { .. } => Code::InlineTableModified,
+            Error::SubTableBeforeTableArray { .. } => Code::SubtableBeforeArrayOfTables,
+            Error::Spanned { .. } | Error::Generic(_) => Code::InvalidDocument,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum PathKey {
     Key(String),
@@ -251,17 +251,37 @@ impl RewriteBuilder for FloatNode {
 }
 
 #[derive(Debug, Clone)]
-pub struct DateNode {}
+pub struct DateNode {
+    val: Option<String>,
+}
+
+impl DateNode {
+    #[cfg(any(feature = "time", feature = "chrono"))]
+    pub fn with_value(mut self, value: impl Into<crate::value::Date>) -> Self {
+        self.val = Some(value.into().to_string());
+        self
+    }
+
+    /// Sets the date from its TOML representation (e.g. `1979-05-27T07:32:00Z`).
+    ///
+    /// The text is not validated.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.val = Some(text.into());
+        self
+    }
+}
 
 impl RewriteBuilder for DateNode {
     type NewNode = super::DateNode;
 
     fn new() -> Self {
-        todo!()
+        Self { val: None }
     }
 
     fn build(self) -> Self::NewNode {
-        todo!()
+        super::DateNode {
+            val: self.val.expect("value is required"),
+        }
     }
 }
 
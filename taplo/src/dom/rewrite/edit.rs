@@ -0,0 +1,1024 @@
+use super::{OldOrNew, RewriteNode, ValueNode};
+use crate::{
+    code::Code,
+    dom::{self, NodeSyntax, Path, PathKey},
+    parser::{self, Parse},
+    syntax::{SyntaxKind::*, SyntaxNode},
+    util::{quote_key, syntax::join_ranges},
+};
+use rowan::{Direction, GreenNodeBuilder, TextRange, TextSize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// There is nothing at the given path.
+    NotFound(Path),
+    /// There is already an entry at the given path.
+    AlreadyExists(Path),
+    /// The node at the given path is not a table.
+    ExpectedTable(Path),
+    /// The node at the given path is not an array.
+    ExpectedArray(Path),
+    /// The path cannot be used for the operation,
+    /// e.g. it is empty or ends with an index
+    /// where a key is required.
+    InvalidPath(Path),
+    /// Only entries with values that are fully contained
+    /// in the entry itself can be moved, tables with
+    /// headers and arrays of tables cannot.
+    NotMovable(Path),
+    /// The given value cannot be used for the operation.
+    InvalidValue(String),
+    /// The edit would have introduced new errors in the document.
+    InvalidResult {
+        syntax_errors: Vec<parser::Error>,
+        dom_errors: Vec<dom::Error>,
+    },
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::NotFound(p) => write!(f, r#"nothing found at "{}""#, p.dotted()),
+            Error::AlreadyExists(p) => write!(f, r#"an entry already exists at "{}""#, p.dotted()),
+            Error::ExpectedTable(p) => write!(f, r#"expected a table at "{}""#, p.dotted()),
+            Error::ExpectedArray(p) => write!(f, r#"expected an array at "{}""#, p.dotted()),
+            Error::InvalidPath(p) => write!(f, r#"invalid path "{}""#, p.dotted()),
+            Error::NotMovable(p) => write!(f, r#"the entry at "{}" cannot be moved"#, p.dotted()),
+            Error::InvalidValue(msg) => write!(f, "invalid value: {}", msg),
+            Error::InvalidResult {
+                syntax_errors,
+                dom_errors,
+            } => {
+                write!(f, "the edit would result in an invalid document")?;
+                for err in syntax_errors {
+                    write!(f, "\n{}", err)?;
+                }
+                for err in dom_errors {
+                    write!(f, "\n{}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Applies path-based edits to a TOML document.
+///
+/// Each edit only changes the text of the affected entries and tables,
+/// the rest of the document is reused as-is, including comments and whitespace.
+///
+/// Edits are checked after they were applied, an edit that would introduce
+/// new syntax or semantic errors is rejected and leaves the document unchanged.
+///
+/// # Example
+///
+/// ```
+/// use taplo::{dom::{Path, rewrite::prelude::*}, parser::parse};
+///
+/// let mut editor = Editor::new(parse("# Comment\nname = \"taplo\"\n"));
+///
+/// editor
+///     .rename(&Path::new().join("name"), "title")
+///     .unwrap();
+///
+/// assert_eq!(editor.to_string(), "# Comment\ntitle = \"taplo\"\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Editor {
+    parse: Parse,
+    dom: dom::RootNode,
+}
+
+impl Editor {
+    pub fn new(parse: Parse) -> Self {
+        Self {
+            dom: parse.clone().into_dom(),
+            parse,
+        }
+    }
+
+    /// The DOM of the current state of the document.
+    pub fn dom(&self) -> &dom::RootNode {
+        &self.dom
+    }
+
+    /// The parse of the current state of the document.
+    pub fn parse(&self) -> &Parse {
+        &self.parse
+    }
+
+    pub fn into_parse(self) -> Parse {
+        self.parse
+    }
+
+    /// Inserts a new entry at the given path.
+    ///
+    /// The table that contains the entry must already exist.
+    ///
+    /// Entries are added after the last entry of the table,
+    /// dotted keys are used for tables that are defined by dotted keys.
+    /// Tables that are not inline are added as new `[table]` sections.
+    pub fn insert(&mut self, path: &Path, value: impl Into<ValueNode>) -> Result<(), Error> {
+        let value = Rendered::new(value.into());
+        self.insert_rendered(path, value)
+    }
+
+    /// Sets the value at the given path.
+    ///
+    /// Values in entries and arrays are replaced in-place,
+    /// tables with headers and arrays of tables are removed
+    /// and inserted again. The entry is inserted if it does not exist.
+    pub fn set(&mut self, path: &Path, value: impl Into<ValueNode>) -> Result<(), Error> {
+        let value = Rendered::new(value.into());
+
+        let found = match self.lookup(path) {
+            Ok(found) => found,
+            Err(Error::NotFound(_)) => return self.insert_rendered(path, value),
+            Err(err) => return Err(err),
+        };
+
+        let node = match found.node {
+            Some(node) => node,
+            None => return Err(Error::InvalidPath(path.clone())),
+        };
+
+        match value {
+            Rendered::Inline(text) if is_inline_value(&node) => {
+                self.apply(vec![(node.value.syntax().text_range(), text)])
+            }
+            value => self.transaction(|editor| {
+                editor.remove(path)?;
+                editor.insert_rendered(path, value)
+            }),
+        }
+    }
+
+    /// Removes the entry or array item at the given path.
+    ///
+    /// Tables are removed with all their entries and sub-tables.
+    pub fn remove(&mut self, path: &Path) -> Result<(), Error> {
+        let node = match self.lookup(path)?.node {
+            Some(node) => node,
+            None => return Err(Error::InvalidPath(path.clone())),
+        };
+
+        let text = self.to_string();
+
+        let inline_syntax = match &node.entry {
+            Some(entry) => entry.syntax().into_node().filter(|n| {
+                n.kind() == ENTRY && n.parent().map(|p| p.kind()) == Some(INLINE_TABLE)
+            }),
+            None => node
+                .value
+                .syntax()
+                .parent()
+                .filter(|p| p.kind() == VALUE)
+                .filter(|p| p.parent().map(|p| p.kind()) == Some(ARRAY)),
+        };
+
+        if let Some(syntax) = inline_syntax {
+            return self.apply(vec![(remove_item_range(&text, &syntax), String::new())]);
+        }
+
+        let mut ranges = Vec::new();
+        blocks(node.entry.as_ref(), &node.value, &mut ranges);
+
+        let mut ranges = merge_ranges(
+            merge_ranges(ranges)
+                .into_iter()
+                .map(|range| line_range(&text, range).unwrap_or(range))
+                .collect(),
+        );
+
+        if let Some(last) = ranges.last_mut() {
+            if last.end() == TextSize::of(&*text) {
+                *last = TextRange::new(empty_lines_start(&text, last.start()), last.end());
+            }
+        }
+
+        self.apply(
+            ranges
+                .into_iter()
+                .map(|range| (range, String::new()))
+                .collect(),
+        )
+    }
+
+    /// Renames the key of the entry at the given path.
+    ///
+    /// All occurrences of the key are renamed, including the ones
+    /// in dotted keys and table headers. The new key is quoted if needed.
+    pub fn rename(&mut self, path: &Path, new_key: &str) -> Result<(), Error> {
+        match path.keys().last() {
+            Some(PathKey::Key(_)) => {}
+            _ => return Err(Error::InvalidPath(path.clone())),
+        }
+
+        let entry = match self.lookup(path)?.node.and_then(|n| n.entry) {
+            Some(entry) => entry,
+            None => return Err(Error::InvalidPath(path.clone())),
+        };
+
+        let new_path = path.skip_right(1).join(new_key);
+
+        if self.lookup(&new_path).is_ok() {
+            return Err(Error::AlreadyExists(new_path));
+        }
+
+        let mut ranges: Vec<TextRange> = entry.key().text_ranges().into_iter().collect();
+        ranges.sort_by_key(|r| r.start());
+        ranges.dedup();
+
+        let new_key = quote_key(new_key);
+
+        self.apply(
+            ranges
+                .into_iter()
+                .map(|range| (range, new_key.clone()))
+                .collect(),
+        )
+    }
+
+    /// Appends a value to the array at the given path.
+    ///
+    /// For arrays of tables the value must be a new table,
+    /// and it is added as a new `[[table]]` section after the last one.
+    pub fn append(&mut self, path: &Path, value: impl Into<ValueNode>) -> Result<(), Error> {
+        let value = Rendered::new(value.into());
+
+        let arr = match self.lookup(path)?.node.map(|n| n.value) {
+            Some(dom::ValueNode::Array(arr)) => arr,
+            _ => return Err(Error::ExpectedArray(path.clone())),
+        };
+
+        let text = self.to_string();
+        let nl = newline(&text);
+
+        if arr.is_array_of_tables() {
+            let entries = match value {
+                Rendered::Section { entries, .. } => entries,
+                Rendered::Inline(_) => {
+                    return Err(Error::InvalidValue(
+                        "only tables that are not inline can be appended to arrays of tables"
+                            .into(),
+                    ))
+                }
+            };
+
+            let last = arr.items().last().unwrap();
+
+            let key = last
+                .syntax()
+                .into_node()
+                .and_then(|n| n.first_child())
+                .map(|k| k.text().to_string())
+                .unwrap_or_default();
+
+            let mut ranges = Vec::new();
+            blocks(None, last, &mut ranges);
+            let end = ranges.iter().map(|r| r.end()).max().unwrap();
+
+            return self.apply(vec![(
+                TextRange::empty(end),
+                format!(
+                    "{nl}{nl}{}",
+                    section(key.trim(), true, &entries, nl),
+                    nl = nl
+                ),
+            )]);
+        }
+
+        let value = value.into_inline();
+
+        let arr_syntax = arr.syntax().into_node().unwrap();
+
+        let bracket_start = arr_syntax.first_token().unwrap().text_range();
+        let bracket_end = arr_syntax.last_token().unwrap().text_range();
+
+        let last_item = match arr_syntax.children().filter(|c| c.kind() == VALUE).last() {
+            Some(item) => item,
+            None => {
+                return self.apply(vec![(
+                    TextRange::new(bracket_start.end(), bracket_end.start()),
+                    value,
+                )])
+            }
+        };
+
+        match comma_after(&last_item) {
+            Some(comma) => {
+                let line_break = comma
+                    .as_token()
+                    .unwrap()
+                    .siblings_with_tokens(Direction::Next)
+                    .skip(1)
+                    .take_while(|t| t.kind() != BRACKET_END)
+                    .find(|t| t.kind() == NEWLINE);
+
+                match line_break {
+                    Some(line_break) => {
+                        let line_start = line_start(&text, last_item.text_range().start());
+                        let indent =
+                            &text[TextRange::new(line_start, last_item.text_range().start())];
+
+                        let pos = line_break.text_range().start() + TextSize::of(nl);
+
+                        self.apply(vec![(
+                            TextRange::empty(pos),
+                            format!("{}{},{}", indent, value, nl),
+                        )])
+                    }
+                    None => self.apply(vec![(
+                        TextRange::empty(comma.text_range().end()),
+                        format!(" {},", value),
+                    )]),
+                }
+            }
+            None => self.apply(vec![(
+                TextRange::empty(trimmed_range(&last_item).end()),
+                format!(", {}", value),
+            )]),
+        }
+    }
+
+    /// Moves the entry at the given path into the table at `to`.
+    ///
+    /// The key of the entry is kept, and its value is moved unchanged.
+    pub fn move_entry(&mut self, from: &Path, to: &Path) -> Result<(), Error> {
+        let key = match from.keys().last() {
+            Some(PathKey::Key(k)) => k.clone(),
+            _ => return Err(Error::InvalidPath(from.clone())),
+        };
+
+        let node = match self.lookup(from)?.node {
+            Some(node) => node,
+            None => return Err(Error::InvalidPath(from.clone())),
+        };
+
+        if !is_inline_value(&node) {
+            return Err(Error::NotMovable(from.clone()));
+        }
+
+        let value = node.value.syntax().to_string();
+
+        self.transaction(|editor| {
+            editor.remove(from)?;
+            editor.insert_rendered(&to.join(key), Rendered::Inline(value))
+        })
+    }
+
+    fn insert_rendered(&mut self, path: &Path, value: Rendered) -> Result<(), Error> {
+        let key = match path.keys().last() {
+            Some(PathKey::Key(k)) => k.clone(),
+            _ => return Err(Error::InvalidPath(path.clone())),
+        };
+
+        if self.lookup(path).is_ok() {
+            return Err(Error::AlreadyExists(path.clone()));
+        }
+
+        let parent_path = path.skip_right(1);
+        let parent = self.lookup(&parent_path)?;
+
+        let text = self.to_string();
+        let nl = newline(&text);
+
+        let header = path
+            .without_index()
+            .keys()
+            .map(|k| quote_key(&k.to_string()))
+            .collect::<Vec<_>>()
+            .join(".");
+
+        let table = match parent.node.as_ref().map(|n| &n.value) {
+            None => None,
+            Some(dom::ValueNode::Table(t)) => Some(t),
+            Some(_) => return Err(Error::ExpectedTable(parent_path)),
+        };
+
+        let (pos, new_text) = match (table, value) {
+            // Root table.
+            (None, Rendered::Inline(value)) => {
+                let root = self.dom.syntax().into_node().unwrap();
+
+                let mut last_entry = None;
+                let mut first_header = None;
+
+                for child in root.children() {
+                    match child.kind() {
+                        ENTRY => last_entry = Some(child),
+                        TABLE_HEADER | TABLE_ARRAY_HEADER => {
+                            first_header = Some(child);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+
+                let line = format!("{} = {}", quote_key(&key), value);
+
+                match (last_entry, first_header) {
+                    (Some(entry), _) => (entry.text_range().end(), format!("{}{}", nl, line)),
+                    (None, Some(header)) => (
+                        leading_trivia_start(&header),
+                        format!("{}{nl}{nl}", line, nl = nl),
+                    ),
+                    (None, None) => {
+                        if text.is_empty() || text.ends_with('\n') {
+                            (TextSize::of(&*text), format!("{}{}", line, nl))
+                        } else {
+                            (TextSize::of(&*text), format!("{}{}{}", nl, line, nl))
+                        }
+                    }
+                }
+            }
+            (None, Rendered::Section { array, entries }) => {
+                let mut ranges = Vec::new();
+                for (_, entry) in self.dom.entries().iter() {
+                    blocks(Some(entry), entry.value(), &mut ranges);
+                }
+
+                match ranges.iter().map(|r| r.end()).max() {
+                    Some(end) => (
+                        end,
+                        format!("{nl}{nl}{}", section(&header, array, &entries, nl), nl = nl),
+                    ),
+                    None => (
+                        TextSize::of(&*text),
+                        format!("{}{}", section(&header, array, &entries, nl), nl),
+                    ),
+                }
+            }
+            // Inline tables.
+            (Some(t), value) if t.is_inline() => {
+                let line = format!("{} = {}", quote_key(&key), value.into_inline());
+                inline_table_insert(t, line)
+            }
+            // Tables with headers.
+            (Some(t), Rendered::Inline(value)) if !t.is_pseudo() => {
+                let header_syntax = t.syntax().into_node().unwrap();
+                (
+                    section_range(&header_syntax).end(),
+                    format!("{}{} = {}", nl, quote_key(&key), value),
+                )
+            }
+            // Tables that are defined by headers of their sub-tables.
+            (Some(t), Rendered::Inline(value))
+                if matches!(t.syntax().kind(), TABLE_HEADER | TABLE_ARRAY_HEADER) =>
+            {
+                let parent_header = parent_path
+                    .without_index()
+                    .keys()
+                    .map(|k| quote_key(&k.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(".");
+
+                let header_syntax = t.syntax().into_node().unwrap();
+
+                (
+                    leading_trivia_start(&header_syntax),
+                    format!(
+                        "[{}]{nl}{} = {}{nl}{nl}",
+                        parent_header,
+                        quote_key(&key),
+                        value,
+                        nl = nl
+                    ),
+                )
+            }
+            (Some(t), Rendered::Section { array, entries })
+                if !t.is_pseudo() || t.syntax().kind() != ENTRY && t.syntax().kind() != KEY =>
+            {
+                let mut ranges = Vec::new();
+                blocks(
+                    parent.node.as_ref().and_then(|n| n.entry.as_ref()),
+                    &parent.node.as_ref().unwrap().value,
+                    &mut ranges,
+                );
+                let end = ranges.iter().map(|r| r.end()).max().unwrap();
+
+                (
+                    end,
+                    format!("{nl}{nl}{}", section(&header, array, &entries, nl), nl = nl),
+                )
+            }
+            // Tables that are defined by dotted keys.
+            (Some(t), value) => {
+                let mut dotted_key = parent.prefix.clone();
+                dotted_key.push(key);
+
+                let line = format!(
+                    "{} = {}",
+                    dotted_key
+                        .iter()
+                        .map(|k| quote_key(k))
+                        .collect::<Vec<_>>()
+                        .join("."),
+                    value.into_inline()
+                );
+
+                let mut ranges = Vec::new();
+                for (_, entry) in t.entries().iter() {
+                    blocks(Some(entry), entry.value(), &mut ranges);
+                }
+                let end = ranges.iter().map(|r| r.end()).max().unwrap();
+
+                if parent.inline_container {
+                    (end, format!(", {}", line))
+                } else {
+                    (end, format!("{}{}", nl, line))
+                }
+            }
+        };
+
+        self.apply(vec![(TextRange::empty(pos), new_text)])
+    }
+
+    /// Finds the node at the given path.
+    fn lookup(&self, path: &Path) -> Result<Lookup, Error> {
+        let mut node: Option<Found> = None;
+        let mut prefix: Vec<String> = Vec::new();
+        let mut inline_container = false;
+
+        for (i, key) in path.keys().enumerate() {
+            let not_found = || Error::NotFound(path.retain_left(i + 1));
+
+            let found = match key {
+                PathKey::Key(k) => {
+                    let entries = match node.as_ref().map(|n| &n.value) {
+                        None => self.dom.entries(),
+                        Some(dom::ValueNode::Table(t)) => t.entries(),
+                        Some(_) => return Err(not_found()),
+                    };
+
+                    let entry = entries
+                        .iter()
+                        .find(|(key, _)| key.full_key_string_stripped() == *k)
+                        .map(|(_, entry)| entry.clone())
+                        .ok_or_else(not_found)?;
+
+                    Found {
+                        value: entry.value().clone(),
+                        entry: Some(entry),
+                    }
+                }
+                PathKey::Index(idx) => match node.as_ref().map(|n| &n.value) {
+                    Some(dom::ValueNode::Array(arr)) => Found {
+                        entry: None,
+                        value: arr.items().get(*idx).cloned().ok_or_else(not_found)?,
+                    },
+                    _ => return Err(not_found()),
+                },
+            };
+
+            if let dom::ValueNode::Table(t) = &found.value {
+                if t.is_pseudo() {
+                    prefix.push(key.to_string());
+                } else {
+                    prefix.clear();
+                    inline_container = t.is_inline();
+                }
+            }
+
+            node = Some(found);
+        }
+
+        Ok(Lookup {
+            node,
+            prefix,
+            inline_container,
+        })
+    }
+
+    /// Applies the given non-overlapping edits and reparses the changed part of the document.
+    fn apply(&mut self, mut edits: Vec<(TextRange, String)>) -> Result<(), Error> {
+        if edits.is_empty() {
+            return Ok(());
+        }
+
+        edits.sort_by_key(|(range, _)| range.start());
+
+        let text = self.to_string();
+        let range = join_ranges(edits.iter().map(|(range, _)| *range));
+
+        let mut replacement = String::new();
+        let mut offset = range.start();
+
+        for (edit_range, edit_text) in edits {
+            replacement += &text[TextRange::new(offset, edit_range.start())];
+            replacement += &edit_text;
+            offset = edit_range.end();
+        }
+
+        replacement += &text[TextRange::new(offset, range.end())];
+
+        let parse = self.parse.reparse(range, &replacement);
+        let dom = parse.clone().into_dom();
+
+        let old_errors = error_counts(&self.parse.errors, self.dom.errors());
+        let new_errors = error_counts(&parse.errors, dom.errors());
+
+        if new_errors
+            .iter()
+            .any(|(error, count)| old_errors.get(error).map_or(true, |old| count > old))
+        {
+            return Err(Error::InvalidResult {
+                syntax_errors: parse.errors,
+                dom_errors: dom.errors().to_vec(),
+            });
+        }
+
+        self.parse = parse;
+        self.dom = dom;
+
+        Ok(())
+    }
+
+    /// Restores the document if any of the edits fail.
+    fn transaction(&mut self, f: impl FnOnce(&mut Self) -> Result<(), Error>) -> Result<(), Error> {
+        let parse = self.parse.clone();
+        let dom = self.dom.clone();
+
+        let res = f(self);
+
+        if res.is_err() {
+            self.parse = parse;
+            self.dom = dom;
+        }
+
+        res
+    }
+}
+
+impl core::fmt::Display for Editor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.dom.syntax())
+    }
+}
+
+impl dom::RootNode {
+    /// Creates an [Editor] for the document.
+    pub fn edit(&self) -> Editor {
+        Editor::new(parser::parse(&self.syntax().to_string()))
+    }
+}
+
+struct Found {
+    /// The entry of the value, `None` for array items.
+    entry: Option<dom::EntryNode>,
+    value: dom::ValueNode,
+}
+
+struct Lookup {
+    /// `None` for the root table.
+    node: Option<Found>,
+
+    /// Keys of pseudo-tables since the last
+    /// table with its own syntax.
+    prefix: Vec<String>,
+
+    /// Whether the last table with its own
+    /// syntax is an inline table.
+    inline_container: bool,
+}
+
+/// A value that was turned into text.
+enum Rendered {
+    Inline(String),
+    Section { array: bool, entries: Vec<String> },
+}
+
+impl Rendered {
+    fn new(value: ValueNode) -> Self {
+        match value {
+            ValueNode::Table(OldOrNew::New(t)) if !t.inline => Rendered::Section {
+                array: t.part_of_array,
+                entries: t.entries.into_iter().map(render).collect(),
+            },
+            value => Rendered::Inline(render(value)),
+        }
+    }
+
+    fn into_inline(self) -> String {
+        match self {
+            Rendered::Inline(s) => s,
+            Rendered::Section { array, entries } => {
+                let table = if entries.is_empty() {
+                    String::from("{}")
+                } else {
+                    format!("{{ {} }}", entries.join(", "))
+                };
+
+                if array {
+                    format!("[{}]", table)
+                } else {
+                    table
+                }
+            }
+        }
+    }
+}
+
+/// Counts the errors by their code and the keys involved,
+/// so that errors that only moved are not mistaken for new ones.
+fn error_counts(
+    syntax_errors: &[parser::Error],
+    dom_errors: &[dom::Error],
+) -> HashMap<(Code, Vec<String>), usize> {
+    let mut counts = HashMap::new();
+
+    let syntax_errors = syntax_errors
+        .iter()
+        .map(|err| (err.code, vec![err.message.clone()]));
+
+    let dom_errors = dom_errors.iter().map(|err| {
+        let keys = match err {
+            dom::Error::DuplicateKey { first, second }
+            | dom::Error::DottedKeyConflict { first, second } => vec![
+                first.full_key_string_stripped(),
+                second.full_key_string_stripped(),
+            ],
+            dom::Error::ExpectedTableArray { target, key }
+            | dom::Error::ExpectedTable { target, key }
+            | dom::Error::InlineTable { target, key }
+            | dom::Error::SubTableBeforeTableArray { target, key } => vec![
+                target.full_key_string_stripped(),
+                key.full_key_string_stripped(),
+            ],
+            dom::Error::Spanned { message, .. } => vec![message.clone()],
+            dom::Error::Generic(message) => vec![message.clone()],
+        };
+
+        (err.code(), keys)
+    });
+
+    for error in syntax_errors.chain(dom_errors) {
+        *counts.entry(error).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+fn render(node: impl Into<RewriteNode>) -> String {
+    let mut builder = GreenNodeBuilder::new();
+    builder.start_node(ROOT.into());
+    node.into().into_tree(&mut builder);
+    builder.finish_node();
+    SyntaxNode::new_root(builder.finish()).to_string()
+}
+
+fn section(header: &str, array: bool, entries: &[String], nl: &str) -> String {
+    let mut s = if array {
+        format!("[[{}]]", header)
+    } else {
+        format!("[{}]", header)
+    };
+
+    for entry in entries {
+        s += nl;
+        s += entry;
+    }
+
+    s
+}
+
+fn newline(text: &str) -> &'static str {
+    if text.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Whether the value is fully contained in an entry or array.
+fn is_inline_value(found: &Found) -> bool {
+    match &found.value {
+        dom::ValueNode::Table(t) => t.is_inline(),
+        dom::ValueNode::Array(arr) => !arr.is_array_of_tables(),
+        _ => true,
+    }
+}
+
+/// Collects the ranges of all the syntax that belongs to an entry or value.
+fn blocks(entry: Option<&dom::EntryNode>, value: &dom::ValueNode, ranges: &mut Vec<TextRange>) {
+    if let Some(entry) = entry {
+        if entry.syntax().kind() == ENTRY {
+            ranges.push(entry.syntax().text_range());
+            return;
+        }
+    }
+
+    match value {
+        dom::ValueNode::Table(t) if !t.is_inline() => {
+            if !t.is_pseudo() {
+                ranges.push(section_range(&t.syntax().into_node().unwrap()));
+            }
+
+            for (_, entry) in t.entries().iter() {
+                blocks(Some(entry), entry.value(), ranges);
+            }
+        }
+        dom::ValueNode::Array(arr) if arr.is_array_of_tables() => {
+            for item in arr.items() {
+                blocks(None, item, ranges);
+            }
+        }
+        value => ranges.push(value.syntax().text_range()),
+    }
+}
+
+/// The range of a table header and the entries that directly follow it.
+fn section_range(header: &SyntaxNode) -> TextRange {
+    let mut end = header.text_range().end();
+
+    for sibling in header.siblings_with_tokens(Direction::Next).skip(1) {
+        match sibling.kind() {
+            TABLE_HEADER | TABLE_ARRAY_HEADER => break,
+            ENTRY => end = sibling.text_range().end(),
+            _ => {}
+        }
+    }
+
+    TextRange::new(header.text_range().start(), end)
+}
+
+/// Sorts the ranges and merges the overlapping ones.
+fn merge_ranges(mut ranges: Vec<TextRange>) -> Vec<TextRange> {
+    ranges.sort_by_key(|r| r.start());
+
+    let mut merged: Vec<TextRange> = Vec::with_capacity(ranges.len());
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end() >= range.start() => *last = last.cover(range),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+fn line_start(text: &str, offset: TextSize) -> TextSize {
+    text[..usize::from(offset)]
+        .rfind('\n')
+        .map(|i| TextSize::from(i as u32 + 1))
+        .unwrap_or_default()
+}
+
+/// Extends the range to the full lines it spans, including the
+/// line break, if there is nothing else on these lines except for comments.
+///
+/// If the lines are preceded by an empty line,
+/// the empty lines that follow are also included.
+fn line_range(text: &str, range: TextRange) -> Option<TextRange> {
+    let start = line_start(text, range.start());
+
+    if !text[TextRange::new(start, range.start())].trim().is_empty() {
+        return None;
+    }
+
+    let after = &text[usize::from(range.end())..];
+    let line_end = after.find('\n').unwrap_or(after.len());
+    let rest = after[..line_end].trim();
+
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return None;
+    }
+
+    let mut start = usize::from(start);
+    let mut end = usize::from(range.end()) + line_end;
+
+    if end < text.len() {
+        // The line break.
+        end += 1;
+
+        let before = text[..start].trim_end_matches('\r');
+        if start == 0 || before.ends_with("\n\n") || before.ends_with("\n\r\n") {
+            while text[end..].starts_with('\n') || text[end..].starts_with("\r\n") {
+                end += text[end..].find('\n').unwrap() + 1;
+            }
+        }
+    }
+
+    if end >= text.len() {
+        start = usize::from(empty_lines_start(text, TextSize::from(start as u32)));
+    }
+
+    Some(TextRange::new(
+        TextSize::from(start as u32),
+        TextSize::from(end as u32),
+    ))
+}
+
+/// The start of the empty lines before the offset,
+/// the line break of the last non-empty line is kept.
+fn empty_lines_start(text: &str, offset: TextSize) -> TextSize {
+    let content_end = text[..usize::from(offset)]
+        .trim_end_matches(&['\n', '\r'][..])
+        .len();
+
+    match text[content_end..usize::from(offset)].find('\n') {
+        Some(i) if content_end != 0 => TextSize::from((content_end + i + 1) as u32),
+        _ => TextSize::from(0),
+    }
+}
+
+/// The range without trailing whitespace.
+fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let end = node
+        .descendants_with_tokens()
+        .filter(|t| t.as_token().is_some() && !matches!(t.kind(), WHITESPACE | NEWLINE))
+        .map(|t| t.text_range().end())
+        .last()
+        .unwrap_or_else(|| node.text_range().end());
+
+    TextRange::new(node.text_range().start(), end)
+}
+
+fn comma_after(item: &SyntaxNode) -> Option<crate::syntax::SyntaxElement> {
+    item.siblings_with_tokens(Direction::Next)
+        .skip(1)
+        .take_while(|s| s.kind() != item.kind())
+        .find(|s| s.kind() == COMMA)
+}
+
+/// The range to remove for an entry of an inline table or an array item,
+/// including the separating comma.
+fn remove_item_range(text: &str, item: &SyntaxNode) -> TextRange {
+    let parent = item.parent().unwrap();
+
+    let comma = comma_after(item);
+
+    let own = TextRange::new(
+        item.text_range().start(),
+        comma
+            .as_ref()
+            .map(|c| c.text_range().end())
+            .unwrap_or_else(|| trimmed_range(item).end()),
+    );
+
+    if let Some(range) = line_range(text, own) {
+        return range;
+    }
+
+    let next = item
+        .siblings(Direction::Next)
+        .skip(1)
+        .find(|s| s.kind() == item.kind());
+
+    if let Some(next) = next {
+        return TextRange::new(item.text_range().start(), next.text_range().start());
+    }
+
+    let prev = item
+        .siblings(Direction::Prev)
+        .skip(1)
+        .find(|s| s.kind() == item.kind());
+
+    match prev {
+        Some(prev) => match comma {
+            Some(_) => TextRange::new(comma_after(&prev).unwrap().text_range().end(), own.end()),
+            None => TextRange::new(trimmed_range(&prev).end(), own.end()),
+        },
+        None => TextRange::new(
+            parent.first_token().unwrap().text_range().end(),
+            parent.last_token().unwrap().text_range().start(),
+        ),
+    }
+}
+
+fn inline_table_insert(table: &dom::TableNode, line: String) -> (TextSize, String) {
+    let syntax = table.syntax().into_node().unwrap();
+
+    match syntax.children().filter(|c| c.kind() == ENTRY).last() {
+        Some(entry) => (trimmed_range(&entry).end(), format!(", {}", line)),
+        None => (
+            syntax.first_token().unwrap().text_range().end(),
+            format!(" {} ", line),
+        ),
+    }
+}
+
+/// The start of the comments that directly precede the node.
+fn leading_trivia_start(node: &SyntaxNode) -> TextSize {
+    let mut start = node.text_range().start();
+    let mut prev = node.prev_sibling_or_token();
+
+    while let Some(p) = prev {
+        match p.kind() {
+            COMMENT => start = p.text_range().start(),
+            WHITESPACE => {}
+            NEWLINE
+                if p.as_token()
+                    .map(|t| t.text().matches('\n').count() == 1)
+                    .unwrap_or(false) => {}
+            _ => break,
+        }
+        prev = p.prev_sibling_or_token();
+    }
+
+    start
+}
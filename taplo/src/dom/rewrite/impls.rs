@@ -30,7 +30,7 @@ fn rewrite_tree(elem: SyntaxElement, info: &mut RewriteInfo, builder: &mut Green
                 builder.finish_node();
             }
             NodeOrToken::Token(token) => {
-                builder.token(token.kind().into(), token.text().clone());
+                builder.token(token.kind().into(), token.text());
             }
         },
     }
@@ -1,14 +1,29 @@
-// TODO: do conversions in a proper way.
-use crate::{dom, syntax::SyntaxKind::*, util::syntax::add_all};
+//! Rewriting and editing of TOML documents.
+//!
+//! [RootNode::rewrite](dom::RootNode::rewrite) allows replacing nodes
+//! while walking the DOM, and [Editor] provides path-based edits
+//! (inserting, removing, renaming, moving entries and appending to arrays).
+//!
+//! Only the modified parts of the document are touched,
+//! comments and whitespace everywhere else are preserved.
+use crate::{
+    dom,
+    syntax::SyntaxKind::*,
+    util::{escape, quote_key, syntax::add_all},
+};
 use rowan::GreenNodeBuilder;
 
 use super::NodeSyntax;
 
 #[doc(hidden)]
 pub mod builders;
+mod edit;
 mod impls;
 
+pub use edit::{Editor, Error};
+
 pub mod prelude {
+    pub use super::Editor;
     pub use super::Rewrite;
     pub use super::RewriteBuilder;
     pub use super::RewriteNode;
@@ -28,7 +43,7 @@ impl RewriteNode {
                     add_all(n, builder);
                 }
                 rowan::NodeOrToken::Token(t) => {
-                    builder.token(t.kind().into(), t.text().clone());
+                    builder.token(t.kind().into(), t.text());
                 }
             },
             RewriteNode::New(node) => node.into_tree(builder),
@@ -59,10 +74,7 @@ pub enum OldOrNew<N: Rewrite + Into<dom::Node>> {
 
 impl<N: Rewrite + Into<dom::Node>> OldOrNew<N> {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        match RewriteNode::from(self) {
-            RewriteNode::New(node) => node.into_tree(builder),
-            _ => {}
-        }
+        RewriteNode::from(self).into_tree(builder)
     }
 }
 
@@ -167,9 +179,9 @@ impl EntryNode {
 
         self.key.into_tree(builder);
 
-        builder.token(WHITESPACE.into(), " ".into());
-        builder.token(EQ.into(), "=".into());
-        builder.token(WHITESPACE.into(), " ".into());
+        builder.token(WHITESPACE.into(), " ");
+        builder.token(EQ.into(), "=");
+        builder.token(WHITESPACE.into(), " ");
 
         self.value.into_tree(builder);
 
@@ -184,22 +196,14 @@ pub struct KeyNode {
 
 impl KeyNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        let keys = self.keys.split('.').map(|s| {
-            if s.is_empty() || s.is_ascii() {
-                s.to_string()
-            } else {
-                format!(r#"'{}'"#, s)
-            }
-        });
-
         builder.start_node(KEY.into());
 
-        for (i, key) in keys.enumerate() {
+        for (i, key) in self.keys.split('.').enumerate() {
             if i != 0 {
-                builder.token(PERIOD.into(), ".".into());
+                builder.token(PERIOD.into(), ".");
             }
 
-            builder.token(IDENT.into(), key.into());
+            builder.token(IDENT.into(), &quote_key(key));
         }
 
         builder.finish_node();
@@ -250,16 +254,17 @@ impl ArrayNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
         builder.start_node(ARRAY.into());
 
-        builder.token(BRACKET_START.into(), "[".into());
-        builder.token(WHITESPACE.into(), " ".into());
+        builder.token(BRACKET_START.into(), "[");
 
-        for item in self.items {
+        for (i, item) in self.items.into_iter().enumerate() {
+            if i != 0 {
+                builder.token(COMMA.into(), ",");
+                builder.token(WHITESPACE.into(), " ");
+            }
             item.into_tree(builder);
-            builder.token(COMMA.into(), ",".into());
         }
 
-        builder.token(WHITESPACE.into(), " ".into());
-        builder.token(BRACKET_END.into(), "]".into());
+        builder.token(BRACKET_END.into(), "]");
 
         builder.finish_node();
     }
@@ -277,19 +282,22 @@ impl TableNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
         if self.inline {
             builder.start_node(INLINE_TABLE.into());
-            builder.token(BRACE_START.into(), "{".into());
-            builder.token(WHITESPACE.into(), " ".into());
+            builder.token(BRACE_START.into(), "{");
+
+            let empty = self.entries.is_empty();
 
             for (i, entry) in self.entries.into_iter().enumerate() {
                 if i != 0 {
-                    builder.token(COMMA.into(), ",".into());
-                    builder.token(WHITESPACE.into(), " ".into());
+                    builder.token(COMMA.into(), ",");
                 }
+                builder.token(WHITESPACE.into(), " ");
                 entry.into_tree(builder);
             }
 
-            builder.token(WHITESPACE.into(), " ".into());
-            builder.token(BRACE_END.into(), "}".into());
+            if !empty {
+                builder.token(WHITESPACE.into(), " ");
+            }
+            builder.token(BRACE_END.into(), "}");
 
             builder.finish_node();
         } else {
@@ -297,12 +305,12 @@ impl TableNode {
 
             if self.part_of_array {
                 builder.start_node(TABLE_ARRAY_HEADER.into());
-                builder.token(BRACKET_START.into(), "[".into());
-                builder.token(BRACKET_START.into(), "[".into());
+                builder.token(BRACKET_START.into(), "[");
+                builder.token(BRACKET_START.into(), "[");
                 key.into_tree(builder);
-                builder.token(BRACKET_END.into(), "]".into());
-                builder.token(BRACKET_END.into(), "]".into());
-                builder.token(NEWLINE.into(), "\n".into());
+                builder.token(BRACKET_END.into(), "]");
+                builder.token(BRACKET_END.into(), "]");
+                builder.token(NEWLINE.into(), "\n");
                 builder.finish_node();
 
                 let entry_count = self.entries.len();
@@ -310,15 +318,15 @@ impl TableNode {
                 for (i, entry) in self.entries.into_iter().enumerate() {
                     entry.into_tree(builder);
                     if i != entry_count - 1 {
-                        builder.token(NEWLINE.into(), "\n".into());
+                        builder.token(NEWLINE.into(), "\n");
                     }
                 }
             } else {
                 builder.start_node(TABLE_HEADER.into());
-                builder.token(BRACKET_START.into(), "[".into());
+                builder.token(BRACKET_START.into(), "[");
                 key.into_tree(builder);
-                builder.token(BRACKET_END.into(), "]".into());
-                builder.token(NEWLINE.into(), "\n".into());
+                builder.token(BRACKET_END.into(), "]");
+                builder.token(NEWLINE.into(), "\n");
                 builder.finish_node();
 
                 let entry_count = self.entries.len();
@@ -326,7 +334,7 @@ impl TableNode {
                 for (i, entry) in self.entries.into_iter().enumerate() {
                     entry.into_tree(builder);
                     if i != entry_count - 1 {
-                        builder.token(NEWLINE.into(), "\n".into());
+                        builder.token(NEWLINE.into(), "\n");
                     }
                 }
             }
@@ -341,7 +349,7 @@ pub struct IntegerNode {
 
 impl IntegerNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        builder.token(INTEGER.into(), self.val.to_string().into());
+        builder.token(INTEGER.into(), &self.val.to_string());
     }
 }
 
@@ -352,8 +360,7 @@ pub struct StringNode {
 
 impl StringNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        // FIXME: handle multiline content.
-        builder.token(STRING.into(), format!(r#"'{}'"#, self.val).into());
+        builder.token(STRING.into(), &format!(r#""{}""#, escape(&self.val)));
     }
 }
 
@@ -364,7 +371,7 @@ pub struct BoolNode {
 
 impl BoolNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        builder.token(BOOL.into(), self.val.to_string().into());
+        builder.token(BOOL.into(), if self.val { "true" } else { "false" });
     }
 }
 
@@ -375,16 +382,36 @@ pub struct FloatNode {
 
 impl FloatNode {
     pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
-        builder.token(FLOAT.into(), self.val.to_string().into());
+        let val = if self.val.is_nan() {
+            String::from(if self.val.is_sign_negative() {
+                "-nan"
+            } else {
+                "nan"
+            })
+        } else if self.val.is_infinite() {
+            String::from(if self.val.is_sign_negative() {
+                "-inf"
+            } else {
+                "inf"
+            })
+        } else {
+            // The debug output always contains
+            // a fractional part or an exponent.
+            format!("{:?}", self.val)
+        };
+
+        builder.token(FLOAT.into(), &val);
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct DateNode {}
+pub struct DateNode {
+    pub(crate) val: String,
+}
 
 impl DateNode {
-    pub(crate) fn into_tree(self, _builder: &mut GreenNodeBuilder) {
-        todo!()
+    pub(crate) fn into_tree(self, builder: &mut GreenNodeBuilder) {
+        builder.token(DATE.into(), &self.val);
     }
 }
 
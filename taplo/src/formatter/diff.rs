@@ -0,0 +1,264 @@
+//! Minimal text edits between a document and its formatted version.
+//!
+//! The texts are compared line by line with Myers' algorithm,
+//! then every changed block of lines is compared word by word,
+//! and finally narrowed down to the characters that actually differ.
+
+use rowan::{TextRange, TextSize};
+
+/// Above this many differing parts the remaining
+/// changes are replaced as a single block instead.
+const MAX_DISTANCE: usize = 1000;
+
+/// A replacement of a range in the original text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    /// The range in the original text.
+    pub range: TextRange,
+    /// The text to replace the range with.
+    pub new_text: String,
+}
+
+/// Returns the edits that turn `original` into `changed`.
+///
+/// The edits are sorted and do not overlap,
+/// unchanged text is never part of an edit.
+pub fn diff(original: &str, changed: &str) -> Vec<TextEdit> {
+    let old_lines = lines(original);
+    let new_lines = lines(changed);
+
+    let mut edits = Vec::new();
+
+    for (old, new) in changed_blocks(&old_lines, &new_lines) {
+        let old_start = offset(&old_lines, old.start);
+        let old_text = &original[old_start..offset(&old_lines, old.end)];
+
+        let new_start = offset(&new_lines, new.start);
+        let new_text = &changed[new_start..offset(&new_lines, new.end)];
+
+        // Changed lines are compared word by word
+        // so that unchanged words are kept as well.
+        let old_words = words(old_text);
+        let new_words = words(new_text);
+
+        for (old, new) in changed_blocks(&old_words, &new_words) {
+            let old_word_start = offset(&old_words, old.start);
+            let old_text = &old_text[old_word_start..offset(&old_words, old.end)];
+
+            let new_word_start = offset(&new_words, new.start);
+            let new_text = &new_text[new_word_start..offset(&new_words, new.end)];
+
+            let prefix = common_prefix(old_text, new_text);
+            let suffix = common_suffix(&old_text[prefix..], &new_text[prefix..]);
+
+            let start = old_start + old_word_start;
+
+            edits.push(TextEdit {
+                range: TextRange::new(
+                    TextSize::from((start + prefix) as u32),
+                    TextSize::from((start + old_text.len() - suffix) as u32),
+                ),
+                new_text: new_text[prefix..new_text.len() - suffix].into(),
+            });
+        }
+    }
+
+    edits
+}
+
+/// Applies sorted, non-overlapping edits to the text.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut s = String::with_capacity(text.len());
+    let mut offset = 0;
+
+    for edit in edits {
+        s += &text[offset..usize::from(edit.range.start())];
+        s += &edit.new_text;
+        offset = edit.range.end().into();
+    }
+
+    s += &text[offset..];
+    s
+}
+
+/// Splits the text into lines, keeping the line endings.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Splits the text into runs of whitespace and other characters.
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut whitespace = None;
+
+    for (i, c) in text.char_indices() {
+        if whitespace.is_some() && whitespace != Some(c.is_whitespace()) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+
+        whitespace = Some(c.is_whitespace());
+    }
+
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// The offset of the part at the given index.
+fn offset(parts: &[&str], index: usize) -> usize {
+    parts[..index].iter().map(|l| l.len()).sum()
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, c1), c2)| c1 != c2)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(c1, c2)| c1 == c2)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
+type Block = (std::ops::Range<usize>, std::ops::Range<usize>);
+
+/// Returns the ranges of parts that differ in the old and new parts.
+fn changed_blocks(old: &[&str], new: &[&str]) -> Vec<Block> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_range = prefix..old.len() - suffix;
+    let new_range = prefix..new.len() - suffix;
+
+    if old_range.is_empty() && new_range.is_empty() {
+        return Vec::new();
+    }
+
+    let snakes = match myers(&old[old_range.clone()], &new[new_range.clone()]) {
+        Some(snakes) => snakes,
+        None => return vec![(old_range, new_range)],
+    };
+
+    let mut blocks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+
+    for (sx, sy, len) in snakes {
+        if sx > x || sy > y {
+            blocks.push((
+                old_range.start + x..old_range.start + sx,
+                new_range.start + y..new_range.start + sy,
+            ));
+        }
+
+        x = sx + len;
+        y = sy + len;
+    }
+
+    if x < old_range.len() || y < new_range.len() {
+        blocks.push((
+            old_range.start + x..old_range.end,
+            new_range.start + y..new_range.end,
+        ));
+    }
+
+    blocks
+}
+
+/// Returns the common runs of parts as `(old_index, new_index, length)`
+/// in order, or `None` if the texts differ too much.
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<(usize, usize, usize)>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (old.len() + new.len()).min(MAX_DISTANCE) as isize;
+
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+
+    let mut found = None;
+
+    'outer: for d in 0..=max {
+        trace.push(v.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[idx] = x;
+
+            if x >= n && y >= m {
+                found = Some(d);
+                break 'outer;
+            }
+        }
+    }
+
+    let d_end = found?;
+
+    let mut snakes = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for d in (0..=d_end).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_k = if k == -d
+                || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize])
+            {
+                k + 1
+            } else {
+                k - 1
+            };
+            let prev_x = v[(prev_k + offset) as usize];
+            (prev_x, prev_x - prev_k)
+        };
+
+        // The diagonal after the step from the previous round.
+        let (start_x, start_y) = if d == 0 {
+            (0, 0)
+        } else if x - y == prev_x - prev_y + 1 {
+            (prev_x + 1, prev_y)
+        } else {
+            (prev_x, prev_y + 1)
+        };
+
+        if x > start_x {
+            snakes.push((start_x as usize, start_y as usize, (x - start_x) as usize));
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    snakes.reverse();
+    Some(snakes)
+}
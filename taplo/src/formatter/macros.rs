@@ -85,6 +85,15 @@ macro_rules! create_options {
 
                 o
             }
+
+            #[doc(hidden)]
+            pub fn update(&mut self, incomplete: OptionsIncomplete) {
+                $(
+                    if incomplete.$name.is_some() {
+                        self.$name = incomplete.$name;
+                    }
+                )+
+            }
         }
 
         #[cfg_attr(feature = "schema", derive(JsonSchema))]
@@ -23,6 +23,9 @@ use schemars::JsonSchema;
 
 #[macro_use]
 mod macros;
+mod diff;
+
+pub use diff::{apply_edits, diff, TextEdit};
 
 #[derive(Debug, Clone, Default)]
 /// Scoped formatter options based on text ranges.
@@ -222,12 +225,7 @@ pub fn format_with_scopes(dom: RootNode, options: Options, scopes: ScopedOptions
     let mut c = Context::default();
     c.scopes = Rc::new(scopes);
 
-    let mut s = format_impl(
-        dom.syntax().into_node().unwrap(),
-        options.clone(),
-        Context::default(),
-    )
-    .to_string();
+    let mut s = format_impl(dom.syntax().into_node().unwrap(), options.clone(), c).to_string();
 
     s = s.trim_end().into();
 
@@ -275,6 +273,99 @@ pub fn format_with_path_scopes<I: IntoIterator<Item = (String, OptionsIncomplete
     s
 }
 
+/// Formats a DOM root node with given scopes, and returns
+/// the minimal edits that turn the original document into the formatted one.
+///
+/// All the scope keys must be valid glob patterns,
+/// otherwise this function will panic!
+///
+/// **This doesn't check errors of the DOM.**
+pub fn format_edits<I: IntoIterator<Item = (String, OptionsIncomplete)>>(
+    dom: RootNode,
+    options: Options,
+    scopes: I,
+) -> Vec<TextEdit> {
+    let original = dom.syntax().to_string();
+    diff(&original, &format_with_path_scopes(dom, options, scopes))
+}
+
+/// Formats only the entries and tables that enclose the given range,
+/// and returns the minimal edits for them.
+///
+/// The rest of the document is still taken into account,
+/// so the result is the same as formatting the entire document
+/// and keeping only the changes inside the enclosing entries and tables.
+///
+/// All the scope keys must be valid glob patterns,
+/// otherwise this function will panic!
+///
+/// **This doesn't check errors of the DOM.**
+pub fn format_range<I: IntoIterator<Item = (String, OptionsIncomplete)>>(
+    dom: RootNode,
+    range: TextRange,
+    options: Options,
+    scopes: I,
+) -> Vec<TextEdit> {
+    let range = enclosing_range(&dom.syntax().into_node().unwrap(), range);
+
+    format_edits(dom, options, scopes)
+        .into_iter()
+        .filter(|edit| range.contains_range(edit.range))
+        .collect()
+}
+
+/// The range of whole lines of top-level entries and tables
+/// that intersect the given range.
+fn enclosing_range(root: &SyntaxNode, range: TextRange) -> TextRange {
+    let children: Vec<SyntaxElement> = root.children_with_tokens().collect();
+
+    let mut start = range.start();
+    let mut end = range.end();
+
+    for (i, child) in children.iter().enumerate() {
+        let child_range = child.text_range();
+
+        if child_range.start() > range.end() || child_range.end() < range.start() {
+            continue;
+        }
+
+        match child.kind() {
+            ENTRY => {
+                start = cmp::min(start, child_range.start());
+                end = cmp::max(end, child_range.end());
+            }
+            TABLE_HEADER | TABLE_ARRAY_HEADER => {
+                start = cmp::min(start, child_range.start());
+                end = cmp::max(end, child_range.end());
+
+                // The entries of the table.
+                for c in children[i + 1..]
+                    .iter()
+                    .take_while(|c| !matches!(c.kind(), TABLE_HEADER | TABLE_ARRAY_HEADER))
+                    .filter(|c| matches!(c.kind(), ENTRY | COMMENT))
+                {
+                    end = cmp::max(end, c.text_range().end());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let text = root.to_string();
+
+    let line_start = text[..usize::from(start)]
+        .rfind('\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let line_end = text[usize::from(end)..]
+        .find('\n')
+        .map(|i| usize::from(end) + i)
+        .unwrap_or_else(|| text.len());
+
+    TextRange::new((line_start as u32).into(), (line_end as u32).into())
+}
+
 fn format_impl(node: SyntaxNode, options: Options, context: Context) -> String {
     assert!(node.kind() == ROOT);
     let mut formatted = format_root(node, &options, &context);
@@ -16,7 +16,8 @@ syntax tree with additional information and functionality.
 Taplo also exposes a [Value](value::Value) type that can be created from [DOM](dom) nodes
 and can be serialized with [Serde](serde) allowing for conversion to JSON, YAML or any other format.
 
-TOML serialization is currently not implemented, but it is planned with a low priority.
+Rust values (including [Value](value::Value)) can be serialized into formatted TOML documents
+with the [ser] module, and deserialized from DOM trees with the [de] module.
 
 # Features
 
@@ -25,9 +26,9 @@ TOML serialization is currently not implemented, but it is planned with a low pr
 
 `chrono` and `time` are mutually exclusive, if neither is set dates will be treated as strings.
 
-- **serde**: Support for [serde](https://serde.rs)
+- **serde**: Support for [serde](https://serde.rs) and TOML serialization
 - **schema**: Enable schema-related utilities and built-in schemas.
-- **rewrite**: Enable DOM manipulation features (**WIP!**)
+- **rewrite**: Enable comment-preserving editing of documents via the DOM
 
 # Usage
 
@@ -68,6 +69,12 @@ assert_eq!(root_node.errors().len(), 1);
 #[cfg(feature = "serde")]
 mod serde;
 
+#[cfg(feature = "serde")]
+pub mod de;
+
+#[cfg(feature = "serde")]
+pub mod ser;
+
 #[cfg(feature = "verify")]
 pub mod verify;
 
@@ -75,6 +82,7 @@ pub mod verify;
 pub mod schema;
 
 pub mod analytics;
+pub mod code;
 pub mod dom;
 pub mod formatter;
 pub mod parser;
@@ -1,14 +1,15 @@
 //! TOML document to syntax tree parsing.
 
 use crate::{
+    code::Code,
     dom,
-    syntax::{SyntaxKind, SyntaxKind::*, SyntaxNode},
+    syntax::{SyntaxElement, SyntaxKind, SyntaxKind::*, SyntaxNode},
     util::{allowed_chars, check_escape},
 };
 use dom::Cast;
 use logos::{Lexer, Logos};
-use rowan::{GreenNode, GreenNodeBuilder, TextRange, TextSize};
-use std::convert::TryInto;
+use rowan::{GreenNode, GreenNodeBuilder, NodeOrToken, TextRange, TextSize};
+use std::{convert::TryInto, ops::Range};
 
 #[macro_use]
 mod macros;
@@ -19,6 +20,9 @@ pub struct Error {
     /// The span of the error.
     pub range: TextRange,
 
+    /// The stable code of the error.
+    pub code: Code,
+
     /// Human-friendly error message.
     pub message: String,
 }
@@ -102,13 +106,14 @@ impl<'p> Parser<'p> {
         }
     }
 
-    fn error(&mut self, message: &str) -> ParserResult<()> {
+    fn error(&mut self, code: Code, message: &str) -> ParserResult<()> {
         let span = self.lexer.span();
         self.add_error(&Error {
             range: TextRange::new(
                 TextSize::from(span.start as u32),
                 TextSize::from(span.end as u32),
             ),
+            code,
             message: message.into(),
         });
         if let Some(t) = self.current_token {
@@ -120,13 +125,14 @@ impl<'p> Parser<'p> {
     }
 
     // report error without consuming the current the token
-    fn report_error(&mut self, message: &str) -> ParserResult<()> {
+    fn report_error(&mut self, code: Code, message: &str) -> ParserResult<()> {
         let span = self.lexer.span();
         self.add_error(&Error {
             range: TextRange::new(
                 TextSize::from(span.start as u32),
                 TextSize::from(span.end as u32),
             ),
+            code,
             message: message.into(),
         });
         Err(())
@@ -167,7 +173,7 @@ impl<'p> Parser<'p> {
                 if kind == t {
                     self.token()
                 } else {
-                    self.error(message)
+                    self.error(Code::InvalidSyntax, message)
                 }
             }
             Err(_) => {
@@ -176,6 +182,7 @@ impl<'p> Parser<'p> {
                         self.lexer.span().start.try_into().unwrap(),
                         self.lexer.span().end.try_into().unwrap(),
                     ),
+                    code: Code::InvalidSyntax,
                     message: "unexpected EOF".into(),
                 });
                 Err(())
@@ -216,6 +223,7 @@ impl<'p> Parser<'p> {
                                         (self.lexer.span().start + e).try_into().unwrap(),
                                         (self.lexer.span().start + e).try_into().unwrap(),
                                     ),
+                                    code: Code::InvalidCharacter,
                                     message: "invalid character in comment".into(),
                                 });
                             }
@@ -240,6 +248,7 @@ impl<'p> Parser<'p> {
                             span.start.try_into().unwrap(),
                             span.end.try_into().unwrap(),
                         ),
+                        code: Code::InvalidSyntax,
                         message: "unexpected token".into(),
                     })
                 }
@@ -276,7 +285,7 @@ impl<'p> Parser<'p> {
                     }
 
                     if not_newline {
-                        let _ = self.error("expected new line");
+                        let _ = self.error(Code::ExpectedNewline, "expected new line");
                         continue;
                     }
 
@@ -310,7 +319,7 @@ impl<'p> Parser<'p> {
                 }
                 _ => {
                     if not_newline {
-                        let _ = self.error("expected new line");
+                        let _ = self.error(Code::ExpectedNewline, "expected new line");
                         continue;
                     }
                     if entry_started {
@@ -358,7 +367,7 @@ impl<'p> Parser<'p> {
 
     fn parse_key(&mut self) -> ParserResult<()> {
         if self.parse_ident().is_err() {
-            return self.error("expected identifier");
+            return self.error(Code::InvalidSyntax, "expected identifier");
         }
 
         let mut after_period = false;
@@ -369,14 +378,14 @@ impl<'p> Parser<'p> {
                     if !after_period {
                         return Ok(());
                     }
-                    return self.error("unexpected end of input");
+                    return self.error(Code::InvalidSyntax, "unexpected end of input");
                 }
             };
 
             match t {
                 PERIOD => {
                     if after_period {
-                        return self.error(r#"unexpected ".""#);
+                        return self.error(Code::InvalidSyntax, r#"unexpected ".""#);
                     } else {
                         self.token()?;
                         after_period = true;
@@ -386,7 +395,9 @@ impl<'p> Parser<'p> {
                     if after_period {
                         match self.parse_ident() {
                             Ok(_) => {}
-                            Err(_) => return self.error("expected identifier"),
+                            Err(_) => {
+                                return self.error(Code::InvalidSyntax, "expected identifier")
+                            }
                         }
                         after_period = false;
                     } else {
@@ -421,6 +432,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid control character in string literal".into(),
                             });
                         }
@@ -439,6 +451,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid character in string".into(),
                             });
                         }
@@ -454,6 +467,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidEscape,
                                 message: "invalid escape sequence".into(),
                             });
                         }
@@ -483,42 +497,42 @@ impl<'p> Parser<'p> {
             BOOL => {
                 self.token_as(IDENT)
             }
-            _ => self.error("expected identifier"),
+            _ => self.error(Code::InvalidSyntax, "expected identifier"),
         }
     }
 
     fn parse_value(&mut self) -> ParserResult<()> {
         let t = match self.get_token() {
             Ok(t) => t,
-            Err(_) => return self.error("expected value"),
+            Err(_) => return self.error(Code::InvalidSyntax, "expected value"),
         };
 
         match t {
             BOOL | DATE => self.token(),
             INTEGER_BIN => {
                 if !check_underscores(self.lexer.slice(), 2) {
-                    self.error("invalid underscores")
+                    self.error(Code::InvalidNumber, "invalid underscores")
                 } else {
                     self.token()
                 }
             }
             INTEGER_HEX => {
                 if !check_underscores(self.lexer.slice(), 16) {
-                    self.error("invalid underscores")
+                    self.error(Code::InvalidNumber, "invalid underscores")
                 } else {
                     self.token()
                 }
             }
             INTEGER_OCT => {
                 if !check_underscores(self.lexer.slice(), 8) {
-                    self.error("invalid underscores")
+                    self.error(Code::InvalidNumber, "invalid underscores")
                 } else {
                     self.token()
                 }
             }
             FLOAT => {
                 if !check_underscores(self.lexer.slice(), 10) {
-                    self.error("invalid underscores")
+                    self.error(Code::InvalidNumber, "invalid underscores")
                 } else {
                     self.token()
                 }
@@ -533,6 +547,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid control character in string literal".into(),
                             });
                         }
@@ -550,6 +565,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid character in string".into(),
                             });
                         }
@@ -567,6 +583,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid character in string".into(),
                             });
                         }
@@ -582,6 +599,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidEscape,
                                 message: "invalid escape sequence".into(),
                             });
                         }
@@ -603,6 +621,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidCharacter,
                                 message: "invalid character in string".into(),
                             });
                         }
@@ -618,6 +637,7 @@ impl<'p> Parser<'p> {
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                     (self.lexer.span().start + e).try_into().unwrap(),
                                 ),
+                                code: Code::InvalidEscape,
                                 message: "invalid escape sequence".into(),
                             });
                         }
@@ -635,9 +655,9 @@ impl<'p> Parser<'p> {
                     || (self.lexer.slice().starts_with("+0") && self.lexer.slice() != "+0")
                     || (self.lexer.slice().starts_with("-0") && self.lexer.slice() != "-0")
                 {
-                    self.error("zero-padded integers are not allowed")
+                    self.error(Code::InvalidNumber, "zero-padded integers are not allowed")
                 } else if !check_underscores(self.lexer.slice(), 10) {
-                    self.error("invalid underscores")
+                    self.error(Code::InvalidNumber, "invalid underscores")
                 } else {
                     self.token()
                 }
@@ -646,10 +666,11 @@ impl<'p> Parser<'p> {
             BRACE_START => with_node!(self.builder, INLINE_TABLE, self.parse_inline_table()),
             IDENT => {
                 // FIXME(bit_flags): This branch is just a workaround.
-                self.report_error("expected value").ok();
+                self.report_error(Code::InvalidSyntax, "expected value")
+                    .ok();
                 self.token_as(ERROR)
             }
-            _ => self.error("expected value"),
+            _ => self.error(Code::InvalidSyntax, "expected value"),
         }
     }
 
@@ -662,7 +683,7 @@ impl<'p> Parser<'p> {
         loop {
             let t = match self.get_token() {
                 Ok(t) => t,
-                Err(_) => return self.report_error(r#"expected "}""#),
+                Err(_) => return self.report_error(Code::InvalidSyntax, r#"expected "}""#),
             };
 
             match t {
@@ -671,7 +692,10 @@ impl<'p> Parser<'p> {
                         // it is still reported as a syntax error,
                         // but we can still analyze it as if it was a valid
                         // table.
-                        let _ = self.report_error("expected value, trailing comma is not allowed");
+                        let _ = self.report_error(
+                            Code::TrailingComma,
+                            "expected value, trailing comma is not allowed",
+                        );
                     }
                     break self.token()?;
                 }
@@ -682,12 +706,15 @@ impl<'p> Parser<'p> {
                         break;
                     }
 
-                    let _ = self.error("newline is not allowed in an inline table");
+                    let _ = self.error(
+                        Code::NewlineInInlineTable,
+                        "newline is not allowed in an inline table",
+                    );
                     was_newline = true;
                 }
                 COMMA => {
                     if first {
-                        let _ = self.error(r#"unexpected ",""#);
+                        let _ = self.error(Code::InvalidSyntax, r#"unexpected ",""#);
                     } else {
                         self.token()?;
                     }
@@ -697,7 +724,7 @@ impl<'p> Parser<'p> {
                 _ => {
                     was_newline = false;
                     if !comma_last && !first {
-                        let _ = self.error(r#"expected ",""#);
+                        let _ = self.error(Code::InvalidSyntax, r#"expected ",""#);
                     }
                     let _ = whitelisted!(
                         self,
@@ -729,14 +756,14 @@ impl<'p> Parser<'p> {
                 }
                 COMMA => {
                     if first || comma_last {
-                        let _ = self.error(r#"unexpected ",""#);
+                        let _ = self.error(Code::InvalidSyntax, r#"unexpected ",""#);
                     }
                     self.token()?;
                     comma_last = true;
                 }
                 _ => {
                     if !comma_last && !first {
-                        let _ = self.error(r#"expected ",""#);
+                        let _ = self.error(Code::InvalidSyntax, r#"expected ",""#);
                     }
                     let _ = whitelisted!(
                         self,
@@ -795,4 +822,124 @@ impl Parse {
     pub fn into_dom(self) -> dom::RootNode {
         dom::RootNode::cast(rowan::NodeOrToken::Node(self.into_syntax())).unwrap()
     }
+
+    /// Replace the given range of the parsed source with `text`,
+    /// and parse the result again.
+    ///
+    /// Only the top-level lines touched by the change are parsed,
+    /// the green subtrees of all other entries and tables are reused
+    /// as they are.
+    ///
+    /// If the change cannot be applied this way (e.g. the edited lines
+    /// contain an unterminated array or string), the entire new source
+    /// is parsed instead, so the result is always the same as if
+    /// the edited source was [parsed](parse) from scratch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or is not on a
+    /// character boundary.
+    pub fn reparse(&self, range: TextRange, text: &str) -> Parse {
+        let root = SyntaxNode::new_root(self.green_node.clone());
+
+        let reparse_all = || {
+            let mut source = root.text().to_string();
+            source.replace_range(Range::<usize>::from(range), text);
+            parse(&source)
+        };
+
+        let children: Vec<SyntaxElement> = root.children_with_tokens().collect();
+
+        // The parser starts from a clean state after every
+        // top-level newline, so the lines between the last newline
+        // before the change and the first one after it can be
+        // parsed on their own.
+        let first = children
+            .iter()
+            .rposition(|c| c.kind() == NEWLINE && c.text_range().end() < range.start())
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+
+        let last = children
+            .iter()
+            .enumerate()
+            .skip(first)
+            .find(|(_, c)| c.kind() == NEWLINE && c.text_range().start() >= range.end())
+            .map(|(idx, _)| idx + 1)
+            .unwrap_or_else(|| children.len());
+
+        let old_start = children
+            .get(first)
+            .map(|c| c.text_range().start())
+            .unwrap_or_else(|| root.text_range().end());
+
+        let old_end = if last == children.len() {
+            root.text_range().end()
+        } else {
+            children[last - 1].text_range().end()
+        };
+
+        // Only the text of the reparsed lines is needed.
+        let mut segment_source = String::with_capacity(usize::from(old_end - old_start));
+        for c in &children[first..last] {
+            match c {
+                NodeOrToken::Node(n) => n.text().for_each_chunk(|s| segment_source.push_str(s)),
+                NodeOrToken::Token(t) => segment_source.push_str(t.text()),
+            }
+        }
+        segment_source.replace_range(Range::<usize>::from(range - old_start), text);
+
+        let new_end = old_end - range.len() + TextSize::of(text);
+
+        let segment = Parser::new(&segment_source).parse();
+
+        // Errors could mean that the edited lines would have
+        // been lexed or parsed differently with the rest of the
+        // document present, we cannot rely on them.
+        if !segment.errors.is_empty() {
+            return reparse_all();
+        }
+
+        if last != children.len()
+            && segment.green_node.children().last().map(|c| c.kind()) != Some(NEWLINE.into())
+        {
+            return reparse_all();
+        }
+
+        let errors = self
+            .errors
+            .iter()
+            .filter_map(|err| {
+                // Errors at the very start or end of the document
+                // (e.g. unexpected EOF) belong to the reparsed lines.
+                if err.range.end() < old_start || (err.range.end() == old_start && first != 0) {
+                    Some(err.clone())
+                } else if err.range.start() > old_end
+                    || (err.range.start() == old_end && last != children.len())
+                {
+                    Some(Error {
+                        range: TextRange::new(
+                            err.range.start() - old_end + new_end,
+                            err.range.end() - old_end + new_end,
+                        ),
+                        code: err.code,
+                        message: err.message.clone(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Parse {
+            green_node: self.green_node.splice_children(
+                first..last,
+                segment.green_node.children().map(|c| match c {
+                    NodeOrToken::Node(n) => NodeOrToken::Node(n.to_owned()),
+                    NodeOrToken::Token(t) => NodeOrToken::Token(t.to_owned()),
+                }),
+            ),
+            errors,
+        }
+    }
 }
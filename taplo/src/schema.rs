@@ -68,9 +68,17 @@ pub const EXTENSION_KEY: &str = "x-taplo";
 /// The scheme of the built-in schemas.
 pub const BUILTIN_SCHEME: &str = "taplo";
 
+mod drafts;
+
+pub mod builtin;
+pub mod bundle;
+
+#[cfg(feature = "rewrite")]
+pub mod fix;
+
 pub mod util {
     use schemars::{
-        schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec},
+        schema::{InstanceType, ObjectValidation, RootSchema, Schema, SchemaObject, SingleOrVec},
         Map,
     };
     use serde_crate::{Deserialize, Serialize};
@@ -422,6 +430,155 @@ pub mod util {
         }
     }
 
+    /// Deserializes a JSON schema.
+    ///
+    /// Besides draft-07, schemas of drafts 2019-09 and 2020-12 are accepted,
+    /// their keywords are converted to draft-07 equivalents where possible.
+    pub fn schema_from_value(
+        mut value: serde_json::Value,
+    ) -> Result<RootSchema, serde_json::Error> {
+        super::drafts::upgrade(&mut value);
+        serde_json::from_value(value)
+    }
+
+    /// Deserializes a JSON schema from bytes, see [schema_from_value].
+    pub fn schema_from_slice(bytes: &[u8]) -> Result<RootSchema, serde_json::Error> {
+        schema_from_value(serde_json::from_slice(bytes)?)
+    }
+
+    /// Attaches a schema to the values at the given dotted keys,
+    /// the values are validated against both the new and the existing schemas.
+    ///
+    /// Keys can contain `*` and `?` glob patterns to match any key at that level.
+    ///
+    /// The definitions of the attached schema are moved
+    /// to the definitions of the root schema under new names.
+    /// Other local references (e.g. `#/properties/name`) are
+    /// moved to definitions first.
+    pub fn attach_schema(root: &mut RootSchema, keys: &str, schema: RootSchema) {
+        let schema = serde_json::to_value(&schema)
+            .ok()
+            .and_then(|value| schema_from_value(value).ok())
+            .unwrap_or(schema);
+
+        let name = (0..)
+            .map(|n| format!("{}-keys-{}", EXTENSION_KEY, n))
+            .find(|name| !root.definitions.contains_key(name))
+            .unwrap();
+
+        root.definitions.insert(
+            name.clone(),
+            with_renamed_refs(Schema::Object(schema.schema), &name),
+        );
+
+        for (def_name, def) in schema.definitions {
+            root.definitions.insert(
+                format!("{}::{}", name, def_name),
+                with_renamed_refs(def, &name),
+            );
+        }
+
+        let mut attached = Schema::Object(SchemaObject {
+            reference: Some(format!("#/definitions/{}", name)),
+            ..Default::default()
+        });
+
+        for key in split_keys(keys).into_iter().rev() {
+            let mut object = ObjectValidation::default();
+
+            if key.contains(&['*', '?'][..]) {
+                object
+                    .pattern_properties
+                    .insert(glob_to_regex(&key), attached);
+            } else {
+                object.properties.insert(key, attached);
+            }
+
+            attached = Schema::Object(SchemaObject {
+                object: Some(Box::new(object)),
+                ..Default::default()
+            });
+        }
+
+        root.schema
+            .subschemas()
+            .all_of
+            .get_or_insert_with(Vec::new)
+            .push(attached);
+    }
+
+    /// Points the local references of an attached schema
+    /// to the renamed definitions.
+    fn with_renamed_refs(schema: Schema, name: &str) -> Schema {
+        fn rename(value: &mut serde_json::Value, name: &str) {
+            match value {
+                serde_json::Value::Object(obj) => {
+                    for (key, value) in obj.iter_mut() {
+                        match value {
+                            serde_json::Value::String(rf) if key == "$ref" => {
+                                if rf == "#" {
+                                    *rf = format!("#/definitions/{}", name);
+                                } else if let Some(def) = local_definition(rf) {
+                                    *rf = format!("#/definitions/{}::{}", name, def);
+                                }
+                            }
+                            value => rename(value, name),
+                        }
+                    }
+                }
+                serde_json::Value::Array(values) => {
+                    for value in values {
+                        rename(value, name);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut value = match serde_json::to_value(&schema) {
+            Ok(v) => v,
+            Err(_) => return schema,
+        };
+
+        rename(&mut value, name);
+
+        serde_json::from_value(value).unwrap_or(schema)
+    }
+
+    /// Splits dotted keys, dots in quoted keys are not separators.
+    fn split_keys(keys: &str) -> Vec<String> {
+        let mut split = Vec::new();
+        let mut key = String::new();
+        let mut quote = None;
+
+        for c in keys.chars() {
+            match (c, quote) {
+                ('"', None) | ('\'', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                ('.', None) => split.push(std::mem::take(&mut key)),
+                (c, _) => key.push(c),
+            }
+        }
+
+        split.push(key);
+        split.into_iter().map(|k| k.trim().to_string()).collect()
+    }
+
+    fn glob_to_regex(glob: &str) -> String {
+        let mut re = String::from("^");
+
+        for c in glob.chars() {
+            match c {
+                '*' => re += ".*",
+                '?' => re.push('.'),
+                c => re += &regex::escape(&c.to_string()),
+            }
+        }
+
+        re.push('$');
+        re
+    }
+
     pub fn local_definition(rf: &str) -> Option<&str> {
         if rf.starts_with("#/definitions/") {
             Some(rf.trim_start_matches("#/definitions/"))
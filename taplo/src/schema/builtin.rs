@@ -0,0 +1,92 @@
+//! Schemas shipped with Taplo.
+//!
+//! The schemas are available without network access under the
+//! [BUILTIN_SCHEME], e.g. `taplo://cargo.toml`, and are associated
+//! with documents by the patterns in their `x-taplo-info`.
+
+use super::{util::schema_from_slice, SchemaExtraInfo, BUILTIN_SCHEME};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::schema::RootSchema;
+
+/// The key of the schema information in the built-in schemas.
+const INFO_KEY: &str = "x-taplo-info";
+
+/// A schema shipped with Taplo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinSchema {
+    /// The name of the schema, its URL is `taplo://<name>`.
+    pub name: &'static str,
+    source: &'static str,
+}
+
+impl BuiltinSchema {
+    /// The URL of the schema.
+    pub fn url(&self) -> String {
+        format!("{}://{}", BUILTIN_SCHEME, self.name)
+    }
+
+    /// Parses the schema.
+    pub fn schema(&self) -> RootSchema {
+        schema_from_slice(self.source.as_bytes()).expect("invalid built-in schema")
+    }
+
+    /// The authors, the version and the patterns of the schema.
+    pub fn info(&self) -> SchemaExtraInfo {
+        serde_json::from_str::<serde_json::Value>(self.source)
+            .ok()
+            .and_then(|mut schema| schema.get_mut(INFO_KEY).map(serde_json::Value::take))
+            .and_then(|info| serde_json::from_value(info).ok())
+            .unwrap_or_default()
+    }
+}
+
+macro_rules! builtin_schemas {
+    ($($name:literal),* $(,)?) => {
+        /// All the built-in schemas.
+        pub const BUILTIN_SCHEMAS: &[BuiltinSchema] = &[
+            $(
+                BuiltinSchema {
+                    name: $name,
+                    source: include_str!(concat!("../../schemas/", $name, ".json")),
+                },
+            )*
+        ];
+    };
+}
+
+builtin_schemas! {
+    "cargo.toml",
+    "dein.toml",
+    "makefile.toml",
+    "pyproject.toml",
+    "rustfmt.toml",
+}
+
+static ASSOCIATIONS: Lazy<Vec<(Regex, BuiltinSchema)>> = Lazy::new(|| {
+    BUILTIN_SCHEMAS
+        .iter()
+        .flat_map(|schema| {
+            schema
+                .info()
+                .patterns
+                .into_iter()
+                .filter_map(move |pattern| Regex::new(&pattern).ok().map(|re| (re, *schema)))
+        })
+        .collect()
+});
+
+/// Returns the built-in schema with the given URL, e.g. `taplo://cargo.toml`.
+pub fn builtin_schema(url: &str) -> Option<BuiltinSchema> {
+    let name = url.strip_prefix(BUILTIN_SCHEME)?.strip_prefix("://")?;
+    BUILTIN_SCHEMAS.iter().find(|s| s.name == name).copied()
+}
+
+/// Returns the built-in schema associated with
+/// the document at the given path or URL.
+pub fn associated_schema(path: &str) -> Option<BuiltinSchema> {
+    ASSOCIATIONS
+        .iter()
+        .find(|(re, _)| re.is_match(path))
+        .map(|(_, schema)| *schema)
+}
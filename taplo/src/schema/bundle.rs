@@ -0,0 +1,207 @@
+//! Schemas split across multiple documents.
+//!
+//! The schema utilities and validation only resolve references
+//! to definitions within the same schema, so documents referenced
+//! with `$ref` (relative paths or URLs) are included in the schema
+//! as definitions with their URLs as `$id`.
+//!
+//! The documents are retrieved by the caller:
+//!
+//! ```ignore
+//! let mut bundle = SchemaBundle::new(url, schema);
+//!
+//! loop {
+//!     let missing = bundle.missing_documents();
+//!
+//!     if missing.is_empty() {
+//!         break;
+//!     }
+//!
+//!     for url in missing {
+//!         match load_schema(&url) {
+//!             Ok(schema) => bundle.add_document(&url, schema),
+//!             Err(_) => bundle.skip_document(&url),
+//!         }
+//!     }
+//! }
+//!
+//! let schema = bundle.into_schema()?;
+//! ```
+
+use super::drafts::{child_keywords, resolve_uri, without_fragment};
+use schemars::schema::RootSchema;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// A schema along with the documents it references.
+#[derive(Debug, Clone)]
+pub struct SchemaBundle {
+    url: String,
+    schema: Value,
+    /// Documents that were added, or could not be retrieved.
+    documents: HashSet<String>,
+}
+
+impl SchemaBundle {
+    /// Creates a bundle for a schema retrieved from the given URL
+    /// or absolute path, relative references are resolved against it.
+    ///
+    /// Paths must use `/` as the separator.
+    pub fn new(url: &str, schema: RootSchema) -> Self {
+        let mut schema = to_value(schema);
+
+        if let Some(obj) = schema.as_object_mut() {
+            if !url.is_empty() {
+                obj.entry("$id")
+                    .or_insert_with(|| Value::String(url.into()));
+            }
+        }
+
+        Self {
+            url: url.into(),
+            schema,
+            documents: HashSet::new(),
+        }
+    }
+
+    /// The URL or path of the schema.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The absolute URLs or paths of the documents that are referenced
+    /// in the bundle but were neither added nor skipped.
+    pub fn missing_documents(&self) -> Vec<String> {
+        let mut ids = HashSet::new();
+        let mut references = Vec::new();
+        collect_references(&self.schema, "", &mut ids, &mut references, true);
+
+        let mut missing = Vec::new();
+
+        for document in references {
+            if !document.is_empty()
+                && !ids.contains(&document)
+                && !self.documents.contains(&document)
+                && !missing.contains(&document)
+            {
+                missing.push(document);
+            }
+        }
+
+        missing
+    }
+
+    /// Adds a referenced document.
+    pub fn add_document(&mut self, url: &str, schema: RootSchema) {
+        self.documents.insert(url.into());
+
+        let mut document = to_value(schema);
+
+        if let Some(obj) = document.as_object_mut() {
+            // The document is identified by where it was retrieved from.
+            obj.insert("$id".into(), Value::String(url.into()));
+            obj.remove("$schema");
+        }
+
+        let definitions = self.schema.as_object_mut().and_then(|root| {
+            root.entry("definitions")
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+        });
+
+        if let Some(definitions) = definitions {
+            let name = definition_name(url);
+            let name = (0..)
+                .map(|n| match n {
+                    0 => name.clone(),
+                    n => format!("{}-{}", name, n),
+                })
+                .find(|name| !definitions.contains_key(name))
+                .unwrap();
+
+            definitions.insert(name, document);
+        }
+    }
+
+    /// Skips a referenced document that could not be retrieved,
+    /// the references to it are left unresolved.
+    pub fn skip_document(&mut self, url: &str) {
+        self.documents.insert(url.into());
+    }
+
+    /// Returns the schema with the references
+    /// to the added documents resolved.
+    pub fn into_schema(self) -> Result<RootSchema, serde_json::Error> {
+        super::util::schema_from_value(self.schema)
+    }
+}
+
+fn to_value(schema: RootSchema) -> Value {
+    serde_json::to_value(schema).unwrap_or_else(|_| Value::Object(Map::new()))
+}
+
+/// Collects the schema resources and the documents of all the references.
+fn collect_references(
+    value: &Value,
+    base: &str,
+    ids: &mut HashSet<String>,
+    references: &mut Vec<String>,
+    keywords: bool,
+) {
+    match value {
+        Value::Object(obj) if !keywords => {
+            for value in obj.values() {
+                collect_references(value, base, ids, references, true);
+            }
+        }
+        Value::Object(obj) => {
+            let mut base = base.to_string();
+
+            if let Some(id) = obj.get("$id").and_then(Value::as_str) {
+                if !id.starts_with('#') {
+                    base = without_fragment(&resolve_uri(&base, id)).to_string();
+                    ids.insert(base.clone());
+                }
+            }
+
+            if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+                references.push(without_fragment(&resolve_uri(&base, reference)).to_string());
+            }
+
+            for (key, value) in obj {
+                if let Some(keywords) = child_keywords(key) {
+                    collect_references(value, &base, ids, references, keywords);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_references(item, base, ids, references, true);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A definition name from the last segment of the URL,
+/// without characters that would have to be escaped in references.
+fn definition_name(url: &str) -> String {
+    let name = without_fragment(url)
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+
+    let name = name.strip_suffix(".json").unwrap_or(name);
+
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '~' | '%' | '#' | '?'))
+        .collect();
+
+    if name.is_empty() {
+        "document".into()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,708 @@
+//! Conversion of newer JSON Schema drafts to draft-07.
+//!
+//! Validation and the schema utilities only understand draft-07,
+//! so keywords of drafts 2019-09 and 2020-12 are rewritten
+//! to their draft-07 equivalents before a schema is deserialized:
+//!
+//! - `$defs`, `$anchor` and JSON pointer references are moved to `definitions`,
+//! - `$ref` to other resources (`$id`) bundled in the same document are resolved,
+//! - `$recursiveRef` and `$dynamicRef` become plain references,
+//! - `prefixItems` and `items` become `items` and `additionalItems`,
+//! - `dependentRequired` and `dependentSchemas` become subschemas,
+//! - `unevaluatedProperties` and `unevaluatedItems` become
+//!   `additionalProperties` and `additionalItems`.
+//!
+//! References to documents that are not part of the schema are left unchanged,
+//! see [SchemaBundle](super::bundle::SchemaBundle) for including them.
+
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Keywords with values that are not schemas.
+const DATA_KEYWORDS: &[&str] = &["enum", "const", "default", "examples"];
+
+/// Keywords with subschemas by property or definition names.
+const SCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "definitions",
+    "$defs",
+    "dependencies",
+    "dependentSchemas",
+];
+
+/// Keywords that only describe a schema.
+const ANNOTATION_KEYWORDS: &[&str] = &[
+    "$id",
+    "$schema",
+    "$anchor",
+    "$dynamicAnchor",
+    "$recursiveAnchor",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+/// Keywords with subschemas that apply to the same value.
+const IN_PLACE_KEYWORDS: &[&str] = &["allOf", "anyOf", "oneOf", "if", "then", "else"];
+
+pub(super) fn upgrade(schema: &mut Value) {
+    if !schema.is_object() {
+        return;
+    }
+
+    let newer_draft = schema
+        .get("$schema")
+        .and_then(Value::as_str)
+        .map(|s| s.contains("2019-09") || s.contains("2020-12"))
+        .unwrap_or(false);
+
+    move_defs(schema);
+
+    let mut resources = Resources::default();
+    resources.collect(schema, String::new(), "", true);
+
+    let original = schema.clone();
+    let mut targets = Targets::new(schema);
+    rewrite_refs(schema, "", &original, &resources, &mut targets, true);
+
+    for (pointer, name) in targets.hoisted {
+        let target = match pointer.as_str() {
+            "" => {
+                let mut root = schema.clone();
+                if let Some(root) = root.as_object_mut() {
+                    root.remove("definitions");
+                    root.remove("$defs");
+                }
+                root
+            }
+            pointer => match schema.pointer(pointer) {
+                Some(target) => target.clone(),
+                None => continue,
+            },
+        };
+
+        let definitions = schema.as_object_mut().and_then(|root| {
+            root.entry("definitions")
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+        });
+
+        if let Some(definitions) = definitions {
+            definitions.insert(name, target);
+        }
+    }
+
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    convert_keywords(schema, &definitions, newer_draft, true);
+}
+
+/// Moves the `$defs` of the root to `definitions`, so that they
+/// are kept even if the document itself does not reference them.
+///
+/// References to them from other documents are
+/// resolved with [resolve_pointer].
+fn move_defs(schema: &mut Value) {
+    let root = match schema.as_object_mut() {
+        Some(root) => root,
+        None => return,
+    };
+
+    let mut defs = match root.remove("$defs") {
+        Some(Value::Object(defs)) => defs,
+        Some(defs) => {
+            root.insert("$defs".into(), defs);
+            return;
+        }
+        None => return,
+    };
+
+    if let Some(definitions) = root
+        .entry("definitions")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+    {
+        let names: Vec<String> = defs.keys().cloned().collect();
+
+        for name in names {
+            if !definitions.contains_key(&name) {
+                if let Some(def) = defs.remove(&name) {
+                    definitions.insert(name, def);
+                }
+            }
+        }
+    }
+
+    // Conflicting names are hoisted as usual if they are referenced.
+    if !defs.is_empty() {
+        root.insert("$defs".into(), Value::Object(defs));
+    }
+}
+
+/// The pointer if it exists in the document, `$defs` that were
+/// moved to `definitions` are found under their new pointers.
+fn resolve_pointer(document: &Value, pointer: String) -> Option<String> {
+    if document.pointer(&pointer).is_some() {
+        return Some(pointer);
+    }
+
+    let moved = pointer.replace("/$defs/", "/definitions/");
+
+    if moved != pointer && document.pointer(&moved).is_some() {
+        Some(moved)
+    } else {
+        None
+    }
+}
+
+/// Whether the keys of the value of a keyword are keywords as well,
+/// [`None`] if the value is not a schema.
+///
+/// The keys of `properties`, `$defs` and the like are names instead,
+/// so a property called `default` is still a schema.
+pub(super) fn child_keywords(key: &str) -> Option<bool> {
+    if DATA_KEYWORDS.contains(&key) {
+        None
+    } else {
+        Some(!SCHEMA_MAP_KEYWORDS.contains(&key))
+    }
+}
+
+/// Schema resources and anchors by their absolute URIs,
+/// the values are JSON pointers within the document.
+#[derive(Default)]
+struct Resources {
+    ids: HashMap<String, String>,
+    anchors: HashMap<String, String>,
+}
+
+impl Resources {
+    fn collect(&mut self, value: &Value, pointer: String, base: &str, keywords: bool) {
+        match value {
+            Value::Object(obj) if !keywords => {
+                for (key, value) in obj {
+                    self.collect(value, format!("{}/{}", pointer, escape(key)), base, true);
+                }
+            }
+            Value::Object(obj) => {
+                let mut base = base.to_string();
+
+                if let Some(id) = obj.get("$id").and_then(Value::as_str) {
+                    if let Some(anchor) = id.strip_prefix('#') {
+                        // Draft-07 style anchor.
+                        self.anchors
+                            .insert(format!("{}#{}", base, anchor), pointer.clone());
+                    } else {
+                        base = without_fragment(&resolve_uri(&base, id)).to_string();
+                        self.ids
+                            .entry(base.clone())
+                            .or_insert_with(|| pointer.clone());
+                    }
+                }
+
+                for keyword in &["$anchor", "$dynamicAnchor"] {
+                    if let Some(anchor) = obj.get(*keyword).and_then(Value::as_str) {
+                        self.anchors
+                            .entry(format!("{}#{}", base, anchor))
+                            .or_insert_with(|| pointer.clone());
+                    }
+                }
+
+                for (key, value) in obj {
+                    if let Some(keywords) = child_keywords(key) {
+                        self.collect(
+                            value,
+                            format!("{}/{}", pointer, escape(key)),
+                            &base,
+                            keywords,
+                        );
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.collect(item, format!("{}/{}", pointer, i), base, true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The JSON pointer of the target of a reference,
+    /// `None` if it is in a different document.
+    fn target(&self, base: &str, reference: &str) -> Option<String> {
+        let uri = resolve_uri(base, reference);
+
+        let (document, fragment) = match uri.find('#') {
+            Some(i) => (&uri[..i], &uri[i + 1..]),
+            None => (uri.as_str(), ""),
+        };
+
+        let resource = self.ids.get(document).cloned().or_else(|| {
+            if document.is_empty() {
+                Some(String::new())
+            } else {
+                None
+            }
+        })?;
+
+        let fragment = percent_decode(fragment);
+
+        if fragment.is_empty() {
+            Some(resource)
+        } else if fragment.starts_with('/') {
+            Some(resource + &fragment)
+        } else {
+            self.anchors
+                .get(&format!("{}#{}", document, fragment))
+                .cloned()
+        }
+    }
+}
+
+/// Definitions created for the targets of references.
+struct Targets {
+    /// The definitions of the schema.
+    existing: HashSet<String>,
+    /// The existing and the new definitions.
+    definitions: HashSet<String>,
+    /// Pointers that are copied to definitions with the given names.
+    hoisted: Vec<(String, String)>,
+}
+
+impl Targets {
+    fn new(schema: &Value) -> Self {
+        let existing: HashSet<String> = schema
+            .get("definitions")
+            .and_then(Value::as_object)
+            .map(|defs| defs.keys().cloned().collect())
+            .unwrap_or_default();
+
+        Self {
+            definitions: existing.clone(),
+            existing,
+            hoisted: Vec::new(),
+        }
+    }
+
+    /// Returns the name of the definition for the target.
+    fn definition(&mut self, pointer: &str) -> String {
+        let segments: Vec<String> = pointer.split('/').skip(1).map(unescape).collect();
+
+        if let [keyword, name] = segments.as_slice() {
+            if keyword == "definitions" && self.existing.contains(name) {
+                return name.clone();
+            }
+        }
+
+        if let Some((_, name)) = self.hoisted.iter().find(|(p, _)| p == pointer) {
+            return name.clone();
+        }
+
+        let preferred = match segments.as_slice() {
+            [] => "root".to_string(),
+            [keyword, name] if keyword == "$defs" => name.clone(),
+            _ => segments.join("."),
+        };
+
+        let name = (0..)
+            .map(|n| match n {
+                0 => preferred.clone(),
+                n => format!("{}-{}", preferred, n),
+            })
+            .find(|name| !self.definitions.contains(name))
+            .unwrap();
+
+        self.definitions.insert(name.clone());
+        self.hoisted.push((pointer.to_string(), name.clone()));
+        name
+    }
+}
+
+/// Replaces references within the document with
+/// references to definitions.
+///
+/// References to targets that do not exist in the document
+/// are left unchanged.
+fn rewrite_refs(
+    value: &mut Value,
+    base: &str,
+    document: &Value,
+    resources: &Resources,
+    targets: &mut Targets,
+    keywords: bool,
+) {
+    match value {
+        Value::Object(obj) if !keywords => {
+            for value in obj.values_mut() {
+                rewrite_refs(value, base, document, resources, targets, true);
+            }
+        }
+        Value::Object(obj) => {
+            let mut base = base.to_string();
+
+            if let Some(id) = obj.get("$id").and_then(Value::as_str) {
+                if !id.starts_with('#') {
+                    base = without_fragment(&resolve_uri(&base, id)).to_string();
+                }
+            }
+
+            for keyword in &["$recursiveRef", "$dynamicRef"] {
+                if let Some(reference) = obj.remove(*keyword) {
+                    obj.entry("$ref").or_insert(reference);
+                }
+            }
+
+            let target = obj
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| resources.target(&base, reference))
+                .and_then(|target| resolve_pointer(document, target));
+
+            if let Some(target) = target {
+                let name = targets.definition(&target);
+                obj.insert(
+                    "$ref".into(),
+                    Value::String(format!("#/definitions/{}", escape(&name))),
+                );
+            }
+
+            for (key, value) in obj.iter_mut() {
+                if let Some(keywords) = child_keywords(key) {
+                    rewrite_refs(value, &base, document, resources, targets, keywords);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                rewrite_refs(item, base, document, resources, targets, true);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn convert_keywords(
+    value: &mut Value,
+    definitions: &Map<String, Value>,
+    newer_draft: bool,
+    keywords: bool,
+) {
+    match value {
+        Value::Object(obj) if !keywords => {
+            for value in obj.values_mut() {
+                convert_keywords(value, definitions, newer_draft, true);
+            }
+        }
+        Value::Object(obj) => {
+            convert_schema(obj, definitions, newer_draft);
+
+            for (key, value) in obj.iter_mut() {
+                if let Some(keywords) = child_keywords(key) {
+                    convert_keywords(value, definitions, newer_draft, keywords);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                convert_keywords(item, definitions, newer_draft, true);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn convert_schema(
+    obj: &mut Map<String, Value>,
+    definitions: &Map<String, Value>,
+    newer_draft: bool,
+) {
+    obj.remove("$defs");
+
+    if let Some(prefix_items) = obj.remove("prefixItems") {
+        if let Some(items) = obj.remove("items") {
+            obj.insert("additionalItems".into(), items);
+        }
+        obj.insert("items".into(), prefix_items);
+    }
+
+    if let Some(unevaluated_items) = obj.remove("unevaluatedItems") {
+        if obj.get("items").map(Value::is_array).unwrap_or(false)
+            && !obj.contains_key("additionalItems")
+        {
+            obj.insert("additionalItems".into(), unevaluated_items);
+        }
+    }
+
+    let mut all_of = Vec::new();
+
+    if let Some(Value::Object(dependent_required)) = obj.remove("dependentRequired") {
+        for (property, required) in dependent_required {
+            all_of.push(dependency(
+                property,
+                serde_json::json!({ "required": required }),
+            ));
+        }
+    }
+
+    if let Some(Value::Object(dependent_schemas)) = obj.remove("dependentSchemas") {
+        for (property, schema) in dependent_schemas {
+            all_of.push(dependency(property, schema));
+        }
+    }
+
+    if let Some(unevaluated) = obj.remove("unevaluatedProperties") {
+        if !obj.contains_key("additionalProperties") {
+            let mut properties = HashSet::new();
+            let mut patterns = HashSet::new();
+            let mut visited = HashSet::new();
+            evaluated_properties(
+                obj,
+                definitions,
+                &mut properties,
+                &mut patterns,
+                &mut visited,
+            );
+
+            // The properties of subschemas are allowed here as well,
+            // they are still validated by the subschemas.
+            let own_properties = obj
+                .entry("properties")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Some(own_properties) = own_properties.as_object_mut() {
+                for property in properties {
+                    own_properties.entry(property).or_insert(Value::Bool(true));
+                }
+            }
+
+            if !patterns.is_empty() {
+                let own_patterns = obj
+                    .entry("patternProperties")
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if let Some(own_patterns) = own_patterns.as_object_mut() {
+                    for pattern in patterns {
+                        own_patterns.entry(pattern).or_insert(Value::Bool(true));
+                    }
+                }
+            }
+
+            obj.insert("additionalProperties".into(), unevaluated);
+        }
+    }
+
+    // References can have other keywords next to them since 2019-09,
+    // they are ignored in draft-07 unless the reference is a subschema.
+    if newer_draft
+        && obj.contains_key("$ref")
+        && obj.keys().any(|k| {
+            k != "$ref" && !ANNOTATION_KEYWORDS.contains(&k.as_str()) && !k.starts_with("x-")
+        })
+    {
+        if let Some(reference) = obj.remove("$ref") {
+            all_of.push(serde_json::json!({ "$ref": reference }));
+        }
+    }
+
+    if !all_of.is_empty() {
+        match obj.get_mut("allOf") {
+            Some(Value::Array(existing)) => existing.extend(all_of),
+            _ => {
+                obj.insert("allOf".into(), Value::Array(all_of));
+            }
+        }
+    }
+}
+
+/// A schema that only applies if the property is present.
+fn dependency(property: String, schema: Value) -> Value {
+    serde_json::json!({
+        "anyOf": [
+            { "not": { "required": [property] } },
+            schema
+        ]
+    })
+}
+
+/// Collects the properties and patterns that are
+/// evaluated by the schema and its subschemas.
+fn evaluated_properties(
+    obj: &Map<String, Value>,
+    definitions: &Map<String, Value>,
+    properties: &mut HashSet<String>,
+    patterns: &mut HashSet<String>,
+    visited: &mut HashSet<String>,
+) {
+    if let Some(Value::Object(props)) = obj.get("properties") {
+        properties.extend(props.keys().cloned());
+    }
+
+    if let Some(Value::Object(pattern_props)) = obj.get("patternProperties") {
+        patterns.extend(pattern_props.keys().cloned());
+    }
+
+    if let Some(name) = obj
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix("#/definitions/"))
+    {
+        let name = unescape(name);
+        if visited.insert(name.clone()) {
+            if let Some(Value::Object(target)) = definitions.get(&name) {
+                evaluated_properties(target, definitions, properties, patterns, visited);
+            }
+        }
+    }
+
+    for keyword in IN_PLACE_KEYWORDS
+        .iter()
+        .copied()
+        .chain(std::iter::once("dependentSchemas"))
+    {
+        let subschemas: Vec<&Value> = match obj.get(keyword) {
+            Some(Value::Array(schemas)) => schemas.iter().collect(),
+            Some(Value::Object(schemas)) if keyword == "dependentSchemas" => {
+                schemas.values().collect()
+            }
+            Some(schema) => vec![schema],
+            None => continue,
+        };
+
+        for schema in subschemas {
+            if let Value::Object(schema) = schema {
+                evaluated_properties(schema, definitions, properties, patterns, visited);
+            }
+        }
+    }
+}
+
+/// Resolves a URI reference against a base URI.
+pub(super) fn resolve_uri(base: &str, reference: &str) -> String {
+    if reference.contains("://") || reference.starts_with("urn:") || is_windows_path(reference) {
+        return reference.to_string();
+    }
+
+    let base = without_fragment(base);
+
+    if reference.is_empty() || reference.starts_with('#') {
+        return format!("{}{}", base, reference);
+    }
+
+    let scheme_end = base.find("://").map(|i| i + 3);
+
+    if let Some(rest) = reference.strip_prefix("//") {
+        return match scheme_end {
+            Some(end) => format!("{}{}", &base[..end], rest),
+            None => reference.to_string(),
+        };
+    }
+
+    if reference.starts_with('/') {
+        return match scheme_end {
+            Some(end) => {
+                let origin_end = base[end..].find('/').map(|i| end + i).unwrap_or(base.len());
+                format!("{}{}", &base[..origin_end], reference)
+            }
+            None => reference.to_string(),
+        };
+    }
+
+    let dir_end = base.rfind('/').map(|i| i + 1).unwrap_or(0);
+    let prefix_end = scheme_end
+        .map(|end| {
+            base[end..]
+                .find('/')
+                .map(|i| end + i + 1)
+                .unwrap_or(base.len())
+        })
+        .unwrap_or(0)
+        .min(dir_end);
+
+    let mut segments: Vec<&str> = base[prefix_end..dir_end]
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let (path, fragment) = match reference.find('#') {
+        Some(i) => (&reference[..i], &reference[i..]),
+        None => (reference, ""),
+    };
+
+    let ref_segments: Vec<&str> = path.split('/').collect();
+
+    for (i, segment) in ref_segments.iter().enumerate() {
+        match *segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s if s.is_empty() && i != ref_segments.len() - 1 => {}
+            s => segments.push(s),
+        }
+    }
+
+    let mut uri = base[..prefix_end].to_string();
+    if prefix_end == 0 && base.starts_with('/') {
+        uri.push('/');
+    }
+    uri += &segments.join("/");
+    uri += fragment;
+    uri
+}
+
+/// Whether the reference is an absolute Windows path, e.g. `C:/schemas/a.json`.
+fn is_windows_path(reference: &str) -> bool {
+    let bytes = reference.as_bytes();
+    bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'/'
+}
+
+pub(super) fn without_fragment(uri: &str) -> &str {
+    match uri.find('#') {
+        Some(i) => &uri[..i],
+        None => uri,
+    }
+}
+
+/// Escapes a JSON pointer segment.
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Unescapes a JSON pointer segment.
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+
+            if let Some(b) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| s.to_string())
+}
@@ -0,0 +1,382 @@
+//! Automatic fixes for documents that do not match their schemas.
+//!
+//! Only corrections that are unlikely to change the meaning
+//! of a document are offered:
+//!
+//! - inserting missing required keys that have a `default` value,
+//! - fixing the casing of strings that only differ from an `enum` value in case,
+//! - wrapping a string in an array where the schema only allows an array,
+//! - removing keys not allowed by `additionalProperties: false`, only if requested.
+
+use super::util::{get_schema_objects, ExtendedSchema};
+use crate::{
+    dom::{
+        self,
+        nodes::{ArrayNode, BoolNode, EntryNode, FloatNode, IntegerNode, StringNode, TableNode},
+        rewrite::{self, prelude::*, OldOrNew},
+        NodeSyntax,
+    },
+    parser::Parse,
+};
+use rowan::TextRange;
+use schemars::schema::{InstanceType, RootSchema, Schema, SingleOrVec};
+use serde_json::Value;
+
+/// The kind of a fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FixKind {
+    /// A missing required key is inserted with its default value.
+    InsertDefault,
+    /// A string is replaced with the enum value that only differs in case.
+    EnumCase,
+    /// A string is wrapped in an array.
+    WrapInArray,
+    /// A key that is not allowed is removed.
+    RemoveProperty,
+}
+
+/// Options for collecting fixes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FixOptions {
+    /// Remove keys that are not allowed by `additionalProperties: false`.
+    ///
+    /// These keys might only be misspelled, so they are kept by default.
+    pub remove_additional: bool,
+}
+
+/// A single fix for a document.
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub kind: FixKind,
+    /// The path of the affected value.
+    pub path: dom::Path,
+    /// The range of the node the fix is for,
+    /// the table for inserted keys.
+    pub range: TextRange,
+    /// A short human-readable description of the fix.
+    pub message: String,
+    edit: Edit,
+}
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Insert(rewrite::ValueNode),
+    Set(rewrite::ValueNode),
+    Remove,
+}
+
+impl Fix {
+    /// Applies the fix to the document.
+    pub fn apply(&self, editor: &mut Editor) -> Result<(), rewrite::Error> {
+        match &self.edit {
+            Edit::Insert(value) => editor.insert(&self.path, value.clone()),
+            Edit::Set(value) => editor.set(&self.path, value.clone()),
+            Edit::Remove => editor.remove(&self.path),
+        }
+    }
+}
+
+/// Collects the fixes for the document.
+///
+/// The fixes are independent of each other,
+/// and can be applied in any combination.
+pub fn collect_fixes(dom: &dom::RootNode, schema: &RootSchema, options: FixOptions) -> Vec<Fix> {
+    let mut collector = Collector {
+        schema,
+        options,
+        fixes: Vec::new(),
+    };
+
+    let end = dom.syntax().text_range().end().min(1.into());
+    collector.table(&dom::Path::new(), dom.entries(), TextRange::up_to(end));
+
+    collector.fixes
+}
+
+/// Applies all the fixes to the document.
+///
+/// Returns the edited document and the fixes that were applied,
+/// fixes that would result in an invalid document are skipped.
+pub fn fix(parse: Parse, schema: &RootSchema, options: FixOptions) -> (Parse, Vec<Fix>) {
+    let mut editor = Editor::new(parse);
+
+    let fixes = collect_fixes(editor.dom(), schema, options);
+
+    let applied = fixes
+        .into_iter()
+        .filter(|fix| fix.apply(&mut editor).is_ok())
+        .collect();
+
+    (editor.into_parse(), applied)
+}
+
+struct Collector<'s> {
+    schema: &'s RootSchema,
+    options: FixOptions,
+    fixes: Vec<Fix>,
+}
+
+impl<'s> Collector<'s> {
+    /// The schemas that must all be valid for the value at the path.
+    ///
+    /// Alternatives in `oneOf` and `anyOf` are not included,
+    /// fixing towards any of them could be wrong.
+    fn schemas(&self, path: &dom::Path) -> Vec<ExtendedSchema<'s>> {
+        let mut schemas: Vec<ExtendedSchema<'s>> =
+            get_schema_objects(path.clone(), self.schema, false)
+                .into_iter()
+                .collect();
+
+        let mut i = 0;
+        while i < schemas.len() {
+            let all_of = schemas[i]
+                .schema
+                .subschemas
+                .as_ref()
+                .and_then(|subs| subs.all_of.as_ref());
+
+            if let Some(all_of) = all_of {
+                schemas.extend(
+                    all_of
+                        .iter()
+                        .filter_map(|s| ExtendedSchema::resolved(&self.schema.definitions, s)),
+                );
+            }
+
+            i += 1;
+        }
+
+        schemas
+    }
+
+    fn table(&mut self, path: &dom::Path, entries: &dom::Entries, range: TextRange) {
+        let schemas = self.schemas(path);
+
+        let mut inserted: Vec<&str> = Vec::new();
+
+        for schema in &schemas {
+            let obj = match &schema.schema.object {
+                Some(obj) => obj,
+                None => continue,
+            };
+
+            for key in &obj.required {
+                if inserted.contains(&key.as_str())
+                    || entries
+                        .iter()
+                        .any(|(k, _)| k.full_key_string_stripped() == *key)
+                {
+                    continue;
+                }
+
+                let default_value = obj
+                    .properties
+                    .get(key)
+                    .and_then(|s| ExtendedSchema::resolved(&self.schema.definitions, s))
+                    .and_then(|s| s.schema.metadata.as_ref())
+                    .and_then(|meta| meta.default.as_ref())
+                    .and_then(to_value_node);
+
+                if let Some(value) = default_value {
+                    inserted.push(key);
+                    self.fixes.push(Fix {
+                        kind: FixKind::InsertDefault,
+                        path: path.join(key),
+                        range,
+                        message: format!(r#"Insert "{}" with its default value"#, key),
+                        edit: Edit::Insert(value),
+                    });
+                }
+            }
+
+            if !self.options.remove_additional
+                || !matches!(
+                    obj.additional_properties.as_deref(),
+                    Some(Schema::Bool(false))
+                )
+            {
+                continue;
+            }
+
+            for (key, _) in entries.iter() {
+                let key_str = key.full_key_string_stripped();
+
+                let allowed = obj.properties.contains_key(&key_str)
+                    || obj.pattern_properties.keys().any(|pattern| {
+                        regex::Regex::new(pattern)
+                            .map(|re| re.is_match(&key_str))
+                            .unwrap_or(true)
+                    });
+
+                let key_path = path.join(key_str.as_str());
+
+                if allowed || self.fixes.iter().any(|f| f.path == key_path) {
+                    continue;
+                }
+
+                self.fixes.push(Fix {
+                    kind: FixKind::RemoveProperty,
+                    path: key_path,
+                    range: key.text_ranges()[0],
+                    message: format!(r#"Remove "{}""#, key_str),
+                    edit: Edit::Remove,
+                });
+            }
+        }
+
+        for (key, entry) in entries.iter() {
+            let entry_path = path.join(key.full_key_string_stripped());
+
+            // Nothing to fix in entries that are removed.
+            if !self
+                .fixes
+                .iter()
+                .any(|f| f.kind == FixKind::RemoveProperty && f.path == entry_path)
+            {
+                self.value(&entry_path, entry.value());
+            }
+        }
+    }
+
+    fn value(&mut self, path: &dom::Path, value: &dom::ValueNode) {
+        match value {
+            dom::ValueNode::Table(table) => {
+                let range = table
+                    .text_ranges()
+                    .first()
+                    .copied()
+                    .unwrap_or_else(|| table.syntax().text_range());
+                self.table(path, table.entries(), range);
+            }
+            dom::ValueNode::Array(arr) => {
+                for (i, item) in arr.items().iter().enumerate() {
+                    self.value(&path.join(i), item);
+                }
+            }
+            dom::ValueNode::String(s) => self.string(path, s),
+            _ => {}
+        }
+    }
+
+    fn string(&mut self, path: &dom::Path, s: &dom::StringNode) {
+        let schemas = self.schemas(path);
+        let range = s.syntax().text_range();
+        let content = s.content();
+
+        let enum_schemas: Vec<&Vec<Value>> = schemas
+            .iter()
+            .filter_map(|s| s.schema.enum_values.as_ref())
+            .collect();
+
+        if !enum_schemas.is_empty()
+            && !enum_schemas
+                .iter()
+                .all(|values| values.iter().any(|v| v.as_str() == Some(content)))
+        {
+            let fixed = enum_schemas[0]
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|v| v.eq_ignore_ascii_case(content))
+                .filter(|fixed| {
+                    enum_schemas
+                        .iter()
+                        .all(|values| values.iter().any(|v| v.as_str() == Some(fixed)))
+                });
+
+            if let Some(fixed) = fixed {
+                self.fixes.push(Fix {
+                    kind: FixKind::EnumCase,
+                    path: path.clone(),
+                    range,
+                    message: format!(r#"Replace with "{}""#, fixed),
+                    edit: Edit::Set(StringNode::rewrite().with_value(fixed).build().into()),
+                });
+            }
+
+            return;
+        }
+
+        let typed: Vec<&ExtendedSchema> = schemas
+            .iter()
+            .filter(|s| s.schema.instance_type.is_some())
+            .collect();
+
+        let only_arrays = !typed.is_empty()
+            && typed
+                .iter()
+                .all(|s| s.is(InstanceType::Array) && !s.is(InstanceType::String));
+
+        if !only_arrays || !typed.iter().all(|s| self.allows_string_items(s)) {
+            return;
+        }
+
+        self.fixes.push(Fix {
+            kind: FixKind::WrapInArray,
+            path: path.clone(),
+            range,
+            message: "Wrap in an array".into(),
+            edit: Edit::Set(
+                ArrayNode::rewrite()
+                    .with_item(OldOrNew::Old(dom::ValueNode::String(s.clone())))
+                    .build()
+                    .into(),
+            ),
+        });
+    }
+
+    /// Whether the first item of the array can be a string.
+    fn allows_string_items(&self, schema: &ExtendedSchema) -> bool {
+        let items = match schema.schema.array.as_ref().and_then(|a| a.items.as_ref()) {
+            Some(items) => items,
+            None => return true,
+        };
+
+        let item = match items {
+            SingleOrVec::Single(item) => &**item,
+            SingleOrVec::Vec(items) => match items.first() {
+                Some(item) => item,
+                None => return true,
+            },
+        };
+
+        match ExtendedSchema::resolved(&self.schema.definitions, item) {
+            Some(item) => item.schema.instance_type.is_none() || item.is(InstanceType::String),
+            None => matches!(item, Schema::Bool(true)),
+        }
+    }
+}
+
+/// Converts a JSON value from a schema to a new TOML value,
+/// `null` has no TOML equivalent.
+fn to_value_node(value: &Value) -> Option<rewrite::ValueNode> {
+    let node = match value {
+        Value::Null => return None,
+        Value::Bool(b) => BoolNode::rewrite().with_value(*b).build().into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => IntegerNode::rewrite().with_value(i).build().into(),
+            None => FloatNode::rewrite().with_value(n.as_f64()?).build().into(),
+        },
+        Value::String(s) => StringNode::rewrite().with_value(s.as_str()).build().into(),
+        Value::Array(items) => {
+            let mut arr = ArrayNode::rewrite();
+            for item in items {
+                arr = arr.with_item(to_value_node(item)?);
+            }
+            arr.build().into()
+        }
+        Value::Object(entries) => {
+            let mut table = TableNode::rewrite();
+            for (key, value) in entries {
+                table = table.with_entry(
+                    EntryNode::rewrite()
+                        .with_key(key.as_str())
+                        .with_value(to_value_node(value)?)
+                        .build(),
+                );
+            }
+            table.build().into()
+        }
+    };
+
+    Some(node)
+}
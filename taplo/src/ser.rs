@@ -0,0 +1,629 @@
+//! Serialization of Rust values into TOML documents.
+//!
+//! Any type that implements [`Serialize`] can be converted into
+//! TOML text, the output is always [formatted](crate::formatter)
+//! with the given options.
+//!
+//! Maps and structs become standard tables and lists that only contain
+//! maps become arrays of tables, everything else (including tables that are
+//! nested in arrays) is written inline.
+//!
+//! Tables within other tables that only contain simple values are also
+//! written inline if they fit in the [column width](formatter::Options::column_width),
+//! e.g. `serde = { version = "1", features = ["derive"] }` under `[dependencies]`.
+//!
+//! # Example
+//!
+//! ```
+//! # use taplo::ser;
+//! # use std::collections::BTreeMap;
+//! let mut package = BTreeMap::new();
+//! package.insert("name", "taplo");
+//! package.insert("version", "0.6.3");
+//!
+//! let mut doc = BTreeMap::new();
+//! doc.insert("package", package);
+//!
+//! let toml = ser::to_string(&doc).unwrap();
+//!
+//! assert_eq!(
+//!     toml,
+//!     r#"[package]
+//! name = "taplo"
+//! version = "0.6.3"
+//! "#
+//! );
+//! ```
+
+use crate::{
+    formatter, util,
+    value::{Map, Value},
+};
+use serde_crate::ser::{self, Serialize};
+use std::fmt::Write;
+
+/// Errors that can occur during serialization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Only maps and structs can be serialized as TOML documents.
+    RootNotTable,
+
+    /// Map keys must be strings, characters, booleans or integers.
+    KeyNotString,
+
+    /// TOML has no `null`, `None` and `()` values
+    /// can only be serialized as map entries,
+    /// in which case they are omitted.
+    UnsupportedNone,
+
+    /// TOML integers are 64-bit signed integers,
+    /// larger unsigned integers cannot be represented.
+    IntegerOutOfRange(u64),
+
+    /// A custom error emitted by a serialize implementation.
+    Custom(String),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::RootNotTable => write!(f, "only tables can be serialized as TOML documents"),
+            Error::KeyNotString => write!(f, "map keys must be strings"),
+            Error::UnsupportedNone => write!(f, "TOML does not support null values"),
+            Error::IntegerOutOfRange(i) => write!(f, "integer {} is out of range", i),
+            Error::Custom(msg) => msg.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serialize the given value as a TOML document with the default
+/// formatter options.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    to_string_with_options(value, formatter::Options::default())
+}
+
+/// Serialize the given value as a TOML document with the given
+/// formatter options.
+pub fn to_string_with_options<T: ?Sized + Serialize>(
+    value: &T,
+    options: formatter::Options,
+) -> Result<String, Error> {
+    value_to_string(&to_value(value)?, options)
+}
+
+/// Write a [Value] as a TOML document.
+///
+/// Unlike [to_string], this preserves dates and times,
+/// as they would be serialized as strings otherwise.
+///
+/// The value must be a [map](Value::Map).
+pub fn value_to_string(value: &Value, options: formatter::Options) -> Result<String, Error> {
+    let root = match value {
+        Value::Map(m) => m,
+        _ => return Err(Error::RootNotTable),
+    };
+
+    check_integers(value)?;
+
+    let mut s = String::new();
+    write_table(&mut s, &mut Vec::new(), root, false, options.column_width);
+
+    Ok(formatter::format(&s, options))
+}
+
+/// Convert any serializable value into a [Value].
+pub fn to_value<T: ?Sized + Serialize>(value: &T) -> Result<Value, Error> {
+    value
+        .serialize(ValueSerializer)?
+        .ok_or(Error::UnsupportedNone)
+}
+
+/// Makes sure that all unsigned integers
+/// fit into a TOML integer.
+fn check_integers(value: &Value) -> Result<(), Error> {
+    match value {
+        Value::UnsizedInteger(i) if *i > i64::MAX as u64 => Err(Error::IntegerOutOfRange(*i)),
+        Value::Array(arr) => arr.iter().try_for_each(check_integers),
+        Value::Map(m) => m.values().try_for_each(check_integers),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the value is a table that is written as
+/// a standard table (or as the root table) at the given path.
+fn is_table(path: &[String], key: &str, value: &Value, column_width: usize) -> bool {
+    let table = match value {
+        Value::Map(m) => m,
+        _ => return false,
+    };
+
+    if path.is_empty()
+        || table
+            .values()
+            .any(|v| matches!(v, Value::Map(_)) || is_table_array(v))
+    {
+        return true;
+    }
+
+    let mut s = String::new();
+    write!(s, "{} = ", self::key(key)).unwrap();
+    write_inline(&mut s, value);
+
+    s.chars().count() > column_width
+}
+
+fn is_table_array(value: &Value) -> bool {
+    match value {
+        Value::Array(arr) => !arr.is_empty() && arr.iter().all(|v| matches!(v, Value::Map(_))),
+        _ => false,
+    }
+}
+
+fn write_table(
+    s: &mut String,
+    path: &mut Vec<String>,
+    table: &Map,
+    array: bool,
+    column_width: usize,
+) {
+    let is_entry =
+        |k: &String, v: &Value| !is_table(path, k, v, column_width) && !is_table_array(v);
+
+    let has_entries = table.iter().any(|(k, v)| is_entry(k, v));
+
+    // Headers for tables that only contain
+    // other tables are omitted.
+    if !path.is_empty() && (array || has_entries || table.is_empty()) {
+        if !s.is_empty() {
+            s.push('\n');
+        }
+
+        let header = path.iter().map(|k| key(k)).collect::<Vec<_>>().join(".");
+
+        if array {
+            writeln!(s, "[[{}]]", header).unwrap();
+        } else {
+            writeln!(s, "[{}]", header).unwrap();
+        }
+    }
+
+    for (k, v) in table.iter().filter(|(k, v)| is_entry(k, v)) {
+        write!(s, "{} = ", key(k)).unwrap();
+        write_inline(s, v);
+        s.push('\n');
+    }
+
+    for (k, v) in table.iter() {
+        match v {
+            Value::Map(m) if is_table(path, k, v, column_width) => {
+                path.push(k.clone());
+                write_table(s, path, m, false, column_width);
+                path.pop();
+            }
+            Value::Array(arr) if is_table_array(v) => {
+                path.push(k.clone());
+                for item in arr {
+                    if let Value::Map(m) = item {
+                        write_table(s, path, m, true, column_width);
+                    }
+                }
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_inline(s: &mut String, value: &Value) {
+    match value {
+        Value::Bool(b) => write!(s, "{}", b).unwrap(),
+        Value::UnsizedInteger(i) => write!(s, "{}", i).unwrap(),
+        Value::Integer(i) => write!(s, "{}", i).unwrap(),
+        Value::Float(f) => {
+            if f.is_nan() {
+                s.push_str(if f.is_sign_negative() { "-nan" } else { "nan" });
+            } else if f.is_infinite() {
+                s.push_str(if f.is_sign_negative() { "-inf" } else { "inf" });
+            } else {
+                // The debug output always contains
+                // a fractional part or an exponent.
+                write!(s, "{:?}", f).unwrap();
+            }
+        }
+        #[cfg(any(feature = "time", feature = "chrono"))]
+        Value::Date(d) => write!(s, "{}", d).unwrap(),
+        Value::String(v) => write_string(s, v),
+        Value::Array(arr) => {
+            s.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i != 0 {
+                    s.push_str(", ");
+                }
+                write_inline(s, item);
+            }
+            s.push(']');
+        }
+        Value::Map(m) => {
+            s.push('{');
+            for (i, (k, v)) in m.iter().enumerate() {
+                if i != 0 {
+                    s.push(',');
+                }
+                write!(s, " {} = ", key(k)).unwrap();
+                write_inline(s, v);
+            }
+            if !m.is_empty() {
+                s.push(' ');
+            }
+            s.push('}');
+        }
+    }
+}
+
+fn key(k: &str) -> String {
+    util::quote_key(k)
+}
+
+fn write_string(s: &mut String, v: &str) {
+    s.push('"');
+    s.push_str(&util::escape(v));
+    s.push('"');
+}
+
+/// Serializes values into [Value]s.
+///
+/// `None` is returned for `None` and unit values,
+/// these are skipped in maps and are errors everywhere else.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeTable;
+    type SerializeStruct = SerializeTable;
+    type SerializeStructVariant = SerializeVariant<SerializeTable>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        if v > i64::MAX as u64 {
+            return Err(Error::IntegerOutOfRange(v));
+        }
+
+        Ok(Some(Value::UnsizedInteger(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Float(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Array(
+            v.iter()
+                .map(|b| Value::UnsizedInteger((*b).into()))
+                .collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let mut m = Map::with_capacity(1);
+        if let Some(v) = value.serialize(self)? {
+            m.insert(variant.to_string(), v);
+        }
+        Ok(Some(Value::Map(m)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeArray(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(SerializeTable {
+            map: Map::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: SerializeTable {
+                map: Map::with_capacity(len),
+                key: None,
+            },
+        })
+    }
+}
+
+struct SerializeArray(Vec<Value>);
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.push(
+            value
+                .serialize(ValueSerializer)?
+                .ok_or(Error::UnsupportedNone)?,
+        );
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Array(self.0)))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeTable {
+    map: Map,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeTable {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let key = match key.serialize(ValueSerializer)? {
+            Some(Value::String(s)) => s,
+            Some(Value::Bool(b)) => b.to_string(),
+            Some(Value::Integer(i)) => i.to_string(),
+            Some(Value::UnsizedInteger(i)) => i.to_string(),
+            _ => return Err(Error::KeyNotString),
+        };
+
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Custom("missing map key".into()))?;
+
+        if let Some(v) = value.serialize(ValueSerializer)? {
+            self.map.insert(key, v);
+        }
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Some(Value::Map(self.map)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeTable {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        if let Some(v) = value.serialize(ValueSerializer)? {
+            self.map.insert(key.to_string(), v);
+        }
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Enum variants with data are serialized
+/// as a table with a single key.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, value: Option<Value>) -> Option<Value> {
+        let mut m = Map::with_capacity(1);
+        if let Some(v) = value {
+            m.insert(variant.to_string(), v);
+        }
+        Some(Value::Map(m))
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeSeq::end(self.inner)?,
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeTable> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeMap::end(self.inner)?,
+        ))
+    }
+}
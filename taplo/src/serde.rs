@@ -20,25 +20,7 @@ impl Serialize for Value {
             Value::Float(f) => serializer.serialize_f64(*f),
             Value::String(s) => serializer.serialize_str(s),
             #[cfg(any(feature = "time", feature = "chrono"))]
-            Value::Date(d) => match d {
-                #[cfg(feature = "chrono")]
-                crate::value::Date::OffsetDateTime(dt) => {
-                    serializer.serialize_str(&dt.to_rfc3339())
-                }
-                #[cfg(feature = "time")]
-                crate::value::Date::OffsetDateTime(dt) => {
-                    serializer.serialize_str(&dt.format(time::Format::Rfc3339))
-                }
-                crate::value::Date::LocalDateTime(dt) => {
-                    serializer.serialize_str(&dt.format("%Y-%m-%d %H:%M:%S").to_string())
-                }
-                crate::value::Date::LocalDate(date) => {
-                    serializer.serialize_str(&date.format("%Y-%m-%d").to_string())
-                }
-                crate::value::Date::LocalTime(time) => {
-                    serializer.serialize_str(&time.format("%H:%M:%S").to_string())
-                }
-            },
+            Value::Date(d) => serializer.serialize_str(&d.to_string()),
             Value::Array(arr) => {
                 let mut seq = serializer.serialize_seq(Some(arr.len()))?;
                 for item in arr {
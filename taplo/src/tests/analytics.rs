@@ -1,5 +1,9 @@
 use crate::{
-    analytics::NodeRef, syntax::SyntaxKind::*, util::coords::Mapper, util::coords::Position,
+    analytics::{NodeRef, Suppressions},
+    code::Code,
+    syntax::SyntaxKind::*,
+    util::coords::Mapper,
+    util::coords::Position,
 };
 use std::fs;
 
@@ -383,3 +387,40 @@ fn incomplete_value_inline_table() {
     assert!(pos.before.as_ref().unwrap().path.dotted() == "table.table.bool");
     assert!(pos.before.unwrap().nodes.last().unwrap().is_value());
 }
+
+#[test]
+fn suppressions() {
+    let src = r#"#:taplo-ignore-next-line TOML200 duplicate-key
+a = 1
+b = 2 #:taplo-ignore-next-line
+c = 3
+d = 4
+#:taplo-ignore-next-line TOML2000
+e = 5
+"#;
+    let mapper = Mapper::new_utf16(src, false);
+    let suppressions = Suppressions::collect_from_syntax(crate::parser::parse(src).into_syntax());
+
+    let line = |l| mapper.offset(Position::new(l, 2));
+
+    assert!(suppressions.is_suppressed(Code::Schema, line(1)));
+    assert!(suppressions.is_suppressed(Code::DuplicateKey, line(1)));
+    assert!(!suppressions.is_suppressed(Code::ExpectedTable, line(1)));
+    assert!(suppressions.is_suppressed(Code::ExpectedTable, line(3)));
+    assert!(!suppressions.is_suppressed(Code::Schema, line(4)));
+    assert!(!suppressions.is_suppressed(Code::Schema, None));
+
+    // Unknown codes only.
+    assert!(!suppressions.is_suppressed(Code::Schema, line(6)));
+    assert!(!suppressions.is_suppressed(Code::DuplicateKey, line(6)));
+}
+
+#[test]
+fn suppressions_document() {
+    let src = "a = 1\n#:taplo-ignore schema unknown-code\n";
+    let suppressions = Suppressions::collect_from_syntax(crate::parser::parse(src).into_syntax());
+
+    assert!(suppressions.is_suppressed(Code::Schema, None));
+    assert!(suppressions.is_suppressed(Code::Schema, Some(0.into())));
+    assert!(!suppressions.is_suppressed(Code::DuplicateKey, None));
+}
@@ -0,0 +1,257 @@
+use crate::{de, parser::parse};
+use serde_crate::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(crate = "serde_crate", deny_unknown_fields)]
+struct Manifest {
+    package: Package,
+    #[serde(default)]
+    dependencies: BTreeMap<String, Dependency>,
+    #[serde(default)]
+    bin: Vec<Target>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(crate = "serde_crate", deny_unknown_fields)]
+struct Package {
+    name: String,
+    version: String,
+    edition: Option<Edition>,
+    authors: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(crate = "serde_crate")]
+enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(crate = "serde_crate", untagged)]
+enum Dependency {
+    Version(String),
+    Detailed {
+        version: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(crate = "serde_crate")]
+struct Target {
+    name: String,
+    test: Option<bool>,
+}
+
+fn range_text<'s>(src: &'s str, err: &de::Error) -> &'s str {
+    &src[err.range.expect("missing error range")]
+}
+
+#[test]
+fn deserialize_struct() {
+    let src = r#"
+[package]
+name = "taplo"
+version = '0.6.3'
+edition = "2018"
+authors = ["tamasfe"]
+
+[dependencies]
+logos = "0.12.0"
+serde = { version = "1", features = ["derive"] }
+regex.version = "1"
+
+[[bin]]
+name = "taplo"
+
+[[bin]]
+name = "taplo-lsp"
+test = false
+"#;
+
+    let manifest: Manifest = de::from_str(src).unwrap();
+
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert("logos".into(), Dependency::Version("0.12.0".into()));
+    dependencies.insert(
+        "regex".into(),
+        Dependency::Detailed {
+            version: "1".into(),
+            features: Vec::new(),
+        },
+    );
+    dependencies.insert(
+        "serde".into(),
+        Dependency::Detailed {
+            version: "1".into(),
+            features: vec!["derive".into()],
+        },
+    );
+
+    assert_eq!(
+        manifest,
+        Manifest {
+            package: Package {
+                name: "taplo".into(),
+                version: "0.6.3".into(),
+                edition: Some(Edition::E2018),
+                authors: vec!["tamasfe".into()],
+            },
+            dependencies,
+            bin: vec![
+                Target {
+                    name: "taplo".into(),
+                    test: None,
+                },
+                Target {
+                    name: "taplo-lsp".into(),
+                    test: Some(false),
+                }
+            ],
+        }
+    );
+}
+
+#[test]
+fn deserialize_from_dom() {
+    let dom = parse("a = 1\nb = [1.5, 2e3]\n[c]\nd = 1979-05-27").into_dom();
+
+    let value: BTreeMap<String, crate::value::Value> = de::from_dom(&dom).unwrap();
+
+    assert_eq!(value["a"].as_i64(), Some(&1));
+    assert_eq!(value["b"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        value["c"].as_map().unwrap()["d"].as_str(),
+        Some("1979-05-27")
+    );
+}
+
+#[test]
+fn error_invalid_type() {
+    let src = r#"
+[package]
+name = "taplo"
+version = 6
+authors = []
+"#;
+
+    let err = de::from_str::<Manifest>(src).unwrap_err();
+    assert_eq!(range_text(src, &err), "6");
+
+    let src = r#"
+[package]
+name = "taplo"
+version = "0.6.3"
+authors = ["tamasfe", 2]
+"#;
+
+    let err = de::from_str::<Manifest>(src).unwrap_err();
+    assert_eq!(range_text(src, &err), "2");
+}
+
+#[test]
+fn error_unknown_field() {
+    let src = r#"
+[package]
+name = "taplo"
+version = "0.6.3"
+authors = []
+homepage = "https://taplo.tamasfe.dev"
+"#;
+
+    let err = de::from_str::<Manifest>(src).unwrap_err();
+    assert_eq!(range_text(src, &err), "homepage");
+    assert!(err.message.contains("unknown field"));
+}
+
+#[test]
+fn error_missing_field() {
+    let src = r#"
+[package]
+name = "taplo"
+authors = []
+"#;
+
+    let err = de::from_str::<Manifest>(src).unwrap_err();
+    assert_eq!(range_text(src, &err), "package");
+    assert!(err.message.contains("version"));
+
+    let err = de::from_str::<Manifest>("").unwrap_err();
+    assert_eq!(err.range, None);
+}
+
+#[test]
+fn error_enum_variant() {
+    let src = r#"
+[package]
+name = "taplo"
+version = "0.6.3"
+edition = "2021"
+authors = []
+"#;
+
+    let err = de::from_str::<Manifest>(src).unwrap_err();
+    assert_eq!(range_text(src, &err), r#""2021""#);
+}
+
+#[test]
+fn error_invalid_document() {
+    let src = "a = 1\na = 2";
+
+    let err = de::from_str::<BTreeMap<String, i64>>(src).unwrap_err();
+    assert_eq!(err.range.map(|r| &src[r]), Some("a"));
+    assert_eq!(u32::from(err.range.unwrap().start()), 6);
+
+    let src = "a = ";
+
+    let err = de::from_str::<BTreeMap<String, i64>>(src).unwrap_err();
+    assert!(err.range.is_some());
+}
+
+#[test]
+fn deserialize_quoted_keys() {
+    let src = r#"
+"basic\tkey" = 1
+'literal\tkey' = 2
+"unicode \u00e9" = 3
+'C:\Users' = 4
+"#;
+
+    let map = de::from_str::<BTreeMap<String, i64>>(src).unwrap();
+
+    assert_eq!(map.get("basic\tkey"), Some(&1));
+    assert_eq!(map.get(r"literal\tkey"), Some(&2));
+    assert_eq!(map.get("unicode é"), Some(&3));
+    assert_eq!(map.get(r"C:\Users"), Some(&4));
+    assert_eq!(map.len(), 4);
+
+    let src = r#"[table."a\tb"]
+'c\td' = 1
+"#;
+
+    let map =
+        de::from_str::<BTreeMap<String, BTreeMap<String, BTreeMap<String, i64>>>>(src).unwrap();
+
+    assert_eq!(map["table"]["a\tb"][r"c\td"], 1);
+}
+
+#[test]
+fn deserialize_inline_dotted_keys() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(crate = "serde_crate")]
+    struct Table {
+        a: i64,
+        b: BTreeMap<String, i64>,
+    }
+
+    let map = de::from_str::<BTreeMap<String, Table>>("t = { a = 1, b.c = 2, b.d = 3 }").unwrap();
+
+    assert_eq!(map["t"].a, 1);
+    assert_eq!(map["t"].b.get("c"), Some(&2));
+    assert_eq!(map["t"].b.get("d"), Some(&3));
+}
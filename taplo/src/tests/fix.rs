@@ -0,0 +1,117 @@
+use crate::{
+    parser::parse,
+    schema::fix::{collect_fixes, fix, FixKind, FixOptions},
+};
+use schemars::schema::RootSchema;
+
+fn schema() -> RootSchema {
+    serde_json::from_value(serde_json::json!({
+        "type": "object",
+        "required": ["name", "edition"],
+        "additionalProperties": false,
+        "properties": {
+            "name": { "type": "string" },
+            "edition": { "type": "string", "default": "2018" },
+            "kind": { "type": "string", "enum": ["Library", "Binary"] },
+            "authors": {
+                "type": "array",
+                "items": { "type": "string" }
+            },
+            "features": { "$ref": "#/definitions/Features" }
+        },
+        "definitions": {
+            "Features": {
+                "type": "object",
+                "required": ["default"],
+                "properties": {
+                    "default": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "default": []
+                    }
+                }
+            }
+        }
+    }))
+    .unwrap()
+}
+
+fn fixed(src: &str, options: FixOptions) -> (String, Vec<FixKind>) {
+    let (parse, fixes) = fix(parse(src), &schema(), options);
+    (
+        parse.into_syntax().to_string(),
+        fixes.into_iter().map(|f| f.kind).collect(),
+    )
+}
+
+#[test]
+fn fix_insert_default() {
+    let (src, kinds) = fixed("name = \"taplo\"\n\n[features]\n", FixOptions::default());
+
+    assert_eq!(kinds, [FixKind::InsertDefault, FixKind::InsertDefault]);
+    assert_eq!(
+        src,
+        "name = \"taplo\"\nedition = \"2018\"\n\n[features]\ndefault = []\n"
+    );
+}
+
+#[test]
+fn fix_enum_case() {
+    let (src, kinds) = fixed(
+        "name = \"taplo\"\nedition = \"2018\"\nkind = \"library\"\n",
+        FixOptions::default(),
+    );
+
+    assert_eq!(kinds, [FixKind::EnumCase]);
+    assert_eq!(
+        src,
+        "name = \"taplo\"\nedition = \"2018\"\nkind = \"Library\"\n"
+    );
+}
+
+#[test]
+fn fix_wrap_in_array() {
+    let (src, kinds) = fixed(
+        "name = \"taplo\"\nedition = \"2018\"\nauthors = 'tamasfe' # Comment\n",
+        FixOptions::default(),
+    );
+
+    assert_eq!(kinds, [FixKind::WrapInArray]);
+    assert_eq!(
+        src,
+        "name = \"taplo\"\nedition = \"2018\"\nauthors = ['tamasfe'] # Comment\n"
+    );
+}
+
+#[test]
+fn fix_remove_additional() {
+    let src = "name = \"taplo\"\nedition = \"2018\"\nnmae = \"taplo\"\n";
+
+    let (unchanged, kinds) = fixed(src, FixOptions::default());
+    assert!(kinds.is_empty());
+    assert_eq!(unchanged, src);
+
+    let (src, kinds) = fixed(
+        src,
+        FixOptions {
+            remove_additional: true,
+        },
+    );
+    assert_eq!(kinds, [FixKind::RemoveProperty]);
+    assert_eq!(src, "name = \"taplo\"\nedition = \"2018\"\n");
+}
+
+#[test]
+fn fix_nothing_to_fix() {
+    let dom =
+        parse("name = \"taplo\"\nedition = \"2021\"\nkind = \"Binary\"\nauthors = []\n").into_dom();
+
+    assert!(collect_fixes(
+        &dom,
+        &schema(),
+        FixOptions {
+            remove_additional: true
+        }
+    )
+    .is_empty());
+}
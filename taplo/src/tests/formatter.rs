@@ -1,6 +1,7 @@
 use difference::Changeset;
 
 use crate::formatter;
+use rowan::TextRange;
 
 macro_rules! assert_format {
     ($expected:expr, $actual:expr) => {
@@ -903,4 +904,158 @@ fn table_entries_no_blank_space_indent_entries_and_tables() {
     );
 
     assert_format!(src, &formatted);
-}
\ No newline at end of file
+}
+#[test]
+fn format_edits_are_minimal() {
+    let src = r#"# Comment
+a   =    1
+b = 2
+c = [1,2]
+
+[table]
+d = "unchanged"
+"#;
+
+    let edits = formatter::format_edits(
+        crate::parser::parse(src).into_dom(),
+        Default::default(),
+        Vec::new(),
+    );
+
+    assert_eq!(
+        edits
+            .iter()
+            .map(|e| (&src[e.range], e.new_text.as_str()))
+            .collect::<Vec<_>>(),
+        vec![("  ", ""), ("   ", ""), ("", " ")]
+    );
+
+    assert_format!(
+        &formatter::format(src, Default::default()),
+        &formatter::apply_edits(src, &edits)
+    );
+}
+
+#[test]
+fn format_edits_formatted() {
+    let src = r#"a = 1
+
+[table]
+b = [1, 2]
+"#;
+
+    let edits = formatter::format_edits(
+        crate::parser::parse(src).into_dom(),
+        Default::default(),
+        Vec::new(),
+    );
+
+    assert!(edits.is_empty());
+}
+
+#[test]
+fn diff_lines() {
+    let original = "a = 1\nb = 2\nc = 3\nd = 4\n";
+    let changed = "a = 1\nc = 3\nx = 0\nd = 4\ne = 5\n";
+
+    let edits = formatter::diff(original, changed);
+
+    assert_eq!(formatter::apply_edits(original, &edits), changed);
+
+    for edit in &edits {
+        assert!(!original[edit.range].contains("a = 1"));
+        assert!(!original[edit.range].contains("d = 4"));
+    }
+}
+
+#[test]
+fn diff_crlf() {
+    let original = "a=1\r\nb  = 2\r\n";
+    let changed = "a = 1\r\nb = 2\r\n";
+
+    let edits = formatter::diff(original, changed);
+
+    assert_eq!(formatter::apply_edits(original, &edits), changed);
+    assert!(edits.iter().all(|e| !original[e.range].contains('\n')));
+}
+
+#[test]
+fn format_range_entry() {
+    let src = r#"a   =   1
+b   =   [1,2,
+  3]
+c   =   3
+"#;
+
+    let start = src.find("2").unwrap() as u32;
+    let edits = formatter::format_range(
+        crate::parser::parse(src).into_dom(),
+        TextRange::new(start.into(), (start + 1).into()),
+        Default::default(),
+        Vec::new(),
+    );
+
+    assert_format!(
+        r#"a   =   1
+b = [1, 2, 3]
+c   =   3
+"#,
+        &formatter::apply_edits(src, &edits)
+    );
+}
+
+#[test]
+fn format_range_table() {
+    let src = r#"a   =   1
+
+[table]
+b   =   2
+c =    3
+
+[other]
+d   =   4
+"#;
+
+    let start = src.find("[table]").unwrap() as u32;
+    let edits = formatter::format_range(
+        crate::parser::parse(src).into_dom(),
+        TextRange::new(start.into(), (start + 1).into()),
+        Default::default(),
+        Vec::new(),
+    );
+
+    assert_format!(
+        r#"a   =   1
+
+[table]
+b = 2
+c = 3
+
+[other]
+d   =   4
+"#,
+        &formatter::apply_edits(src, &edits)
+    );
+}
+
+#[test]
+fn format_with_scopes() {
+    let src = "a = [1,2]\nb = [1,2]\n";
+
+    let start = src.find("b").unwrap() as u32;
+    let scopes = vec![(
+        TextRange::new(start.into(), (src.len() as u32).into()),
+        formatter::OptionsIncomplete {
+            compact_arrays: Some(false),
+            ..Default::default()
+        },
+    )];
+
+    let formatted = formatter::format_with_scopes(
+        crate::parser::parse(src).into_dom(),
+        Default::default(),
+        scopes.into_iter().collect(),
+    );
+
+    assert_format!("a = [1, 2]\nb = [ 1, 2 ]\n", &formatted);
+}
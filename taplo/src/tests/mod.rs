@@ -6,5 +6,17 @@ mod generated {
 #[cfg(feature = "rewrite")]
 mod rewrite;
 
+#[cfg(all(feature = "schema", feature = "rewrite"))]
+mod fix;
+
+#[cfg(feature = "schema")]
+mod schema;
+
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "serde")]
+mod ser;
+
 mod analytics;
-mod formatter;
\ No newline at end of file
+mod formatter;
+mod parser;
\ No newline at end of file
@@ -0,0 +1,154 @@
+use crate::{
+    code::Code,
+    parser::{parse, Parse},
+};
+use rowan::{TextRange, TextSize};
+
+const SOURCE: &str = r#"# comment
+title = "TOML"
+
+[owner]
+name = "Tom"
+dob = 1979-05-27T07:32:00-08:00
+
+[database]
+ports = [
+  8001,
+  8001,
+  8002,
+]
+
+[[products]]
+name = "Hammer"
+"#;
+
+fn reparse(src: &str, find: &str, replace_with: &str) -> (Parse, String) {
+    let start = src.find(find).unwrap();
+    let range = TextRange::new(
+        TextSize::from(start as u32),
+        TextSize::from((start + find.len()) as u32),
+    );
+
+    let mut new_src = src.to_string();
+    new_src.replace_range(start..start + find.len(), replace_with);
+
+    (parse(src).reparse(range, replace_with), new_src)
+}
+
+fn assert_same(p: Parse, src: &str) {
+    let expected = parse(src);
+
+    assert_eq!(p.errors, expected.errors);
+    assert_eq!(
+        format!("{:#?}", p.into_syntax()),
+        format!("{:#?}", expected.into_syntax())
+    );
+}
+
+#[test]
+fn reparse_value() {
+    let (p, src) = reparse(SOURCE, r#""Tom""#, r#""Tom Preston-Werner""#);
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_reuses_untouched_nodes() {
+    let old = parse(SOURCE);
+    let (p, _) = reparse(SOURCE, r#""Tom""#, r#""Tom Preston-Werner""#);
+
+    let old_children: Vec<_> = old.green_node.children().collect();
+    let new_children: Vec<_> = p.green_node.children().collect();
+
+    assert_eq!(old_children.len(), new_children.len());
+
+    // The table header before the change and the
+    // array of tables after it are kept as they are.
+    assert_eq!(old_children[..5], new_children[..5]);
+    assert_eq!(
+        old_children[old_children.len() - 4..],
+        new_children[new_children.len() - 4..]
+    );
+}
+
+#[test]
+fn reparse_multi_line_entry() {
+    let (p, src) = reparse(SOURCE, "  8002,\n", "  8002,\n  8003,\n");
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_insert_lines() {
+    let (p, src) = reparse(
+        SOURCE,
+        "[database]\n",
+        "[database]\nserver = \"1.1.1.1\"\n\n",
+    );
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_remove_newline() {
+    let (p, src) = reparse(SOURCE, "\n[owner]", "[owner]");
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_unterminated_array() {
+    let (p, src) = reparse(SOURCE, "  8002,\n]", "  8002,\n");
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_multi_line_string() {
+    let (p, src) = reparse(SOURCE, r#""TOML""#, r#""""TOML"#);
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_keeps_errors() {
+    let src = SOURCE.replace("# comment", "invalid =");
+    let (p, src) = reparse(&src, "Hammer", "Nail");
+    assert_same(p, &src);
+}
+
+#[test]
+fn reparse_end_of_document() {
+    let (p, src) = reparse(SOURCE, "\"Hammer\"\n", "\"Hammer\"\nsku = 738594937\n");
+    assert_same(p, &src);
+
+    let start = TextSize::from(SOURCE.len() as u32);
+    let p = parse(SOURCE).reparse(TextRange::empty(start), "[empty]\n");
+    assert_same(p, &(SOURCE.to_string() + "[empty]\n"));
+}
+
+#[test]
+fn error_codes() {
+    let code = |src: &str| parse(src).errors[0].code;
+
+    assert_eq!(code("a = 1 b = 2"), Code::ExpectedNewline);
+    assert_eq!(code("a = 1__0"), Code::InvalidNumber);
+    assert_eq!(code(r#"a = "\q""#), Code::InvalidEscape);
+    assert_eq!(code("a = { b = 1,\n}"), Code::NewlineInInlineTable);
+    assert_eq!(code("a = { b = 1, }"), Code::TrailingComma);
+    assert_eq!(code("a ="), Code::InvalidSyntax);
+
+    let dom = parse("a = 1\na = 2\n\n[b]\n[[b]]\n").into_dom();
+
+    assert_eq!(
+        dom.errors().iter().map(|e| e.code()).collect::<Vec<_>>(),
+        vec![Code::DuplicateKey, Code::ExpectedArrayOfTables]
+    );
+}
+
+#[test]
+fn code_from_str() {
+    assert_eq!("TOML001".parse(), Ok(Code::DuplicateKey));
+    assert_eq!("toml001".parse(), Ok(Code::DuplicateKey));
+    assert_eq!("duplicate-key".parse(), Ok(Code::DuplicateKey));
+    assert!("TOML999".parse::<Code>().is_err());
+
+    for code in Code::ALL {
+        assert_eq!(code.id().parse(), Ok(*code));
+        assert_eq!(code.name().parse(), Ok(*code));
+    }
+}
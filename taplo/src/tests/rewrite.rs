@@ -1,4 +1,11 @@
-use crate::dom::{nodes::*, rewrite::prelude::*};
+use crate::{
+    dom::{
+        nodes::*,
+        rewrite::{prelude::*, Error},
+        Path, PathKey,
+    },
+    formatter,
+};
 use std::fs;
 
 #[test]
@@ -122,3 +129,277 @@ fn rewrite_table() {
 
     assert!(expected == rewritten)
 }
+
+fn path(s: &str) -> Path {
+    s.split('.')
+        .map(|k| match k.parse::<usize>() {
+            Ok(idx) => PathKey::Index(idx),
+            Err(_) => PathKey::Key(k.into()),
+        })
+        .collect()
+}
+
+fn string(s: &str) -> crate::dom::rewrite::StringNode {
+    StringNode::rewrite().with_value(s).build()
+}
+
+fn editor() -> Editor {
+    let src = fs::read_to_string("../test-data/rewrite/edit.toml").unwrap();
+    Editor::new(crate::parser::parse(&src))
+}
+
+#[test]
+fn edit_insert() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_insert_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor
+        .insert(&path("package.edition"), string("2018"))
+        .unwrap();
+    editor
+        .insert(
+            &path("package.metadata.rustdoc"),
+            BoolNode::rewrite().with_value(false).build(),
+        )
+        .unwrap();
+    editor
+        .insert(
+            &path("dependencies.serde.optional"),
+            BoolNode::rewrite().with_value(true).build(),
+        )
+        .unwrap();
+    editor
+        .insert(
+            &path("dependencies.indexmap"),
+            TableNode::rewrite()
+                .with_entry(
+                    EntryNode::rewrite()
+                        .with_key("version")
+                        .with_value(string("1"))
+                        .build(),
+                )
+                .build(),
+        )
+        .unwrap();
+    editor
+        .insert(
+            &path("lints"),
+            TableNode::rewrite()
+                .top_level("lints", false)
+                .with_entry(
+                    EntryNode::rewrite()
+                        .with_key("unsafe")
+                        .with_value(string("forbid"))
+                        .build(),
+                )
+                .build(),
+        )
+        .unwrap();
+    editor
+        .insert(
+            &path("workspace"),
+            BoolNode::rewrite().with_value(true).build(),
+        )
+        .unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_remove() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_remove_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor.remove(&path("package.authors")).unwrap();
+    editor.remove(&path("package.metadata")).unwrap();
+    editor.remove(&path("dependencies.serde.features")).unwrap();
+    editor.remove(&path("features.default.0")).unwrap();
+    editor.remove(&path("bin.1")).unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_remove_tables() {
+    let expected =
+        fs::read_to_string("../test-data/rewrite/edit_remove_tables_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor.remove(&path("dependencies")).unwrap();
+    editor.remove(&path("bin")).unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_rename() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_rename_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor.rename(&path("package.name"), "title").unwrap();
+    editor
+        .rename(&path("dependencies.regex"), "my regex")
+        .unwrap();
+    editor.rename(&path("bin"), "example").unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_set() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_set_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor
+        .set(&path("package.version"), string("0.2.0"))
+        .unwrap();
+    editor.set(&path("package.license"), string("MIT")).unwrap();
+    editor
+        .set(&path("bin.0.name"), string("taplo-lsp"))
+        .unwrap();
+    editor
+        .set(
+            &path("features"),
+            TableNode::rewrite().top_level("features", false).build(),
+        )
+        .unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_append() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_append_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor
+        .append(&path("package.authors"), string("someone"))
+        .unwrap();
+    editor
+        .append(&path("dependencies.serde.features"), string("rc"))
+        .unwrap();
+    editor
+        .append(&path("features.default"), string("regex"))
+        .unwrap();
+    editor
+        .append(
+            &path("bin"),
+            TableNode::rewrite()
+                .top_level("bin", true)
+                .with_entry(
+                    EntryNode::rewrite()
+                        .with_key("name")
+                        .with_value(string("taplo-lsp"))
+                        .build(),
+                )
+                .build(),
+        )
+        .unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_move() {
+    let expected = fs::read_to_string("../test-data/rewrite/edit_move_expected.toml").unwrap();
+    let mut editor = editor();
+
+    editor
+        .move_entry(&path("package.metadata.docs"), &path("features"))
+        .unwrap();
+    editor
+        .move_entry(&path("dependencies.serde"), &path("bin.1"))
+        .unwrap();
+
+    assert_eq!(expected, editor.to_string());
+}
+
+#[test]
+fn edit_errors() {
+    let mut editor = editor();
+    let original = editor.to_string();
+
+    assert!(matches!(
+        editor.insert(&path("package.name"), string("taplo")),
+        Err(Error::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        editor.insert(&path("nothing.here"), string("taplo")),
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        editor.insert(&path("package.name.inner"), string("taplo")),
+        Err(Error::ExpectedTable(_))
+    ));
+    assert!(matches!(
+        editor.append(&path("package.name"), string("taplo")),
+        Err(Error::ExpectedArray(_))
+    ));
+    assert!(matches!(
+        editor.rename(&path("package"), "features"),
+        Err(Error::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        editor.move_entry(&path("bin"), &path("package")),
+        Err(Error::NotMovable(_))
+    ));
+    assert!(matches!(
+        editor.insert(
+            &path("package.date"),
+            DateNode::rewrite().with_text("not a date").build()
+        ),
+        Err(Error::InvalidResult { .. })
+    ));
+    // The second step fails, the removal must not be kept.
+    assert!(matches!(
+        editor.move_entry(&path("package.name"), &path("bin.0")),
+        Err(Error::AlreadyExists(_))
+    ));
+
+    assert_eq!(original, editor.to_string());
+}
+
+#[test]
+fn edit_errors_replaced() {
+    let src = "a = tru\nb = 1\n";
+    let mut editor = Editor::new(crate::parser::parse(src));
+
+    // The existing error is removed, but a different one is introduced.
+    assert!(matches!(
+        editor.set(&path("a"), DateNode::rewrite().with_text("1.").build()),
+        Err(Error::InvalidResult { .. })
+    ));
+
+    // Existing errors are allowed to stay.
+    editor.insert(&path("c"), string("taplo")).unwrap();
+
+    assert_eq!("a = tru\nb = 1\nc = \"taplo\"\n", editor.to_string());
+}
+
+#[test]
+fn edit_formatted() {
+    let src = fs::read_to_string("../test-data/rewrite/edit.toml").unwrap();
+    let src = formatter::format(&src, Default::default());
+
+    let mut editor = Editor::new(crate::parser::parse(&src));
+
+    editor
+        .insert(&path("package.edition"), string("2018"))
+        .unwrap();
+    editor
+        .set(&path("dependencies.regex"), string("1.4"))
+        .unwrap();
+    editor.remove(&path("package.metadata")).unwrap();
+    editor.remove(&path("bin.0")).unwrap();
+    editor
+        .append(&path("features.default"), string("regex"))
+        .unwrap();
+    editor
+        .rename(&path("dependencies.serde"), "serde_crate")
+        .unwrap();
+
+    let edited = editor.to_string();
+
+    assert_eq!(formatter::format(&edited, Default::default()), edited);
+    assert!(edited.contains("name = \"taplo\"        # The name."));
+}
@@ -0,0 +1,536 @@
+use crate::{
+    dom::Path,
+    schema::{
+        builtin::{associated_schema, builtin_schema, BUILTIN_SCHEMAS},
+        bundle::SchemaBundle,
+        util::{attach_schema, get_schema_objects, local_definition, schema_from_value},
+    },
+};
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use serde_json::json;
+
+fn schema(value: serde_json::Value) -> RootSchema {
+    schema_from_value(value).unwrap()
+}
+
+fn object(schema: &Schema) -> &SchemaObject {
+    match schema {
+        Schema::Object(o) => o,
+        Schema::Bool(_) => panic!("expected a schema object"),
+    }
+}
+
+fn property<'s>(schema: &'s SchemaObject, name: &str) -> &'s SchemaObject {
+    object(&schema.object.as_ref().unwrap().properties[name])
+}
+
+/// Asserts that all the local references point to existing definitions.
+fn assert_refs_resolved(root: &RootSchema) {
+    fn check(value: &serde_json::Value, root: &RootSchema) {
+        match value {
+            serde_json::Value::Object(obj) => {
+                if let Some(rf) = obj.get("$ref").and_then(|r| r.as_str()) {
+                    let def = local_definition(rf).expect("expected a definition reference");
+                    assert!(root.definitions.contains_key(def), "missing {}", rf);
+                }
+
+                obj.values().for_each(|value| check(value, root));
+            }
+            serde_json::Value::Array(values) => values.iter().for_each(|value| check(value, root)),
+            _ => {}
+        }
+    }
+
+    check(&serde_json::to_value(root).unwrap(), root);
+}
+
+#[test]
+fn schema_defs() {
+    let root = schema(json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/$defs/Name" }
+        },
+        "$defs": {
+            "Name": { "type": "string" }
+        }
+    }));
+
+    assert_eq!(
+        property(&root.schema, "name").reference.as_deref(),
+        Some("#/definitions/Name")
+    );
+    assert!(root.definitions.contains_key("Name"));
+
+    let schemas = get_schema_objects(Path::new().join("name"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+}
+
+#[test]
+fn schema_keyword_property_names() {
+    let root = schema(json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": {
+            "default": { "$ref": "#/$defs/Name" },
+            "other": { "$ref": "#/$defs/Name" },
+            "examples": {
+                "type": "object",
+                "properties": {
+                    "const": { "prefixItems": [{ "type": "string" }] }
+                }
+            }
+        },
+        "$defs": {
+            "Name": { "type": "string" }
+        }
+    }));
+
+    for name in &["default", "other"] {
+        assert_eq!(
+            property(&root.schema, name).reference.as_deref(),
+            Some("#/definitions/Name")
+        );
+    }
+
+    let nested = property(property(&root.schema, "examples"), "const");
+    assert!(matches!(
+        &nested.array.as_ref().unwrap().items,
+        Some(SingleOrVec::Vec(items)) if items.len() == 1
+    ));
+
+    let schemas = get_schema_objects(Path::new().join("default"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+}
+
+#[test]
+fn schema_anchor() {
+    let root = schema(json!({
+        "properties": {
+            "name": { "$ref": "#name" }
+        },
+        "definitions": {
+            "Other": { "$anchor": "name", "type": "string" }
+        }
+    }));
+
+    assert_eq!(
+        property(&root.schema, "name").reference.as_deref(),
+        Some("#/definitions/Other")
+    );
+}
+
+#[test]
+fn schema_bundled_resource() {
+    let root = schema(json!({
+        "$id": "https://example.com/schemas/root.json",
+        "properties": {
+            "name": { "$ref": "name.json" },
+            "version": { "$ref": "https://example.com/other.json#/definitions/Version" }
+        },
+        "$defs": {
+            "Name": {
+                "$id": "https://example.com/schemas/name.json",
+                "type": "string"
+            }
+        }
+    }));
+
+    assert_eq!(
+        property(&root.schema, "name").reference.as_deref(),
+        Some("#/definitions/Name")
+    );
+
+    // Not part of the document.
+    assert_eq!(
+        property(&root.schema, "version").reference.as_deref(),
+        Some("https://example.com/other.json#/definitions/Version")
+    );
+}
+
+#[test]
+fn schema_pointer_ref() {
+    let root = schema(json!({
+        "properties": {
+            "name": { "type": "string" },
+            "alias": { "$ref": "#/properties/name" }
+        }
+    }));
+
+    assert_eq!(
+        property(&root.schema, "alias").reference.as_deref(),
+        Some("#/definitions/properties.name")
+    );
+    assert!(root.definitions.contains_key("properties.name"));
+}
+
+#[test]
+fn schema_dependent_required() {
+    let root = schema(json!({
+        "properties": {
+            "a": { "type": "string" },
+            "b": { "type": "string" }
+        },
+        "dependentRequired": {
+            "a": ["b"]
+        }
+    }));
+
+    let all_of = root
+        .schema
+        .subschemas
+        .as_ref()
+        .and_then(|s| s.all_of.as_ref())
+        .unwrap();
+    assert_eq!(all_of.len(), 1);
+
+    let any_of = object(&all_of[0])
+        .subschemas
+        .as_ref()
+        .and_then(|s| s.any_of.as_ref())
+        .unwrap();
+    assert_eq!(any_of.len(), 2);
+}
+
+#[test]
+fn schema_unevaluated_properties() {
+    let root = schema(json!({
+        "$schema": "https://json-schema.org/draft/2019-09/schema",
+        "$ref": "#/$defs/Base",
+        "properties": {
+            "name": { "type": "string" }
+        },
+        "unevaluatedProperties": false,
+        "$defs": {
+            "Base": {
+                "properties": {
+                    "version": { "type": "string" }
+                }
+            }
+        }
+    }));
+
+    let obj = root.schema.object.as_ref().unwrap();
+    assert!(obj.properties.contains_key("name"));
+    assert!(obj.properties.contains_key("version"));
+    assert_eq!(
+        obj.additional_properties.as_deref(),
+        Some(&Schema::Bool(false))
+    );
+
+    // The reference is kept as a subschema.
+    assert!(root.schema.reference.is_none());
+    let all_of = root
+        .schema
+        .subschemas
+        .as_ref()
+        .and_then(|s| s.all_of.as_ref())
+        .unwrap();
+    assert_eq!(
+        object(&all_of[0]).reference.as_deref(),
+        Some("#/definitions/Base")
+    );
+}
+
+#[test]
+fn schema_prefix_items() {
+    let root = schema(json!({
+        "type": "array",
+        "prefixItems": [{ "type": "string" }, { "type": "integer" }],
+        "items": false
+    }));
+
+    let arr = root.schema.array.as_ref().unwrap();
+    assert!(matches!(&arr.items, Some(SingleOrVec::Vec(items)) if items.len() == 2));
+    assert_eq!(arr.additional_items.as_deref(), Some(&Schema::Bool(false)));
+}
+
+#[test]
+fn schema_bundle_relative_refs() {
+    let mut bundle = SchemaBundle::new(
+        "https://example.com/schemas/root.json",
+        schema(json!({
+            "properties": {
+                "package": { "$ref": "fragments/package.json" },
+                "version": { "$ref": "../common.json#/definitions/Version" }
+            }
+        })),
+    );
+
+    assert_eq!(
+        bundle.missing_documents(),
+        vec![
+            "https://example.com/schemas/fragments/package.json".to_string(),
+            "https://example.com/common.json".to_string(),
+        ]
+    );
+
+    bundle.add_document(
+        "https://example.com/schemas/fragments/package.json",
+        schema(json!({
+            "type": "object",
+            "properties": {
+                "name": { "$ref": "#/definitions/Name" }
+            },
+            "definitions": {
+                "Name": { "type": "string" }
+            }
+        })),
+    );
+    bundle.add_document(
+        "https://example.com/common.json",
+        schema(json!({
+            "definitions": {
+                "Version": { "type": "string" }
+            }
+        })),
+    );
+
+    assert!(bundle.missing_documents().is_empty());
+
+    let root = bundle.into_schema().unwrap();
+
+    let schemas = get_schema_objects(Path::new().join("package").join("name"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+
+    let schemas = get_schema_objects(Path::new().join("version"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+}
+
+#[test]
+fn schema_bundle_defs_refs() {
+    let mut bundle = SchemaBundle::new(
+        "https://example.com/root.json",
+        schema(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "properties": {
+                "name": { "$ref": "other.json#/$defs/Name" }
+            }
+        })),
+    );
+
+    bundle.add_document(
+        "https://example.com/other.json",
+        schema(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": {
+                "Name": { "type": "string" }
+            }
+        })),
+    );
+
+    let root = bundle.into_schema().unwrap();
+
+    let reference = property(&root.schema, "name").reference.clone().unwrap();
+    let name = reference.strip_prefix("#/definitions/").unwrap();
+    assert!(root.definitions.contains_key(name));
+
+    let schemas = get_schema_objects(Path::new().join("name"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+}
+
+#[test]
+fn schema_missing_ref_target() {
+    let root = schema(json!({
+        "properties": {
+            "name": { "$ref": "#/definitions/Missing" },
+            "other": { "$ref": "#/$defs/Missing" }
+        }
+    }));
+
+    assert_eq!(
+        property(&root.schema, "name").reference.as_deref(),
+        Some("#/definitions/Missing")
+    );
+    assert_eq!(
+        property(&root.schema, "other").reference.as_deref(),
+        Some("#/$defs/Missing")
+    );
+    assert!(root.definitions.is_empty());
+}
+
+#[test]
+fn schema_bundle_cyclic_refs() {
+    let mut bundle = SchemaBundle::new(
+        "/schemas/a.json",
+        schema(json!({
+            "properties": {
+                "b": { "$ref": "b.json" },
+                "value": { "type": "integer" }
+            }
+        })),
+    );
+
+    assert_eq!(
+        bundle.missing_documents(),
+        vec!["/schemas/b.json".to_string()]
+    );
+
+    bundle.add_document(
+        "/schemas/b.json",
+        schema(json!({
+            "properties": {
+                "a": { "$ref": "a.json" }
+            }
+        })),
+    );
+
+    assert!(bundle.missing_documents().is_empty());
+
+    let root = bundle.into_schema().unwrap();
+
+    let schemas = get_schema_objects(Path::new().join("b").join("a").join("value"), &root, false);
+    assert!(schemas.iter().any(|s| s.is(InstanceType::Integer)));
+}
+
+#[test]
+fn schema_bundle_skipped_document() {
+    let mut bundle = SchemaBundle::new(
+        "https://example.com/root.json",
+        schema(json!({
+            "properties": {
+                "name": { "$ref": "name.json" }
+            }
+        })),
+    );
+
+    bundle.skip_document("https://example.com/name.json");
+    assert!(bundle.missing_documents().is_empty());
+
+    let root = bundle.into_schema().unwrap();
+    assert_eq!(
+        property(&root.schema, "name").reference.as_deref(),
+        Some("name.json")
+    );
+}
+
+#[test]
+fn schema_attach_quoted_keys() {
+    let mut root = schema(json!({
+        "properties": {
+            "tool": { "type": "object" }
+        }
+    }));
+
+    attach_schema(
+        &mut root,
+        r#"tool."my.tool" . 'name'"#,
+        schema(json!({ "type": "string" })),
+    );
+
+    let schemas = get_schema_objects(
+        Path::new().join("tool").join("my.tool").join("name"),
+        &root,
+        false,
+    );
+    assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+
+    let schemas = get_schema_objects(Path::new().join("tool").join("my"), &root, false);
+    assert!(!schemas.iter().any(|s| s.is(InstanceType::String)));
+
+    assert_refs_resolved(&root);
+}
+
+#[test]
+fn schema_attach_patterns() {
+    let mut root = schema(json!({}));
+
+    attach_schema(
+        &mut root,
+        "plugins.*.v+?",
+        schema(json!({ "type": "integer" })),
+    );
+
+    let all_of = root
+        .schema
+        .subschemas
+        .as_ref()
+        .and_then(|s| s.all_of.as_ref())
+        .unwrap();
+    let plugins = property(object(&all_of[0]), "plugins");
+    let any = object(&plugins.object.as_ref().unwrap().pattern_properties["^.*$"]);
+    assert!(any
+        .object
+        .as_ref()
+        .unwrap()
+        .pattern_properties
+        .contains_key("^v\\+.$"));
+
+    let schemas = get_schema_objects(
+        Path::new().join("plugins").join("foo").join("v+1"),
+        &root,
+        false,
+    );
+    assert!(schemas.iter().any(|s| s.is(InstanceType::Integer)));
+
+    let schemas = get_schema_objects(
+        Path::new().join("plugins").join("foo").join("v11"),
+        &root,
+        false,
+    );
+    assert!(!schemas.iter().any(|s| s.is(InstanceType::Integer)));
+}
+
+#[test]
+fn schema_attach_renamed_refs() {
+    let mut root = schema(json!({
+        "definitions": {
+            "Name": { "type": "integer" }
+        }
+    }));
+
+    // Not upgraded, so the pointer references are still there.
+    let attached: RootSchema = serde_json::from_value(json!({
+        "type": "object",
+        "properties": {
+            "name": { "$ref": "#/definitions/Name" },
+            "alias": { "$ref": "#/properties/name" },
+            "nested": { "$ref": "#" }
+        },
+        "definitions": {
+            "Name": { "type": "string" }
+        }
+    }))
+    .unwrap();
+
+    attach_schema(&mut root, "package", attached);
+
+    assert!(root.definitions.contains_key("x-taplo-keys-0"));
+    assert!(root.definitions.contains_key("x-taplo-keys-0::Name"));
+    assert_refs_resolved(&root);
+
+    for path in &[
+        Path::new().join("package").join("name"),
+        Path::new().join("package").join("alias"),
+        Path::new().join("package").join("nested").join("name"),
+    ] {
+        let schemas = get_schema_objects(path.clone(), &root, false);
+        assert!(schemas.iter().any(|s| s.is(InstanceType::String)));
+        assert!(!schemas.iter().any(|s| s.is(InstanceType::Integer)));
+    }
+}
+
+#[test]
+fn schema_builtin() {
+    for builtin in BUILTIN_SCHEMAS {
+        assert!(builtin.schema().schema.object.is_some());
+        assert!(!builtin.info().patterns.is_empty());
+    }
+
+    assert_eq!(
+        builtin_schema("taplo://cargo.toml").map(|s| s.url()),
+        Some("taplo://cargo.toml".into())
+    );
+    assert!(builtin_schema("taplo://unknown.toml").is_none());
+
+    assert_eq!(
+        associated_schema("file:///home/user/project/Cargo.toml").map(|s| s.name),
+        Some("cargo.toml")
+    );
+    assert_eq!(
+        associated_schema("pyproject.toml").map(|s| s.name),
+        Some("pyproject.toml")
+    );
+    assert!(associated_schema("/home/user/project/config.toml").is_none());
+}
@@ -0,0 +1,226 @@
+use crate::{
+    formatter,
+    parser::parse,
+    ser::{self, Error},
+    value::Value,
+};
+use serde_crate::Serialize;
+use std::{collections::BTreeMap, convert::TryFrom};
+
+#[derive(Serialize)]
+#[serde(crate = "serde_crate")]
+struct Manifest {
+    package: Package,
+    dependencies: BTreeMap<String, Dependency>,
+    bin: Vec<Target>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "serde_crate")]
+struct Package {
+    name: String,
+    version: String,
+    authors: Vec<String>,
+    description: Option<String>,
+    metadata: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "serde_crate", untagged)]
+enum Dependency {
+    Version(String),
+    Detailed {
+        version: String,
+        features: Vec<String>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "serde_crate")]
+struct Target {
+    name: String,
+    path: String,
+}
+
+fn manifest() -> Manifest {
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert("logos".into(), Dependency::Version("0.12.0".into()));
+    dependencies.insert(
+        "serde".into(),
+        Dependency::Detailed {
+            version: "1".into(),
+            features: vec!["derive".into()],
+        },
+    );
+
+    let mut docs = BTreeMap::new();
+    docs.insert("features".into(), "all".into());
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert("docs.rs".into(), docs);
+
+    Manifest {
+        package: Package {
+            name: "taplo".into(),
+            version: "0.6.3".into(),
+            authors: vec!["tamasfe".into()],
+            description: None,
+            metadata,
+        },
+        dependencies,
+        bin: vec![
+            Target {
+                name: "taplo".into(),
+                path: "bin/taplo.rs".into(),
+            },
+            Target {
+                name: "taplo-lsp".into(),
+                path: "bin/lsp.rs".into(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn serialize_struct() {
+    let toml = ser::to_string(&manifest()).unwrap();
+
+    assert_eq!(
+        toml,
+        r#"[package]
+name = "taplo"
+version = "0.6.3"
+authors = ["tamasfe"]
+
+[package.metadata]
+"docs.rs" = { features = "all" }
+
+[dependencies]
+logos = "0.12.0"
+serde = { version = "1", features = ["derive"] }
+
+[[bin]]
+name = "taplo"
+path = "bin/taplo.rs"
+
+[[bin]]
+name = "taplo-lsp"
+path = "bin/lsp.rs"
+"#
+    );
+}
+
+#[test]
+fn serialize_with_options() {
+    let toml = ser::to_string_with_options(
+        &manifest(),
+        formatter::Options {
+            indent_tables: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert!(toml.contains("\n  [package.metadata]\n"));
+}
+
+#[test]
+fn serialize_value_roundtrip() {
+    let src = r#"
+string = "with \"quotes\"\n and \ttabs"
+"quoted key" = -2
+float = 1e100
+nan = nan
+array = [1, { a = 2 }, [3]]
+empty = {}
+
+[[tables]]
+a = 1
+
+[[tables]]
+b = 2
+"#;
+
+    let value = Value::try_from(parse(src).into_dom()).unwrap();
+    let toml = ser::value_to_string(&value, Default::default()).unwrap();
+
+    let dom = parse(&toml).into_dom();
+    assert!(dom.errors().is_empty(), "{}", toml);
+
+    let new_value = Value::try_from(dom).unwrap();
+
+    // NaN != NaN
+    let without_nan = |v: Value| match v {
+        Value::Map(mut m) => {
+            assert!(m.remove("nan").unwrap().into_f64().unwrap().is_nan());
+            m
+        }
+        _ => unreachable!(),
+    };
+
+    assert_eq!(without_nan(value), without_nan(new_value));
+}
+
+#[test]
+fn serialize_dates_roundtrip() {
+    let src = r#"
+offset = 1979-05-27T00:32:00.999999-07:00
+local = 1979-05-27T07:32:00.5
+local_whole = 1979-05-27T07:32:00
+date = 1979-05-27
+time = 00:32:00.000001
+"#;
+
+    let value = Value::try_from(parse(src).into_dom()).unwrap();
+    let toml = ser::value_to_string(&value, Default::default()).unwrap();
+
+    assert!(
+        toml.contains("local = 1979-05-27T07:32:00.500\n"),
+        "{}",
+        toml
+    );
+    assert!(
+        toml.contains("local_whole = 1979-05-27T07:32:00\n"),
+        "{}",
+        toml
+    );
+    assert!(toml.contains("time = 00:32:00.000001\n"), "{}", toml);
+
+    let dom = parse(&toml).into_dom();
+    assert!(dom.errors().is_empty(), "{}", toml);
+
+    assert_eq!(value, Value::try_from(dom).unwrap());
+}
+
+#[test]
+fn serialize_errors() {
+    assert_eq!(ser::to_string(&1), Err(Error::RootNotTable));
+    assert_eq!(ser::to_string(&vec![1, 2]), Err(Error::RootNotTable));
+
+    let mut none_in_array = BTreeMap::new();
+    none_in_array.insert("value", vec![Some(1), None]);
+    assert_eq!(ser::to_string(&none_in_array), Err(Error::UnsupportedNone));
+
+    let mut bad_key = BTreeMap::new();
+    bad_key.insert(vec![1], 1);
+    assert_eq!(ser::to_string(&bad_key), Err(Error::KeyNotString));
+
+    let mut too_large = BTreeMap::new();
+    too_large.insert("value", i64::MAX as u64);
+    assert!(ser::to_string(&too_large).is_ok());
+    too_large.insert("value", i64::MAX as u64 + 1);
+    assert_eq!(
+        ser::to_string(&too_large),
+        Err(Error::IntegerOutOfRange(i64::MAX as u64 + 1))
+    );
+
+    let mut too_large = crate::value::Map::new();
+    too_large.insert(
+        "value".into(),
+        Value::Array(vec![Value::UnsizedInteger(u64::MAX)]),
+    );
+    assert_eq!(
+        ser::value_to_string(&Value::Map(too_large), Default::default()),
+        Err(Error::IntegerOutOfRange(u64::MAX))
+    );
+}
@@ -2,7 +2,7 @@
 
 use rowan::{TextRange, TextSize};
 
-use std::collections::BTreeMap;
+use std::convert::TryFrom;
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
 pub struct Position {
@@ -36,19 +36,69 @@ pub struct CharacterRange(u64, u64);
 
 /// A mapper that translates offset:length bytes to
 /// 1-based line:row characters.
-#[derive(Debug, Clone)]
+///
+/// Only the lines and the characters that are not a single
+/// byte are stored, so the mapper can be updated after
+/// an edit without going through the entire source.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Mapper {
-    /// Mapping offsets to positions.
-    offset_to_position: BTreeMap<TextSize, Position>,
+    lines: Vec<Line>,
+
+    /// Whether positions use UTF-16 character sizes.
+    utf16: bool,
 
-    /// Mapping positions to offsets.
-    position_to_offset: BTreeMap<Position, TextSize>,
+    /// The first line and character, either 0 or 1.
+    base: u64,
+}
 
-    /// Line count.
-    lines: usize,
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Line {
+    /// Offset of the first byte of the line.
+    start: TextSize,
 
-    /// Ending position.
-    end: Position,
+    /// Length of the line in bytes, including the line ending.
+    len: TextSize,
+
+    /// Characters whose size in the positions
+    /// differs from their byte length.
+    wide: Vec<WideChar>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct WideChar {
+    /// Offset of the character relative to the start of the line.
+    offset: TextSize,
+
+    /// Length of the character in bytes.
+    len: TextSize,
+
+    /// Size of the character in positions.
+    size: TextSize,
+}
+
+impl Line {
+    fn end(&self) -> TextSize {
+        self.start + self.len
+    }
+
+    /// The character count of the line,
+    /// or of the part of it before the given offset.
+    fn characters(&self, before: TextSize) -> Option<TextSize> {
+        let mut characters = before;
+
+        for c in &self.wide {
+            if c.offset + c.len <= before {
+                characters = characters - c.len + c.size;
+            } else if c.offset < before {
+                // The offset is inside the character.
+                return None;
+            } else {
+                break;
+            }
+        }
+
+        Some(characters)
+    }
 }
 
 impl Mapper {
@@ -66,7 +116,37 @@ impl Mapper {
     }
 
     pub fn offset(&self, position: Position) -> Option<TextSize> {
-        self.position_to_offset.get(&position).copied()
+        let line_idx = usize::try_from(position.line.checked_sub(self.base)?).ok()?;
+        let character = u32::try_from(position.character.checked_sub(self.base)?).ok()?;
+        let character = TextSize::from(character);
+
+        let line = self.lines.get(line_idx)?;
+
+        // Bytes over the character count so far.
+        let mut shift = TextSize::from(0);
+
+        for c in &line.wide {
+            let c_start = c.offset - shift;
+
+            if c_start + c.size <= character {
+                shift += c.len - c.size;
+            } else if c_start < character {
+                // The position is inside a character.
+                return None;
+            } else {
+                break;
+            }
+        }
+
+        let offset = character + shift;
+        let is_last = line_idx + 1 == self.lines.len();
+
+        // Only the last line has a position after its last character.
+        if offset < line.len || (is_last && offset == line.len) {
+            Some(line.start + offset)
+        } else {
+            None
+        }
     }
 
     pub fn text_range(&self, range: Range) -> Option<TextRange> {
@@ -75,7 +155,28 @@ impl Mapper {
     }
 
     pub fn position(&self, offset: TextSize) -> Option<Position> {
-        self.offset_to_position.get(&offset).copied()
+        if offset > self.lines.last()?.end() {
+            return None;
+        }
+
+        let line_idx = self.line_index(offset);
+        let line = &self.lines[line_idx];
+
+        let mut before = offset - line.start;
+
+        // Every byte of a character is mapped to the position of the character.
+        if let Some(c) = line
+            .wide
+            .iter()
+            .find(|c| c.offset < before && before < c.offset + c.len)
+        {
+            before = c.offset;
+        }
+
+        Some(Position {
+            line: self.base + line_idx as u64,
+            character: self.base + u64::from(u32::from(line.characters(before)?)),
+        })
     }
 
     pub fn range(&self, range: TextRange) -> Option<Range> {
@@ -83,12 +184,8 @@ impl Mapper {
             .and_then(|start| self.position(range.end()).map(|end| Range { start, end }))
     }
 
-    pub fn mappings(&self) -> (&BTreeMap<TextSize, Position>, &BTreeMap<Position, TextSize>) {
-        (&self.offset_to_position, &self.position_to_offset)
-    }
-
     pub fn line_count(&self) -> usize {
-        self.lines
+        self.base as usize + self.lines.len() - 1
     }
 
     pub fn all_range(&self) -> Range {
@@ -97,58 +194,142 @@ impl Mapper {
                 line: 0,
                 character: 0,
             },
-            end: self.end,
+            end: self.end(),
         }
     }
 
-    fn new_impl(source: &str, utf16: bool, base: u64) -> Self {
-        let mut offset_to_position = BTreeMap::new();
-        let mut position_to_offset = BTreeMap::new();
+    /// Updates the mapper after the given range of the source
+    /// was replaced with `text`.
+    ///
+    /// Only the lines touched by the edit are changed,
+    /// the lines after them are shifted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn edit(&mut self, range: TextRange, text: &str) {
+        let first = self.line_index(range.start());
+        let last = self.line_index(range.end());
+
+        let first_line = &self.lines[first];
+        let prefix_len = range.start() - first_line.start;
+        let prefix_wide: Vec<WideChar> = first_line
+            .wide
+            .iter()
+            .copied()
+            .filter(|c| c.offset < prefix_len)
+            .collect();
+        let start = first_line.start;
+
+        let last_line = &self.lines[last];
+        let suffix_offset = range.end() - last_line.start;
+        let suffix_len = last_line.len - suffix_offset;
+        let suffix_wide: Vec<WideChar> = last_line
+            .wide
+            .iter()
+            .filter(|c| c.offset >= suffix_offset)
+            .map(|c| WideChar {
+                offset: c.offset - suffix_offset,
+                ..*c
+            })
+            .collect();
+
+        let mut lines = Self::lines(text, self.utf16);
+
+        let head = &mut lines[0];
+        head.wide = prefix_wide
+            .into_iter()
+            .chain(head.wide.iter().map(|c| WideChar {
+                offset: c.offset + prefix_len,
+                ..*c
+            }))
+            .collect();
+        head.len += prefix_len;
+
+        let tail = lines.last_mut().unwrap();
+        let tail_len = tail.len;
+        tail.wide.extend(suffix_wide.into_iter().map(|c| WideChar {
+            offset: c.offset + tail_len,
+            ..c
+        }));
+        tail.len += suffix_len;
+
+        let mut line_start = start;
+        for line in &mut lines {
+            line.start = line_start;
+            line_start += line.len;
+        }
 
-        let mut line: u64 = base;
-        let mut character: u64 = base;
-        let mut last_offset = 0;
+        let new_count = lines.len();
+        self.lines.splice(first..=last, lines);
 
-        for c in source.chars() {
-            let new_offset = last_offset + c.len_utf8();
+        let old_end = range.end();
+        let new_end = range.start() + TextSize::of(text);
 
-            let character_size = if utf16 { c.len_utf16() } else { 1 };
+        for line in &mut self.lines[first + new_count..] {
+            line.start = line.start - old_end + new_end;
+        }
+    }
 
-            offset_to_position.extend(
-                (last_offset..new_offset)
-                    .map(|b| (TextSize::from(b as u32), Position { line, character })),
-            );
+    fn end(&self) -> Position {
+        let line = self.lines.last().unwrap();
 
-            position_to_offset.extend(
-                (last_offset..new_offset)
-                    .map(|b| (Position { line, character }, TextSize::from(b as u32))),
-            );
+        Position {
+            line: self.base + self.lines.len() as u64 - 1,
+            character: self.base + u64::from(u32::from(line.characters(line.len).unwrap())),
+        }
+    }
 
-            last_offset = new_offset;
+    /// The index of the line that contains the offset.
+    fn line_index(&self, offset: TextSize) -> usize {
+        self.lines.partition_point(|l| l.start <= offset) - 1
+    }
+
+    fn lines(source: &str, utf16: bool) -> Vec<Line> {
+        let mut lines = Vec::new();
+
+        let mut line = Line {
+            start: 0.into(),
+            len: 0.into(),
+            wide: Vec::new(),
+        };
+
+        for c in source.chars() {
+            let len = TextSize::of(c);
+            let size = TextSize::from(if utf16 { c.len_utf16() as u32 } else { 1 });
+
+            if len != size {
+                line.wide.push(WideChar {
+                    offset: line.len,
+                    len,
+                    size,
+                });
+            }
+
+            line.len += len;
 
-            character += character_size as u64;
             if c == '\n' {
-                // LF is at the start of each line.
-                line += 1;
-                character = base;
+                let start = line.end();
+                lines.push(std::mem::replace(
+                    &mut line,
+                    Line {
+                        start,
+                        len: 0.into(),
+                        wide: Vec::new(),
+                    },
+                ));
             }
         }
 
-        // Last imaginary character.
-        offset_to_position.insert(
-            TextSize::from(last_offset as u32),
-            Position { line, character },
-        );
-        position_to_offset.insert(
-            Position { line, character },
-            TextSize::from(last_offset as u32),
-        );
+        lines.push(line);
+        lines
+    }
 
+    fn new_impl(source: &str, utf16: bool, base: u64) -> Self {
         Self {
-            offset_to_position,
-            position_to_offset,
-            lines: line as usize,
-            end: Position { line, character },
+            lines: Self::lines(source, utf16),
+            utf16,
+            base,
         }
     }
 }
@@ -215,7 +396,7 @@ line-3"#;
 
     let mapper = Mapper::new_utf16(s1, false);
 
-    assert!(s1.len() == mapper.mappings().0.len() - 1);
+    assert!(mapper.line_count() == 2);
 
     assert!(
         mapper.position(0.into()).unwrap()
@@ -235,3 +416,81 @@ line-3"#;
             }
     )
 }
+
+#[cfg(test)]
+#[test]
+fn test_mapper_positions() {
+    let source = "a = \"é😀\"\r\nb = 2\n";
+
+    let mapper = Mapper::new_utf16(source, false);
+
+    assert_eq!(mapper.position(5.into()), Some(Position::new(0, 5)));
+    // Every byte of a character has the same position.
+    assert_eq!(mapper.position(6.into()), Some(Position::new(0, 5)));
+    assert_eq!(mapper.position(7.into()), Some(Position::new(0, 6)));
+    assert_eq!(mapper.position(11.into()), Some(Position::new(0, 8)));
+    assert_eq!(mapper.position(14.into()), Some(Position::new(1, 0)));
+    assert_eq!(mapper.position(20.into()), Some(Position::new(2, 0)));
+    assert_eq!(mapper.position(21.into()), None);
+
+    assert_eq!(mapper.offset(Position::new(0, 5)), Some(5.into()));
+    assert_eq!(mapper.offset(Position::new(0, 6)), Some(7.into()));
+    assert_eq!(mapper.offset(Position::new(0, 8)), Some(11.into()));
+    assert_eq!(mapper.offset(Position::new(0, 10)), Some(13.into()));
+    // Inside a surrogate pair.
+    assert_eq!(mapper.offset(Position::new(0, 7)), None);
+    assert_eq!(mapper.offset(Position::new(0, 11)), None);
+    assert_eq!(mapper.offset(Position::new(2, 0)), Some(20.into()));
+    assert_eq!(mapper.offset(Position::new(2, 1)), None);
+    assert_eq!(mapper.offset(Position::new(3, 0)), None);
+
+    assert_eq!(mapper.line_count(), 2);
+    assert_eq!(mapper.all_range().end, Position::new(2, 0));
+
+    let mapper = Mapper::new_utf8(source, true);
+    assert_eq!(mapper.position(11.into()), Some(Position::new(1, 8)));
+    assert_eq!(mapper.offset(Position::new(1, 8)), Some(11.into()));
+    assert_eq!(mapper.offset(Position::new(1, 9)), Some(12.into()));
+}
+
+#[cfg(test)]
+#[test]
+fn test_mapper_edit() {
+    let source = "a = \"é😀\"\nb = [\n  \"ü\",\n]\n\nc = \"😀\"";
+
+    let edits: &[(u32, u32, &str)] = &[
+        (0, 0, ""),
+        (0, 0, "x"),
+        (4, 4, "😀"),
+        (5, 7, ""),
+        (5, 11, "ö\n😀\n"),
+        (11, 12, "\n"),
+        (7, 20, ""),
+        (12, 32, "é"),
+        (0, 40, ""),
+        (0, 40, "\n\n"),
+        (33, 33, "d = 1\n"),
+        (40, 40, "é"),
+        (35, 40, "\n"),
+    ];
+
+    for &(start, end, text) in edits {
+        let range = TextRange::new(start.into(), end.into());
+
+        let mut edited = source.to_string();
+        edited.replace_range(std::ops::Range::<usize>::from(range), text);
+
+        for &utf16 in &[true, false] {
+            let mut mapper = Mapper::new_impl(source, utf16, 0);
+            mapper.edit(range, text);
+
+            assert_eq!(
+                mapper,
+                Mapper::new_impl(&edited, utf16, 0),
+                "{:?} {:?}",
+                range,
+                text
+            );
+        }
+    }
+}
@@ -53,6 +53,28 @@ pub enum Escape {
 }
 use Escape::*;
 
+/// Escape a string so that it can be used
+/// in a basic string, the quotes are not included.
+pub fn escape(s: &str) -> String {
+    let mut new_s = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => new_s += r#"\""#,
+            '\\' => new_s += r#"\\"#,
+            '\n' => new_s += r#"\n"#,
+            '\r' => new_s += r#"\r"#,
+            '\t' => new_s += r#"\t"#,
+            '\u{8}' => new_s += r#"\b"#,
+            '\u{c}' => new_s += r#"\f"#,
+            c if c.is_control() => new_s += &format!("\\u{:04X}", c as u32),
+            c => new_s.push(c),
+        }
+    }
+
+    new_s
+}
+
 /// Unescape all supported sequences found in [Escape](Escape).
 ///
 /// If it fails, the index of failure is returned.
@@ -6,6 +6,7 @@ pub mod syntax;
 pub mod coords;
 
 pub use escape::check_escape;
+pub use escape::escape;
 pub use escape::unescape;
 
 pub(crate) mod allowed_chars {
@@ -100,6 +101,20 @@ pub(crate) mod allowed_chars {
     }
 }
 
+/// Returns the key unchanged if it can be used as a bare key,
+/// otherwise quotes and escapes it.
+pub fn quote_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        format!(r#""{}""#, escape(key))
+    }
+}
+
 pub trait StrExt {
     fn strip_quotes(self) -> Self;
 }
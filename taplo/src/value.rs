@@ -44,6 +44,47 @@ pub enum Date {
     LocalTime(time::Time),
 }
 
+#[cfg(any(feature = "time", feature = "chrono"))]
+impl core::fmt::Display for Date {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "chrono")]
+            Date::OffsetDateTime(dt) => f.write_str(&dt.to_rfc3339()),
+            #[cfg(feature = "time")]
+            Date::OffsetDateTime(dt) => f.write_str(&dt.format(time::Format::Rfc3339)),
+            #[cfg(feature = "chrono")]
+            Date::LocalDateTime(dt) => f.write_str(&dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            #[cfg(feature = "time")]
+            Date::LocalDateTime(dt) => {
+                f.write_str(&dt.format("%Y-%m-%dT%H:%M:%S"))?;
+                write_fraction(f, dt.nanosecond())
+            }
+            Date::LocalDate(date) => f.write_str(&date.format("%Y-%m-%d").to_string()),
+            #[cfg(feature = "chrono")]
+            Date::LocalTime(time) => f.write_str(&time.format("%H:%M:%S%.f").to_string()),
+            #[cfg(feature = "time")]
+            Date::LocalTime(time) => {
+                f.write_str(&time.format("%H:%M:%S"))?;
+                write_fraction(f, time.nanosecond())
+            }
+        }
+    }
+}
+
+/// Writes the fractional seconds without trailing zeros, if any.
+#[cfg(feature = "time")]
+fn write_fraction(f: &mut core::fmt::Formatter<'_>, nanoseconds: u32) -> core::fmt::Result {
+    if nanoseconds == 0 {
+        return Ok(());
+    }
+
+    write!(
+        f,
+        ".{}",
+        format!("{:09}", nanoseconds).trim_end_matches('0')
+    )
+}
+
 /// Contains all possible value types in a TOML document.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -345,11 +386,11 @@ impl TryFrom<dom::DateNode> for Value {
             return Ok(Value::Date(Date::OffsetDateTime(d)));
         }
 
-        if let Ok(d) = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%dT%H:%M:%S") {
+        if let Ok(d) = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%dT%H:%M:%S%.f") {
             return Ok(Value::Date(Date::LocalDateTime(d)));
         }
 
-        if let Ok(d) = NaiveTime::parse_from_str(&date_str, "%H:%M:%S") {
+        if let Ok(d) = NaiveTime::parse_from_str(&date_str, "%H:%M:%S%.f") {
             return Ok(Value::Date(Date::LocalTime(d)));
         }
 
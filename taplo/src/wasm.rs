@@ -1,7 +1,7 @@
-use crate::{formatter, parser::parse, value::Value};
-use schemars::schema::RootSchema;
+use crate::schema::util::schema_from_slice;
+use crate::{code::Code, formatter, parser::parse, ser, value::Value};
 use serde_crate::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::TryFrom};
+use std::convert::TryFrom;
 use verify::{
     serde::{KeySpans, Spanned},
     Verifier,
@@ -58,6 +58,8 @@ pub struct Range {
 #[serde(crate = "serde_crate")]
 pub struct LintError {
     pub range: Option<Range>,
+    /// The stable code of the error, e.g. `TOML001`.
+    pub code: String,
     pub error: String,
 }
 
@@ -86,6 +88,7 @@ pub fn lint(
                         end: err.range.end().into(),
                     }
                     .into(),
+                    code: err.code.id().into(),
                     error: err.message,
                 })
                 .collect(),
@@ -102,6 +105,7 @@ pub fn lint(
                 .into_iter()
                 .map(|err| LintError {
                     range: None,
+                    code: err.code().id().into(),
                     error: err.to_string(),
                 })
                 .collect(),
@@ -110,7 +114,7 @@ pub fn lint(
     }
 
     if let Some(schema_json) = schema_json {
-        let schema: RootSchema = serde_json::from_str(&schema_json)
+        let schema = schema_from_slice(schema_json.as_bytes())
             .map_err(|err| JsValue::from_str(&format!("invalid schema: {}", err)))?;
 
         if schema_key_spans {
@@ -123,6 +127,7 @@ pub fn lint(
                         .iter()
                         .map(|err| LintError {
                             range: None,
+                            code: Code::Schema.id().into(),
                             error: format!(
                                 "{err}{span}",
                                 err = &err,
@@ -146,6 +151,7 @@ pub fn lint(
                                 start: s.0.start().into(),
                                 end: s.0.end().into(),
                             }),
+                            code: Code::Schema.id().into(),
                             error: err.value.to_string(),
                         })
                         .collect(),
@@ -158,22 +164,14 @@ pub fn lint(
     Ok(JsValue::from_serde(&LintResult::default()).unwrap())
 }
 
-// Required because JSON Keys can be in any order,
-// and toml-rs cannot handle it by default.
-#[derive(Default, Serialize, Deserialize)]
-#[serde(crate = "serde_crate")]
-#[serde(transparent)]
-struct JsonVal(
-    #[serde(serialize_with = "toml::ser::tables_last")] HashMap<String, serde_json::Value>,
-);
-
 #[wasm_bindgen]
 pub fn from_json(json_source: &str) -> Result<JsValue, JsValue> {
-    let v: JsonVal =
+    let v: Value =
         serde_json::from_str(json_source).map_err(|err| JsValue::from_str(&format!("{}", err)))?;
 
     Ok(JsValue::from_str(
-        &toml::to_string_pretty(&v).map_err(|err| JsValue::from_str(&format!("{}", err)))?,
+        &ser::value_to_string(&v, formatter::Options::default())
+            .map_err(|err| JsValue::from_str(&format!("{}", err)))?,
     ))
 }
 